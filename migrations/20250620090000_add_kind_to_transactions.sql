@@ -0,0 +1,11 @@
+-- Add an explicit kind to transactions so amounts can be signed by it
+ALTER TABLE transactions ADD COLUMN kind VARCHAR(20);
+
+-- Existing rows were only ever recorded with the amount's own sign
+UPDATE transactions SET kind = CASE WHEN amount < 0 THEN 'EXPENSE' ELSE 'INCOME' END;
+
+ALTER TABLE transactions ALTER COLUMN kind SET NOT NULL;
+ALTER TABLE transactions ADD CONSTRAINT transactions_kind_check
+    CHECK (kind IN ('INCOME', 'EXPENSE', 'TRANSFER', 'ADJUSTMENT'));
+
+CREATE INDEX idx_transactions_user_id_kind ON transactions(user_id, kind);
@@ -0,0 +1,8 @@
+-- Track when a transaction happened separately from when it was recorded
+ALTER TABLE transactions ADD COLUMN occurred_on DATE NOT NULL DEFAULT CURRENT_DATE;
+ALTER TABLE transactions ADD COLUMN occurred_time TIME;
+
+-- Until now the insert time was the only timestamp a transaction had
+UPDATE transactions SET occurred_on = created_at::date WHERE created_at IS NOT NULL;
+
+CREATE INDEX idx_transactions_user_id_occurred_on ON transactions(user_id, occurred_on);
@@ -0,0 +1,32 @@
+-- Create accounts table
+CREATE TABLE accounts (
+    id SERIAL PRIMARY KEY,
+    user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    name VARCHAR(100) NOT NULL,
+    account_type VARCHAR(20) NOT NULL CHECK (account_type IN ('CHECKING', 'SAVINGS', 'CREDIT_CARD', 'CASH')),
+    currency CHAR(3) NOT NULL DEFAULT 'USD',
+    opening_balance DECIMAL(12,2) NOT NULL DEFAULT 0,
+    archived BOOLEAN NOT NULL DEFAULT FALSE,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE (user_id, name)
+);
+
+CREATE INDEX idx_accounts_user_id ON accounts(user_id);
+
+CREATE TRIGGER update_accounts_updated_at
+    BEFORE UPDATE ON accounts
+    FOR EACH ROW
+    EXECUTE FUNCTION update_updated_at_column();
+
+-- Every transaction belongs to an account; existing history moves into a default one per user
+ALTER TABLE transactions ADD COLUMN account_id INTEGER REFERENCES accounts(id) ON DELETE RESTRICT;
+
+INSERT INTO accounts (user_id, name, account_type)
+SELECT DISTINCT user_id, 'Main', 'CHECKING' FROM transactions;
+
+UPDATE transactions t SET account_id = a.id FROM accounts a WHERE a.user_id = t.user_id;
+
+ALTER TABLE transactions ALTER COLUMN account_id SET NOT NULL;
+
+CREATE INDEX idx_transactions_account_id ON transactions(account_id);
@@ -0,0 +1,6 @@
+-- A transfer is stored as two TRANSFER transactions, one per account, pointing at each other.
+-- Deleting either leg removes its counterpart too.
+ALTER TABLE transactions
+    ADD COLUMN counterpart_id INTEGER REFERENCES transactions(id) ON DELETE CASCADE;
+
+CREATE UNIQUE INDEX idx_transactions_counterpart_id ON transactions(counterpart_id);
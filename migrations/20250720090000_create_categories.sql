@@ -0,0 +1,82 @@
+-- Create categories table; categories nest one level deep (e.g. Food > Groceries)
+CREATE TABLE categories (
+    id SERIAL PRIMARY KEY,
+    user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    parent_id INTEGER REFERENCES categories(id) ON DELETE RESTRICT,
+    name VARCHAR(50) NOT NULL,
+    kind VARCHAR(10) NOT NULL CHECK (kind IN ('INCOME', 'EXPENSE')),
+    icon VARCHAR(50),
+    color CHAR(7),
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE UNIQUE INDEX categories_user_id_kind_parent_id_name_key
+    ON categories (user_id, kind, COALESCE(parent_id, 0), LOWER(name));
+CREATE INDEX idx_categories_parent_id ON categories(parent_id);
+
+CREATE TRIGGER update_categories_updated_at
+    BEFORE UPDATE ON categories
+    FOR EACH ROW
+    EXECUTE FUNCTION update_updated_at_column();
+
+-- Existing users get the same default set new users are seeded with
+INSERT INTO categories (user_id, name, kind, icon, color)
+SELECT u.id, d.name, d.kind, d.icon, d.color
+FROM users u
+CROSS JOIN (VALUES
+    ('Food', 'EXPENSE', 'utensils', '#E67E22'),
+    ('Housing', 'EXPENSE', 'home', '#8E44AD'),
+    ('Transportation', 'EXPENSE', 'car', '#2980B9'),
+    ('Health', 'EXPENSE', 'heart', '#C0392B'),
+    ('Entertainment', 'EXPENSE', 'film', '#16A085'),
+    ('Shopping', 'EXPENSE', 'shopping-bag', '#D35400'),
+    ('Other Expenses', 'EXPENSE', 'more-horizontal', '#7F8C8D'),
+    ('Salary', 'INCOME', 'briefcase', '#27AE60'),
+    ('Other Income', 'INCOME', 'plus-circle', '#2ECC71')
+) AS d(name, kind, icon, color);
+
+INSERT INTO categories (user_id, parent_id, name, kind)
+SELECT p.user_id, p.id, d.name, p.kind
+FROM categories p
+JOIN (VALUES
+    ('Food', 'Groceries'),
+    ('Food', 'Restaurants'),
+    ('Housing', 'Rent'),
+    ('Housing', 'Utilities'),
+    ('Transportation', 'Fuel'),
+    ('Transportation', 'Public Transit')
+) AS d(parent, name) ON d.parent = p.name;
+
+-- Every distinct category string becomes a category, matched case- and whitespace-insensitively.
+-- Income transactions get INCOME categories, everything else EXPENSE; transfers are not categorized.
+ALTER TABLE transactions ADD COLUMN category_id INTEGER REFERENCES categories(id) ON DELETE RESTRICT;
+
+WITH used AS (
+    SELECT
+        user_id,
+        TRIM(category) as name,
+        CASE WHEN kind = 'INCOME' THEN 'INCOME' ELSE 'EXPENSE' END as kind
+    FROM transactions
+    WHERE kind <> 'TRANSFER' AND TRIM(category) <> ''
+)
+INSERT INTO categories (user_id, name, kind)
+SELECT u.user_id, MIN(u.name), u.kind
+FROM used u
+WHERE NOT EXISTS (
+    SELECT 1 FROM categories c
+    WHERE c.user_id = u.user_id AND c.kind = u.kind AND LOWER(c.name) = LOWER(u.name)
+)
+GROUP BY u.user_id, LOWER(u.name), u.kind;
+
+UPDATE transactions t SET category_id = (
+    SELECT MIN(c.id) FROM categories c
+    WHERE c.user_id = t.user_id
+        AND c.kind = CASE WHEN t.kind = 'INCOME' THEN 'INCOME' ELSE 'EXPENSE' END
+        AND LOWER(c.name) = LOWER(TRIM(t.category))
+)
+WHERE t.kind <> 'TRANSFER';
+
+ALTER TABLE transactions DROP COLUMN category;
+
+CREATE INDEX idx_transactions_category_id ON transactions(category_id);
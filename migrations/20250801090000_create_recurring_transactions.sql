@@ -0,0 +1,48 @@
+-- Create recurring transaction templates with an RRULE-like schedule
+CREATE TABLE recurring_transactions (
+    id SERIAL PRIMARY KEY,
+    user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    account_id INTEGER NOT NULL REFERENCES accounts(id) ON DELETE CASCADE,
+    category_id INTEGER REFERENCES categories(id) ON DELETE SET NULL,
+    kind VARCHAR(20) NOT NULL CHECK (kind IN ('INCOME', 'EXPENSE', 'ADJUSTMENT')),
+    amount DECIMAL(12,2) NOT NULL CHECK (amount > 0),
+    description VARCHAR(255) NOT NULL DEFAULT '',
+    frequency VARCHAR(10) NOT NULL CHECK (frequency IN ('DAILY', 'WEEKLY', 'MONTHLY', 'YEARLY')),
+    interval INTEGER NOT NULL DEFAULT 1 CHECK (interval > 0),
+    start_date DATE NOT NULL,
+    until DATE,
+    count INTEGER CHECK (count > 0),
+    -- Every occurrence up to this date has been posted or skipped
+    materialized_through DATE,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    CHECK (until IS NULL OR count IS NULL),
+    CHECK (until IS NULL OR until >= start_date)
+);
+
+CREATE INDEX idx_recurring_transactions_user_id ON recurring_transactions(user_id);
+
+CREATE TRIGGER update_recurring_transactions_updated_at
+    BEFORE UPDATE ON recurring_transactions
+    FOR EACH ROW
+    EXECUTE FUNCTION update_updated_at_column();
+
+-- One row per occurrence that was modified, skipped or posted. The unique key is what makes
+-- posting idempotent: an occurrence is claimed by moving it to POSTED exactly once.
+CREATE TABLE recurring_occurrences (
+    id SERIAL PRIMARY KEY,
+    recurring_id INTEGER NOT NULL REFERENCES recurring_transactions(id) ON DELETE CASCADE,
+    occurrence_date DATE NOT NULL,
+    status VARCHAR(10) NOT NULL CHECK (status IN ('MODIFIED', 'SKIPPED', 'POSTED')),
+    amount DECIMAL(12,2) CHECK (amount > 0),
+    description VARCHAR(255),
+    transaction_id INTEGER REFERENCES transactions(id) ON DELETE SET NULL,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE (recurring_id, occurrence_date)
+);
+
+CREATE TRIGGER update_recurring_occurrences_updated_at
+    BEFORE UPDATE ON recurring_occurrences
+    FOR EACH ROW
+    EXECUTE FUNCTION update_updated_at_column();
@@ -0,0 +1,7 @@
+-- Identifier a bank gave a transaction (e.g. an OFX FITID), kept when it is imported from a
+-- statement so importing the same statement again doesn't record it twice.
+ALTER TABLE transactions ADD COLUMN external_id VARCHAR(255);
+
+CREATE UNIQUE INDEX transactions_account_id_external_id_key
+    ON transactions(account_id, external_id)
+    WHERE external_id IS NOT NULL;
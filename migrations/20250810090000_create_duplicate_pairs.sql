@@ -0,0 +1,22 @@
+-- Pairs of transactions suspected to record the same movement of money. `original_id` is
+-- always the older of the two. Dismissed pairs are kept so they are not flagged again;
+-- merging deletes one of the transactions and the pair with it.
+CREATE TABLE duplicate_pairs (
+    id SERIAL PRIMARY KEY,
+    user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    original_id INTEGER NOT NULL REFERENCES transactions(id) ON DELETE CASCADE,
+    duplicate_id INTEGER NOT NULL REFERENCES transactions(id) ON DELETE CASCADE,
+    status VARCHAR(10) NOT NULL DEFAULT 'PENDING' CHECK (status IN ('PENDING', 'DISMISSED')),
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    CHECK (original_id < duplicate_id),
+    UNIQUE (original_id, duplicate_id)
+);
+
+CREATE INDEX idx_duplicate_pairs_user_id_status ON duplicate_pairs(user_id, status);
+CREATE INDEX idx_duplicate_pairs_duplicate_id ON duplicate_pairs(duplicate_id);
+
+CREATE TRIGGER update_duplicate_pairs_updated_at
+    BEFORE UPDATE ON duplicate_pairs
+    FOR EACH ROW
+    EXECUTE FUNCTION update_updated_at_column();
@@ -0,0 +1,21 @@
+-- User-defined categorization rules. A rule applies when every one of its conditions
+-- (a JSON array such as [{"field": "description", "operator": "contains", "value": "UBER"}])
+-- matches a transaction; rules are tried in ascending priority.
+CREATE TABLE rules (
+    id SERIAL PRIMARY KEY,
+    user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    name VARCHAR(100) NOT NULL,
+    priority INTEGER NOT NULL DEFAULT 0,
+    conditions JSONB NOT NULL CHECK (jsonb_typeof(conditions) = 'array'),
+    category_id INTEGER REFERENCES categories(id) ON DELETE SET NULL,
+    enabled BOOLEAN NOT NULL DEFAULT TRUE,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX idx_rules_user_id_priority ON rules(user_id, priority);
+
+CREATE TRIGGER update_rules_updated_at
+    BEFORE UPDATE ON rules
+    FOR EACH ROW
+    EXECUTE FUNCTION update_updated_at_column();
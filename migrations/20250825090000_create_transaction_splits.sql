@@ -0,0 +1,27 @@
+-- Lines a transaction is split into, each with its own category. Amounts are signed like
+-- the transaction's and add up to it; a split transaction has no category of its own.
+CREATE TABLE transaction_splits (
+    id SERIAL PRIMARY KEY,
+    transaction_id INTEGER NOT NULL REFERENCES transactions(id) ON DELETE CASCADE,
+    category_id INTEGER NOT NULL REFERENCES categories(id),
+    amount DECIMAL(12,2) NOT NULL CHECK (amount <> 0),
+    memo VARCHAR(255),
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX idx_transaction_splits_transaction_id ON transaction_splits(transaction_id);
+CREATE INDEX idx_transaction_splits_category_id ON transaction_splits(category_id);
+
+-- What each transaction contributes to its categories: one line per split, or the whole
+-- transaction in its own category when it isn't split.
+CREATE VIEW transaction_lines AS
+SELECT
+    t.id AS transaction_id,
+    t.user_id,
+    t.account_id,
+    t.kind,
+    t.occurred_on,
+    COALESCE(s.category_id, t.category_id) AS category_id,
+    COALESCE(s.amount, t.amount) AS amount
+FROM transactions t
+LEFT JOIN transaction_splits s ON s.transaction_id = t.id;
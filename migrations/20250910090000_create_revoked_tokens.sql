@@ -0,0 +1,11 @@
+-- Access tokens revoked before they expire, by their `jti`. An entry is only needed until
+-- its token would have expired anyway, and is purged after that.
+CREATE TABLE revoked_tokens (
+    jti VARCHAR(36) PRIMARY KEY,
+    user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    expires_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    revoked_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX idx_revoked_tokens_expires_at ON revoked_tokens(expires_at);
+CREATE INDEX idx_refresh_tokens_expires_at ON refresh_tokens(expires_at);
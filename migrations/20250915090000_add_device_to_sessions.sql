@@ -0,0 +1,5 @@
+-- Where a session was started from, so that users can tell their sessions apart.
+-- `ip_address` is updated on each refresh along with `last_used_at`.
+ALTER TABLE sessions
+    ADD COLUMN user_agent VARCHAR(512),
+    ADD COLUMN ip_address VARCHAR(45);
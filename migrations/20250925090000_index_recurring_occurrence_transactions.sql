@@ -0,0 +1,2 @@
+-- Duplicate detection looks up whether a transaction was posted by a recurring schedule.
+CREATE INDEX idx_recurring_occurrences_transaction_id ON recurring_occurrences(transaction_id);
@@ -0,0 +1,9 @@
+-- Wrong second-factor codes sent to finish a login, kept while they count towards the
+-- user's limit. Counted per user, so that logging in again does not give more guesses.
+CREATE TABLE mfa_failures (
+    id SERIAL PRIMARY KEY,
+    user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX idx_mfa_failures_user_id_created_at ON mfa_failures(user_id, created_at);
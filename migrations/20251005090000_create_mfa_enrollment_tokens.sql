@@ -0,0 +1,14 @@
+-- Single-use permission for an admin who has to add an authenticator to do so while
+-- logging in, issued by another admin. The password alone is not enough, or whoever knows
+-- it could add their own authenticator. Kept only as SHA-256 hashes.
+CREATE TABLE mfa_enrollment_tokens (
+    id SERIAL PRIMARY KEY,
+    user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    token_hash CHAR(64) NOT NULL UNIQUE,
+    issued_by INTEGER REFERENCES users(id) ON DELETE SET NULL,
+    expires_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX idx_mfa_enrollment_tokens_user_id ON mfa_enrollment_tokens(user_id);
+CREATE INDEX idx_mfa_enrollment_tokens_expires_at ON mfa_enrollment_tokens(expires_at);
@@ -1,5 +1,9 @@
+use std::sync::Arc;
+
 use sqlx::PgPool;
 
+use crate::modules::attachments::storage::AttachmentStorage;
+
 pub mod modules;
 pub mod utils;
 pub mod middleware;
@@ -7,9 +11,10 @@ pub mod middleware;
 #[derive(Clone)]
 pub struct AppState {
     pub db: PgPool,
+    pub storage: Arc<dyn AttachmentStorage>,
 }
 
 // Re-export commonly used items
 pub use modules::*;
 pub use utils::*;
-pub use middleware::*; 
\ No newline at end of file
+pub use middleware::{jwt::JwtMiddleware, rbac::RbacMiddleware}; 
\ No newline at end of file
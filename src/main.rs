@@ -3,14 +3,30 @@ use actix_cors::Cors;
 use dotenv::dotenv;
 use sqlx::postgres::PgPoolOptions;
 use std::env;
+use std::time::Duration;
 use env_logger::Env;
 use log::LevelFilter;
 
 use finance_tracker::{
     modules::users::routes as user_routes,
     modules::transactions::routes as transaction_routes,
+    modules::accounts::routes as account_routes,
+    modules::categories::routes as category_routes,
+    modules::budgets::routes as budget_routes,
+    modules::recurring::routes as recurring_routes,
+    modules::imports::routes as import_routes,
+    modules::duplicates::routes as duplicate_routes,
+    modules::rules::routes as rule_routes,
+    modules::tags::routes as tag_routes,
+    modules::attachments::routes as attachment_routes,
+    modules::mfa::routes as mfa_routes,
+    modules::attachments::storage::storage_from_env,
+    modules::recurring::services::spawn_recurring_scheduler,
+    modules::users::services::{bootstrap_admin, spawn_token_purger},
     utils::not_found,
     utils::constants::api::API_PREFIX,
+    utils::constants::scheduler::RECURRING_INTERVAL_SECONDS,
+    utils::constants::auth::TOKEN_PURGE_INTERVAL_SECONDS,
     AppState,
 };
 
@@ -38,7 +54,19 @@ async fn main() -> std::io::Result<()> {
         .await
         .expect("Failed to create pool");
 
-    let app_state = web::Data::new(AppState { db: pool });
+    if let Ok(username) = env::var("BOOTSTRAP_ADMIN_USERNAME") {
+        bootstrap_admin(&pool, &username).await.expect("Failed to bootstrap the first admin");
+    }
+
+    let scheduler_interval = env::var("RECURRING_SCHEDULER_INTERVAL_SECONDS")
+        .ok()
+        .and_then(|value| value.parse::<u64>().ok())
+        .unwrap_or(RECURRING_INTERVAL_SECONDS);
+    spawn_recurring_scheduler(pool.clone(), Duration::from_secs(scheduler_interval));
+    spawn_token_purger(pool.clone(), Duration::from_secs(TOKEN_PURGE_INTERVAL_SECONDS));
+
+    let storage = storage_from_env().expect("Invalid attachment storage configuration");
+    let app_state = web::Data::new(AppState { db: pool, storage });
 
     log::info!("🚀 Server starting at http://localhost:{}{}", port, API_PREFIX);
 
@@ -59,6 +87,16 @@ async fn main() -> std::io::Result<()> {
                 web::scope(API_PREFIX)
                     .configure(user_routes::init)
                     .configure(transaction_routes::init)
+                    .configure(account_routes::init)
+                    .configure(category_routes::init)
+                    .configure(budget_routes::init)
+                    .configure(recurring_routes::init)
+                    .configure(import_routes::init)
+                    .configure(duplicate_routes::init)
+                    .configure(rule_routes::init)
+                    .configure(tag_routes::init)
+                    .configure(attachment_routes::init)
+                    .configure(mfa_routes::init)
             )
             .default_service(web::route().to(not_found::not_found))
     })
@@ -1,19 +1,35 @@
 use actix_web::{
     body::EitherBody,
     dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform},
+    web,
     Error,
     HttpMessage,
+    HttpRequest,
     HttpResponse,
+    ResponseError,
 };
 use futures::future::{ok, LocalBoxFuture, Ready};
-use std::task::{Context, Poll};
 use actix_web::http::StatusCode;
+use log::{error, warn};
+use std::rc::Rc;
+use crate::modules::users::services::is_access_token_revoked;
 use crate::utils::jwt::{validate_token, Claims};
 use crate::utils::response::GenericResponse;
 use crate::utils::errors::AppError;
+use crate::AppState;
 
+/// Authenticates requests by their bearer JWT, and rejects tokens that were revoked by
+/// logging out.
 pub struct JwtMiddleware;
 
+/// Returns the claims `JwtMiddleware` stored for this request.
+pub fn request_claims(req: &HttpRequest) -> Result<Claims, AppError> {
+    req.extensions().get::<Claims>().cloned().ok_or_else(|| {
+        error!("Claims not found in request extensions");
+        AppError::InternalServerError("Authentication state error".to_string())
+    })
+}
+
 impl<S, B> Transform<S, ServiceRequest> for JwtMiddleware
 where
     S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
@@ -27,12 +43,12 @@ where
     type Future = Ready<Result<Self::Transform, Self::InitError>>;
 
     fn new_transform(&self, service: S) -> Self::Future {
-        ok(JwtMiddlewareService { service })
+        ok(JwtMiddlewareService { service: Rc::new(service) })
     }
 }
 
 pub struct JwtMiddlewareService<S> {
-    service: S,
+    service: Rc<S>,
 }
 
 impl<S, B> Service<ServiceRequest> for JwtMiddlewareService<S>
@@ -71,10 +87,37 @@ where
         let token = auth_header.strip_prefix("Bearer ").unwrap_or("");
         match validate_token(token) {
             Ok(claims) => {
-                req.extensions_mut().insert(claims);
-                let fut = self.service.call(req);
+                let service = Rc::clone(&self.service);
                 Box::pin(async move {
-                    let res = fut.await?;
+                    let revoked = match req.app_data::<web::Data<AppState>>() {
+                        Some(state) => is_access_token_revoked(&state.db, &claims).await,
+                        None => Err(AppError::InternalServerError("Application state missing".to_string())),
+                    };
+                    match revoked {
+                        Ok(false) => {}
+                        Ok(true) => {
+                            warn!("Revoked token {} used by user {}", claims.jti, claims.user_id);
+                            let response = HttpResponse::Unauthorized()
+                                .json(GenericResponse {
+                                    status: StatusCode::UNAUTHORIZED.as_u16(),
+                                    data: None::<()>,
+                                    message: "Token has been revoked".to_string(),
+                                });
+                            return Ok(ServiceResponse::new(
+                                req.into_parts().0,
+                                response.map_into_right_body(),
+                            ));
+                        }
+                        Err(e) => {
+                            return Ok(ServiceResponse::new(
+                                req.into_parts().0,
+                                e.error_response().map_into_right_body(),
+                            ));
+                        }
+                    }
+
+                    req.extensions_mut().insert(claims);
+                    let res = service.call(req).await?;
                     Ok(res.map_into_left_body())
                 })
             }
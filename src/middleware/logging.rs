@@ -2,10 +2,8 @@ use actix_web::{
     body::EitherBody,
     dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform},
     Error,
-    HttpResponse,
 };
 use futures::future::{ok, LocalBoxFuture, Ready};
-use std::task::{Context, Poll};
 
 pub struct Logging;
 
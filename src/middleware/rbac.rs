@@ -6,6 +6,7 @@ use actix_web::{
     HttpResponse,
 };
 use futures::future::{ok, LocalBoxFuture, Ready};
+use std::str::FromStr;
 use actix_web::http::StatusCode;
 use crate::utils::jwt::Claims;
 use crate::utils::response::GenericResponse;
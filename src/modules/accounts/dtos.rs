@@ -0,0 +1,53 @@
+use bigdecimal::BigDecimal;
+use serde::Deserialize;
+use validator::{Validate, ValidationError};
+
+use crate::modules::accounts::models::AccountType;
+use crate::utils::money::{deserialize_optional_amount, has_money_scale};
+
+#[derive(Deserialize, Validate)]
+pub struct CreateAccountRequest {
+    #[validate(length(min = 1, max = 100, message = "Name must be between 1 and 100 characters"))]
+    pub name: String,
+    pub account_type: AccountType,
+    #[validate(custom(function = "validate_currency"))]
+    pub currency: Option<String>,
+    #[serde(default, deserialize_with = "deserialize_optional_amount")]
+    #[validate(custom(function = "validate_balance"))]
+    pub opening_balance: Option<BigDecimal>,
+}
+
+#[derive(Deserialize, Validate)]
+pub struct UpdateAccountRequest {
+    #[validate(length(min = 1, max = 100, message = "Name must be between 1 and 100 characters"))]
+    pub name: Option<String>,
+    pub account_type: Option<AccountType>,
+    #[serde(default, deserialize_with = "deserialize_optional_amount")]
+    #[validate(custom(function = "validate_balance"))]
+    pub opening_balance: Option<BigDecimal>,
+    pub archived: Option<bool>,
+}
+
+#[derive(Deserialize)]
+pub struct AccountListQuery {
+    #[serde(default)]
+    pub include_archived: bool,
+}
+
+fn validate_currency(currency: &str) -> Result<(), ValidationError> {
+    if currency.len() != 3 || !currency.chars().all(|c| c.is_ascii_uppercase()) {
+        let mut error = ValidationError::new("currency");
+        error.message = Some("Currency must be a 3-letter ISO 4217 code such as USD".into());
+        return Err(error);
+    }
+    Ok(())
+}
+
+fn validate_balance(balance: &BigDecimal) -> Result<(), ValidationError> {
+    if !has_money_scale(balance) {
+        let mut error = ValidationError::new("scale");
+        error.message = Some("Balance cannot have more than 2 decimal places".into());
+        return Err(error);
+    }
+    Ok(())
+}
@@ -0,0 +1,84 @@
+use bigdecimal::BigDecimal;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+use crate::utils::money::serialize_amount;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, sqlx::Type)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+#[sqlx(type_name = "VARCHAR", rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum AccountType {
+    Checking,
+    Savings,
+    CreditCard,
+    Cash,
+}
+
+impl AccountType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AccountType::Checking => "CHECKING",
+            AccountType::Savings => "SAVINGS",
+            AccountType::CreditCard => "CREDIT_CARD",
+            AccountType::Cash => "CASH",
+        }
+    }
+}
+
+impl fmt::Display for AccountType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+#[derive(Serialize, Deserialize, sqlx::FromRow)]
+pub struct Account {
+    pub id: i32,
+    pub user_id: i32,
+    pub name: String,
+    pub account_type: AccountType,
+    pub currency: String,
+    #[serde(serialize_with = "serialize_amount")]
+    pub opening_balance: BigDecimal,
+    pub archived: bool,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl fmt::Display for Account {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Account {{ id: {}, user_id: {}, name: {}, type: {}, currency: {}, archived: {} }}",
+            self.id,
+            self.user_id,
+            self.name,
+            self.account_type,
+            self.currency,
+            self.archived
+        )
+    }
+}
+
+/// An account together with its current balance: the opening balance plus every
+/// signed transaction amount recorded against it.
+#[derive(Serialize)]
+pub struct AccountWithBalance {
+    #[serde(flatten)]
+    pub account: Account,
+    #[serde(serialize_with = "serialize_amount")]
+    pub balance: BigDecimal,
+}
+
+/// Net worth in one currency. Balances are not converted between currencies.
+#[derive(Serialize)]
+pub struct NetWorth {
+    pub currency: String,
+    #[serde(serialize_with = "serialize_amount")]
+    pub total_assets: BigDecimal,
+    #[serde(serialize_with = "serialize_amount")]
+    pub total_liabilities: BigDecimal,
+    #[serde(serialize_with = "serialize_amount")]
+    pub net_worth: BigDecimal,
+}
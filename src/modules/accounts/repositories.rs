@@ -0,0 +1,164 @@
+use bigdecimal::BigDecimal;
+use chrono::NaiveDate;
+use sqlx::PgPool;
+
+use crate::modules::accounts::models::{Account, AccountType, AccountWithBalance, NetWorth};
+
+pub async fn insert_account(
+    pool: &PgPool,
+    user_id: i32,
+    name: &str,
+    account_type: AccountType,
+    currency: &str,
+    opening_balance: &BigDecimal,
+) -> Result<Account, sqlx::Error> {
+    sqlx::query_as!(
+        Account,
+        "INSERT INTO accounts (user_id, name, account_type, currency, opening_balance) VALUES ($1, $2, $3, $4, $5) RETURNING id, user_id, name, account_type as \"account_type: AccountType\", currency, opening_balance, archived, created_at, updated_at",
+        user_id,
+        name,
+        account_type.as_str(),
+        currency,
+        opening_balance
+    )
+        .fetch_one(pool)
+        .await
+}
+
+pub async fn find_account_by_id(pool: &PgPool, id: i32) -> Result<Option<Account>, sqlx::Error> {
+    sqlx::query_as!(
+        Account,
+        "SELECT id, user_id, name, account_type as \"account_type: AccountType\", currency, opening_balance, archived, created_at, updated_at FROM accounts WHERE id = $1",
+        id
+    )
+        .fetch_optional(pool)
+        .await
+}
+
+pub async fn find_accounts_with_balance_by_user_id(
+    pool: &PgPool,
+    user_id: i32,
+    include_archived: bool,
+) -> Result<Vec<AccountWithBalance>, sqlx::Error> {
+    let records = sqlx::query!(
+        r#"
+        SELECT
+            a.id, a.user_id, a.name, a.account_type as "account_type: AccountType", a.currency,
+            a.opening_balance, a.archived, a.created_at, a.updated_at,
+            a.opening_balance + COALESCE(SUM(t.amount), 0) as "balance!"
+        FROM accounts a
+        LEFT JOIN transactions t ON t.account_id = a.id
+        WHERE a.user_id = $1 AND ($2 OR NOT a.archived)
+        GROUP BY a.id
+        ORDER BY a.archived, a.name
+        "#,
+        user_id,
+        include_archived
+    )
+        .fetch_all(pool)
+        .await?;
+
+    Ok(records.into_iter().map(|r| AccountWithBalance {
+        account: Account {
+            id: r.id,
+            user_id: r.user_id,
+            name: r.name,
+            account_type: r.account_type,
+            currency: r.currency,
+            opening_balance: r.opening_balance,
+            archived: r.archived,
+            created_at: r.created_at,
+            updated_at: r.updated_at,
+        },
+        balance: r.balance,
+    }).collect())
+}
+
+pub async fn calculate_account_balance(pool: &PgPool, account_id: i32) -> Result<BigDecimal, sqlx::Error> {
+    let result = sqlx::query!(
+        r#"
+        SELECT a.opening_balance + COALESCE(SUM(t.amount), 0) as "balance!"
+        FROM accounts a
+        LEFT JOIN transactions t ON t.account_id = a.id
+        WHERE a.id = $1
+        GROUP BY a.id
+        "#,
+        account_id
+    )
+        .fetch_one(pool)
+        .await?;
+
+    Ok(result.balance)
+}
+
+/// The account's balance at the start of `date`: its opening balance plus every transaction
+/// that occurred before that day.
+pub async fn calculate_account_balance_before(
+    pool: &PgPool,
+    account_id: i32,
+    date: NaiveDate,
+) -> Result<BigDecimal, sqlx::Error> {
+    let result = sqlx::query!(
+        r#"
+        SELECT a.opening_balance + COALESCE(SUM(t.amount), 0) as "balance!"
+        FROM accounts a
+        LEFT JOIN transactions t ON t.account_id = a.id AND t.occurred_on < $2
+        WHERE a.id = $1
+        GROUP BY a.id
+        "#,
+        account_id,
+        date
+    )
+        .fetch_one(pool)
+        .await?;
+
+    Ok(result.balance)
+}
+
+pub async fn update_account(
+    pool: &PgPool,
+    id: i32,
+    name: &str,
+    account_type: AccountType,
+    opening_balance: &BigDecimal,
+    archived: bool,
+) -> Result<Option<Account>, sqlx::Error> {
+    // updated_at is maintained by the update_accounts_updated_at trigger
+    sqlx::query_as!(
+        Account,
+        "UPDATE accounts SET name = $2, account_type = $3, opening_balance = $4, archived = $5 WHERE id = $1 RETURNING id, user_id, name, account_type as \"account_type: AccountType\", currency, opening_balance, archived, created_at, updated_at",
+        id,
+        name,
+        account_type.as_str(),
+        opening_balance,
+        archived
+    )
+        .fetch_optional(pool)
+        .await
+}
+
+pub async fn calculate_user_net_worth(pool: &PgPool, user_id: i32) -> Result<Vec<NetWorth>, sqlx::Error> {
+    sqlx::query_as!(
+        NetWorth,
+        r#"
+        WITH balances AS (
+            SELECT a.currency, a.opening_balance + COALESCE(SUM(t.amount), 0) as balance
+            FROM accounts a
+            LEFT JOIN transactions t ON t.account_id = a.id
+            WHERE a.user_id = $1
+            GROUP BY a.id
+        )
+        SELECT
+            currency as "currency!",
+            COALESCE(SUM(balance) FILTER (WHERE balance > 0), 0) as "total_assets!",
+            COALESCE(-SUM(balance) FILTER (WHERE balance < 0), 0) as "total_liabilities!",
+            COALESCE(SUM(balance), 0) as "net_worth!"
+        FROM balances
+        GROUP BY currency
+        ORDER BY currency
+        "#,
+        user_id
+    )
+        .fetch_all(pool)
+        .await
+}
@@ -0,0 +1,112 @@
+use actix_web::{http::StatusCode, web, HttpResponse, HttpRequest};
+use validator::Validate;
+
+use crate::modules::accounts::dtos::{CreateAccountRequest, UpdateAccountRequest, AccountListQuery};
+use crate::modules::accounts::services::{
+    create_user_account, list_user_accounts, get_account_with_balance, update_user_account, get_user_net_worth,
+};
+use crate::utils::response::GenericResponse;
+use crate::utils::errors::AppError;
+use crate::middleware::jwt::{request_claims, JwtMiddleware};
+use crate::AppState;
+
+pub fn init(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/accounts")
+            .service(
+                web::resource("")
+                    .wrap(JwtMiddleware)
+                    .route(web::post().to(add_account))
+                    .route(web::get().to(list_accounts))
+            )
+            .service(
+                web::resource("/net-worth")
+                    .wrap(JwtMiddleware)
+                    .route(web::get().to(net_worth))
+            )
+            .service(
+                web::resource("/{id}")
+                    .wrap(JwtMiddleware)
+                    .route(web::get().to(get_account))
+                    .route(web::patch().to(patch_account))
+            ),
+    );
+}
+
+async fn add_account(
+    state: web::Data<AppState>,
+    account: web::Json<CreateAccountRequest>,
+    req: HttpRequest,
+) -> Result<HttpResponse, AppError> {
+    account.validate()?;
+    let claims = request_claims(&req)?;
+
+    let account = create_user_account(&state.db, claims.user_id, &account).await?;
+    log::info!("Account created for user {}: {}", claims.user_id, account);
+
+    Ok(HttpResponse::Created().json(GenericResponse {
+        status: StatusCode::CREATED.as_u16(),
+        data: Some(account),
+        message: "Account created successfully".to_string(),
+    }))
+}
+
+async fn list_accounts(
+    state: web::Data<AppState>,
+    query: web::Query<AccountListQuery>,
+    req: HttpRequest,
+) -> Result<HttpResponse, AppError> {
+    let claims = request_claims(&req)?;
+    let accounts = list_user_accounts(&state.db, claims.user_id, query.include_archived).await?;
+
+    Ok(HttpResponse::Ok().json(GenericResponse {
+        status: StatusCode::OK.as_u16(),
+        data: Some(accounts),
+        message: "Accounts retrieved successfully".to_string(),
+    }))
+}
+
+async fn net_worth(state: web::Data<AppState>, req: HttpRequest) -> Result<HttpResponse, AppError> {
+    let claims = request_claims(&req)?;
+    let net_worth = get_user_net_worth(&state.db, claims.user_id).await?;
+
+    Ok(HttpResponse::Ok().json(GenericResponse {
+        status: StatusCode::OK.as_u16(),
+        data: Some(net_worth),
+        message: "Net worth retrieved successfully".to_string(),
+    }))
+}
+
+async fn get_account(
+    state: web::Data<AppState>,
+    path: web::Path<i32>,
+    req: HttpRequest,
+) -> Result<HttpResponse, AppError> {
+    let claims = request_claims(&req)?;
+    let account = get_account_with_balance(&state.db, &claims, path.into_inner()).await?;
+
+    Ok(HttpResponse::Ok().json(GenericResponse {
+        status: StatusCode::OK.as_u16(),
+        data: Some(account),
+        message: "Account retrieved successfully".to_string(),
+    }))
+}
+
+async fn patch_account(
+    state: web::Data<AppState>,
+    path: web::Path<i32>,
+    account: web::Json<UpdateAccountRequest>,
+    req: HttpRequest,
+) -> Result<HttpResponse, AppError> {
+    account.validate()?;
+    let claims = request_claims(&req)?;
+
+    let account = update_user_account(&state.db, &claims, path.into_inner(), &account).await?;
+    log::info!("Account updated by user {}: {}", claims.user_id, account);
+
+    Ok(HttpResponse::Ok().json(GenericResponse {
+        status: StatusCode::OK.as_u16(),
+        data: Some(account),
+        message: "Account updated successfully".to_string(),
+    }))
+}
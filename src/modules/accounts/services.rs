@@ -0,0 +1,100 @@
+use bigdecimal::BigDecimal;
+use log::warn;
+use sqlx::PgPool;
+
+use crate::modules::accounts::dtos::{CreateAccountRequest, UpdateAccountRequest};
+use crate::modules::accounts::models::{Account, AccountWithBalance, NetWorth};
+use crate::modules::accounts::repositories::{
+    insert_account, find_account_by_id, find_accounts_with_balance_by_user_id, calculate_account_balance,
+    update_account, calculate_user_net_worth,
+};
+use crate::utils::errors::AppError;
+use crate::utils::jwt::Claims;
+
+const DEFAULT_CURRENCY: &str = "USD";
+
+pub async fn create_user_account(
+    pool: &PgPool,
+    user_id: i32,
+    request: &CreateAccountRequest,
+) -> Result<Account, AppError> {
+    let currency = request.currency.as_deref().unwrap_or(DEFAULT_CURRENCY);
+    let opening_balance = request.opening_balance.clone().unwrap_or_default();
+
+    Ok(insert_account(pool, user_id, request.name.trim(), request.account_type, currency, &opening_balance).await?)
+}
+
+pub async fn list_user_accounts(
+    pool: &PgPool,
+    user_id: i32,
+    include_archived: bool,
+) -> Result<Vec<AccountWithBalance>, AppError> {
+    Ok(find_accounts_with_balance_by_user_id(pool, user_id, include_archived).await?)
+}
+
+/// Loads account `id` for its owner, or for an admin whoever owns it.
+pub async fn get_accessible_account(pool: &PgPool, claims: &Claims, id: i32) -> Result<Account, AppError> {
+    let account = find_account_by_id(pool, id)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("Account {} not found", id)))?;
+
+    if account.user_id != claims.user_id && !claims.is_admin() {
+        warn!("User {} attempted to access account {} owned by user {}", claims.user_id, id, account.user_id);
+        return Err(AppError::Forbidden("You do not have access to this account".to_string()));
+    }
+
+    Ok(account)
+}
+
+pub async fn get_account_with_balance(pool: &PgPool, claims: &Claims, id: i32) -> Result<AccountWithBalance, AppError> {
+    let account = get_accessible_account(pool, claims, id).await?;
+    let balance = calculate_account_balance(pool, account.id).await?;
+    Ok(AccountWithBalance { account, balance })
+}
+
+pub async fn update_user_account(
+    pool: &PgPool,
+    claims: &Claims,
+    id: i32,
+    request: &UpdateAccountRequest,
+) -> Result<Account, AppError> {
+    let existing = get_accessible_account(pool, claims, id).await?;
+
+    let name = request.name.as_deref().map(str::trim).unwrap_or(&existing.name);
+    let account_type = request.account_type.unwrap_or(existing.account_type);
+    let opening_balance: &BigDecimal = request.opening_balance.as_ref().unwrap_or(&existing.opening_balance);
+    let archived = request.archived.unwrap_or(existing.archived);
+
+    update_account(pool, id, name, account_type, opening_balance, archived)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("Account {} not found", id)))
+}
+
+pub async fn get_user_net_worth(pool: &PgPool, user_id: i32) -> Result<Vec<NetWorth>, AppError> {
+    Ok(calculate_user_net_worth(pool, user_id).await?)
+}
+
+/// Checks that transactions for `owner_id` may be recorded against `account_id`:
+/// the account must exist, belong to that user and not be archived. Failures are
+/// reported against the request field `field`.
+pub async fn ensure_account_accepts_transactions(
+    pool: &PgPool,
+    owner_id: i32,
+    account_id: i32,
+    field: &'static str,
+) -> Result<Account, AppError> {
+    let account = match find_account_by_id(pool, account_id).await? {
+        Some(account) if account.user_id == owner_id => account,
+        _ => return Err(AppError::invalid_field(field, "account", "Account not found")),
+    };
+
+    if account.archived {
+        return Err(AppError::invalid_field(
+            field,
+            "archived",
+            "Transactions cannot be recorded on an archived account",
+        ));
+    }
+
+    Ok(account)
+}
@@ -0,0 +1,5 @@
+pub mod models;
+pub mod repositories;
+pub mod routes;
+pub mod services;
+pub mod storage;
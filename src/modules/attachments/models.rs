@@ -0,0 +1,56 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::fmt;
+
+#[derive(Serialize)]
+pub struct Attachment {
+    pub id: i32,
+    pub transaction_id: i32,
+    pub user_id: i32,
+    pub file_name: String,
+    pub content_type: String,
+    pub size_bytes: i64,
+    /// Hex-encoded SHA-256 digest of the file, as uploaded.
+    pub sha256: String,
+    /// Where the file is kept in the storage backend; never shown to clients.
+    #[serde(skip)]
+    pub storage_key: String,
+    pub created_at: DateTime<Utc>,
+}
+
+impl fmt::Display for Attachment {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Attachment {{ id: {}, transaction_id: {}, user_id: {}, file_name: {}, content_type: {}, size_bytes: {} }}",
+            self.id, self.transaction_id, self.user_id, self.file_name, self.content_type, self.size_bytes
+        )
+    }
+}
+
+/// The metadata of an uploaded file, before it is saved.
+pub struct AttachmentDraft {
+    pub file_name: String,
+    pub content_type: &'static str,
+    pub size_bytes: i64,
+    pub sha256: String,
+    pub storage_key: String,
+}
+
+/// The kinds of files that can be attached, told apart by their first bytes rather than
+/// by what the client claims they are.
+pub fn sniff_content_type(data: &[u8]) -> Option<&'static str> {
+    if data.starts_with(b"%PDF-") {
+        Some("application/pdf")
+    } else if data.starts_with(&[0xFF, 0xD8, 0xFF]) {
+        Some("image/jpeg")
+    } else if data.starts_with(&[0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1A, b'\n']) {
+        Some("image/png")
+    } else if data.len() >= 12 && &data[..4] == b"RIFF" && &data[8..12] == b"WEBP" {
+        Some("image/webp")
+    } else if data.len() >= 12 && &data[4..8] == b"ftyp" && matches!(&data[8..12], b"heic" | b"heix" | b"mif1") {
+        Some("image/heic")
+    } else {
+        None
+    }
+}
@@ -0,0 +1,84 @@
+use sqlx::PgPool;
+
+use crate::modules::attachments::models::{Attachment, AttachmentDraft};
+
+pub async fn insert_attachment(
+    pool: &PgPool,
+    user_id: i32,
+    transaction_id: i32,
+    draft: &AttachmentDraft,
+) -> Result<Attachment, sqlx::Error> {
+    sqlx::query_as!(
+        Attachment,
+        r#"
+        INSERT INTO attachments (transaction_id, user_id, file_name, content_type, size_bytes, sha256, storage_key)
+        VALUES ($1, $2, $3, $4, $5, $6, $7)
+        RETURNING id, transaction_id, user_id, file_name, content_type, size_bytes, sha256, storage_key, created_at
+        "#,
+        transaction_id,
+        user_id,
+        draft.file_name,
+        draft.content_type,
+        draft.size_bytes,
+        draft.sha256,
+        draft.storage_key
+    )
+        .fetch_one(pool)
+        .await
+}
+
+pub async fn find_attachments_by_transaction_id(pool: &PgPool, transaction_id: i32) -> Result<Vec<Attachment>, sqlx::Error> {
+    sqlx::query_as!(
+        Attachment,
+        r#"
+        SELECT id, transaction_id, user_id, file_name, content_type, size_bytes, sha256, storage_key, created_at
+        FROM attachments
+        WHERE transaction_id = $1
+        ORDER BY id
+        "#,
+        transaction_id
+    )
+        .fetch_all(pool)
+        .await
+}
+
+pub async fn find_attachment_by_id(pool: &PgPool, id: i32) -> Result<Option<Attachment>, sqlx::Error> {
+    sqlx::query_as!(
+        Attachment,
+        r#"
+        SELECT id, transaction_id, user_id, file_name, content_type, size_bytes, sha256, storage_key, created_at
+        FROM attachments
+        WHERE id = $1
+        "#,
+        id
+    )
+        .fetch_optional(pool)
+        .await
+}
+
+pub async fn count_attachments_by_transaction_id(pool: &PgPool, transaction_id: i32) -> Result<i64, sqlx::Error> {
+    sqlx::query_scalar!(
+        r#"SELECT COUNT(*) as "count!" FROM attachments WHERE transaction_id = $1"#,
+        transaction_id
+    )
+        .fetch_one(pool)
+        .await
+}
+
+/// Storage keys of the files attached to any of the given transactions.
+pub async fn find_attachment_keys_by_transaction_ids(pool: &PgPool, transaction_ids: &[i32]) -> Result<Vec<String>, sqlx::Error> {
+    sqlx::query_scalar!(
+        "SELECT storage_key FROM attachments WHERE transaction_id = ANY($1)",
+        transaction_ids
+    )
+        .fetch_all(pool)
+        .await
+}
+
+pub async fn delete_attachment(pool: &PgPool, id: i32) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query!("DELETE FROM attachments WHERE id = $1", id)
+        .execute(pool)
+        .await?;
+
+    Ok(result.rows_affected() > 0)
+}
@@ -0,0 +1,168 @@
+use actix_multipart::{Multipart, MultipartError};
+use actix_web::http::header::{ContentDisposition, DispositionParam, DispositionType, ExtendedValue, Charset};
+use actix_web::{http::StatusCode, web, HttpResponse, HttpRequest};
+use futures::TryStreamExt;
+
+use crate::modules::attachments::services::{
+    attach_file, list_transaction_attachments, get_owned_attachment, read_attachment, remove_attachment,
+};
+use crate::utils::constants::uploads::MAX_ATTACHMENT_BYTES;
+use crate::utils::response::GenericResponse;
+use crate::utils::errors::AppError;
+use crate::middleware::jwt::{request_claims, JwtMiddleware};
+use crate::AppState;
+
+pub fn init(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/attachments")
+            .service(
+                web::resource("/{id}/download")
+                    .wrap(JwtMiddleware)
+                    .route(web::get().to(download_attachment))
+            )
+            .service(
+                web::resource("/{id}")
+                    .wrap(JwtMiddleware)
+                    .route(web::get().to(get_attachment))
+                    .route(web::delete().to(delete_attachment))
+            ),
+    );
+}
+
+/// `POST /transactions/{id}/attachments`, registered with the transaction routes.
+pub async fn upload_attachment(
+    state: web::Data<AppState>,
+    path: web::Path<i32>,
+    payload: Multipart,
+    req: HttpRequest,
+) -> Result<HttpResponse, AppError> {
+    let claims = request_claims(&req)?;
+    let (file_name, data) = read_file_part(payload).await?;
+
+    let attachment = attach_file(&state.db, state.storage.as_ref(), &claims, path.into_inner(), &file_name, data).await?;
+    log::info!("Attachment uploaded by user {}: {}", claims.user_id, attachment);
+
+    Ok(HttpResponse::Created().json(GenericResponse {
+        status: StatusCode::CREATED.as_u16(),
+        data: Some(attachment),
+        message: "Attachment uploaded successfully".to_string(),
+    }))
+}
+
+/// `GET /transactions/{id}/attachments`, registered with the transaction routes.
+pub async fn list_attachments(
+    state: web::Data<AppState>,
+    path: web::Path<i32>,
+    req: HttpRequest,
+) -> Result<HttpResponse, AppError> {
+    let claims = request_claims(&req)?;
+    let attachments = list_transaction_attachments(&state.db, &claims, path.into_inner()).await?;
+
+    Ok(HttpResponse::Ok().json(GenericResponse {
+        status: StatusCode::OK.as_u16(),
+        data: Some(attachments),
+        message: "Attachments retrieved successfully".to_string(),
+    }))
+}
+
+async fn get_attachment(
+    state: web::Data<AppState>,
+    path: web::Path<i32>,
+    req: HttpRequest,
+) -> Result<HttpResponse, AppError> {
+    let claims = request_claims(&req)?;
+    let attachment = get_owned_attachment(&state.db, &claims, path.into_inner()).await?;
+
+    Ok(HttpResponse::Ok().json(GenericResponse {
+        status: StatusCode::OK.as_u16(),
+        data: Some(attachment),
+        message: "Attachment retrieved successfully".to_string(),
+    }))
+}
+
+async fn download_attachment(
+    state: web::Data<AppState>,
+    path: web::Path<i32>,
+    req: HttpRequest,
+) -> Result<HttpResponse, AppError> {
+    let claims = request_claims(&req)?;
+    let (attachment, data) = read_attachment(&state.db, state.storage.as_ref(), &claims, path.into_inner()).await?;
+
+    // Always a download, so that an uploaded file is never rendered in the API's origin
+    let mut parameters = vec![DispositionParam::Filename(attachment.file_name.replace(|c: char| !c.is_ascii(), "_"))];
+    if !attachment.file_name.is_ascii() {
+        parameters.push(DispositionParam::FilenameExt(ExtendedValue {
+            charset: Charset::Ext("UTF-8".to_string()),
+            language_tag: None,
+            value: attachment.file_name.clone().into_bytes(),
+        }));
+    }
+    Ok(HttpResponse::Ok()
+        .content_type(attachment.content_type.as_str())
+        .insert_header(ContentDisposition { disposition: DispositionType::Attachment, parameters })
+        .insert_header(("X-Content-Type-Options", "nosniff"))
+        .body(data))
+}
+
+async fn delete_attachment(
+    state: web::Data<AppState>,
+    path: web::Path<i32>,
+    req: HttpRequest,
+) -> Result<HttpResponse, AppError> {
+    let claims = request_claims(&req)?;
+    let id = path.into_inner();
+    remove_attachment(&state.db, state.storage.as_ref(), &claims, id).await?;
+    log::info!("Attachment {} deleted by user {}", id, claims.user_id);
+
+    Ok(HttpResponse::Ok().json(GenericResponse {
+        status: StatusCode::OK.as_u16(),
+        data: None::<()>,
+        message: "Attachment deleted successfully".to_string(),
+    }))
+}
+
+/// Reads the `file` part of an upload into memory with its file name; other parts are
+/// skipped.
+async fn read_file_part(mut payload: Multipart) -> Result<(String, web::Bytes), AppError> {
+    while let Some(mut field) = payload.try_next().await.map_err(multipart_error)? {
+        if field.name() != Some("file") {
+            while field.try_next().await.map_err(multipart_error)?.is_some() {}
+            continue;
+        }
+
+        let file_name = field
+            .content_disposition()
+            .and_then(|disposition| disposition.get_filename())
+            .map(clean_file_name)
+            .filter(|name| !name.is_empty())
+            .unwrap_or_else(|| "attachment".to_string());
+        let mut data = Vec::new();
+        while let Some(chunk) = field.try_next().await.map_err(multipart_error)? {
+            if data.len() + chunk.len() > MAX_ATTACHMENT_BYTES {
+                return Err(AppError::invalid_field(
+                    "file",
+                    "size",
+                    &format!("Attachments cannot exceed {} MB", MAX_ATTACHMENT_BYTES / (1024 * 1024)),
+                ));
+            }
+            data.extend_from_slice(&chunk);
+        }
+        if data.is_empty() {
+            return Err(AppError::invalid_field("file", "empty", "The uploaded file is empty"));
+        }
+        return Ok((file_name, web::Bytes::from(data)));
+    }
+
+    Err(AppError::invalid_field("file", "required", "The form is missing the 'file' part"))
+}
+
+/// The last path component of a client's file name, without control characters, cut to
+/// 255 characters.
+fn clean_file_name(name: &str) -> String {
+    let name = name.rsplit(['/', '\\']).next().unwrap_or_default();
+    name.chars().filter(|c| !c.is_control()).take(255).collect::<String>().trim().to_string()
+}
+
+fn multipart_error(error: MultipartError) -> AppError {
+    AppError::invalid_field("file", "multipart", &format!("Invalid multipart upload: {}", error))
+}
@@ -0,0 +1,127 @@
+use actix_web::web::Bytes;
+use log::{error, warn};
+use sha2::{Digest, Sha256};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::modules::attachments::models::{sniff_content_type, Attachment, AttachmentDraft};
+use crate::modules::attachments::repositories::{
+    insert_attachment, find_attachments_by_transaction_id, find_attachment_by_id, count_attachments_by_transaction_id,
+    delete_attachment,
+};
+use crate::modules::attachments::storage::AttachmentStorage;
+use crate::modules::transactions::models::Transaction;
+use crate::modules::transactions::repositories::find_transaction_by_id;
+use crate::utils::constants::uploads::MAX_ATTACHMENTS_PER_TRANSACTION;
+use crate::utils::errors::AppError;
+use crate::utils::jwt::Claims;
+
+/// Stores a file and attaches it to one of the caller's transactions. The file is kept
+/// only if its metadata is saved too.
+pub async fn attach_file(
+    pool: &PgPool,
+    storage: &dyn AttachmentStorage,
+    claims: &Claims,
+    transaction_id: i32,
+    file_name: &str,
+    data: Bytes,
+) -> Result<Attachment, AppError> {
+    let transaction = get_owned_transaction(pool, claims, transaction_id).await?;
+    if count_attachments_by_transaction_id(pool, transaction.id).await? >= MAX_ATTACHMENTS_PER_TRANSACTION {
+        return Err(AppError::Conflict(format!(
+            "A transaction can have at most {} attachments",
+            MAX_ATTACHMENTS_PER_TRANSACTION
+        )));
+    }
+    let content_type = sniff_content_type(&data).ok_or_else(|| {
+        AppError::invalid_field("file", "content_type", "Only PDF documents and JPEG, PNG, WebP or HEIC images can be attached")
+    })?;
+
+    let draft = AttachmentDraft {
+        file_name: file_name.to_string(),
+        content_type,
+        size_bytes: data.len() as i64,
+        sha256: hex::encode(Sha256::digest(&data)),
+        storage_key: format!("{}/{}", transaction.user_id, Uuid::new_v4()),
+    };
+    storage.put(&draft.storage_key, content_type, data).await?;
+
+    match insert_attachment(pool, transaction.user_id, transaction.id, &draft).await {
+        Ok(attachment) => Ok(attachment),
+        Err(e) => {
+            discard_stored_files(storage, &[draft.storage_key]).await;
+            Err(e.into())
+        }
+    }
+}
+
+pub async fn list_transaction_attachments(pool: &PgPool, claims: &Claims, transaction_id: i32) -> Result<Vec<Attachment>, AppError> {
+    get_owned_transaction(pool, claims, transaction_id).await?;
+    Ok(find_attachments_by_transaction_id(pool, transaction_id).await?)
+}
+
+/// Loads an attachment of the caller's. Unlike most resources, attachments are private
+/// to their owner; admins cannot read them either.
+pub async fn get_owned_attachment(pool: &PgPool, claims: &Claims, id: i32) -> Result<Attachment, AppError> {
+    let attachment = find_attachment_by_id(pool, id)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("Attachment {} not found", id)))?;
+
+    if attachment.user_id != claims.user_id {
+        warn!("User {} attempted to access attachment {} owned by user {}", claims.user_id, id, attachment.user_id);
+        return Err(AppError::Forbidden("You do not have access to this attachment".to_string()));
+    }
+
+    Ok(attachment)
+}
+
+/// An attachment of the caller's together with its file.
+pub async fn read_attachment(
+    pool: &PgPool,
+    storage: &dyn AttachmentStorage,
+    claims: &Claims,
+    id: i32,
+) -> Result<(Attachment, Bytes), AppError> {
+    let attachment = get_owned_attachment(pool, claims, id).await?;
+    let data = storage.get(&attachment.storage_key).await?;
+    Ok((attachment, data))
+}
+
+pub async fn remove_attachment(
+    pool: &PgPool,
+    storage: &dyn AttachmentStorage,
+    claims: &Claims,
+    id: i32,
+) -> Result<(), AppError> {
+    let attachment = get_owned_attachment(pool, claims, id).await?;
+
+    if !delete_attachment(pool, id).await? {
+        return Err(AppError::NotFound(format!("Attachment {} not found", id)));
+    }
+    discard_stored_files(storage, &[attachment.storage_key]).await;
+    Ok(())
+}
+
+/// Deletes files whose attachments are gone. A file that can't be deleted is only
+/// logged, since nothing refers to it any more.
+pub async fn discard_stored_files(storage: &dyn AttachmentStorage, keys: &[String]) {
+    for key in keys {
+        if let Err(e) = storage.delete(key).await {
+            error!("Failed to delete attachment file {}: {:?}", key, e);
+        }
+    }
+}
+
+/// Files can only be attached to, and listed for, the caller's own transactions.
+async fn get_owned_transaction(pool: &PgPool, claims: &Claims, id: i32) -> Result<Transaction, AppError> {
+    let transaction = find_transaction_by_id(pool, id)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("Transaction {} not found", id)))?;
+
+    if transaction.user_id != claims.user_id {
+        warn!("User {} attempted to access attachments of transaction {} owned by user {}", claims.user_id, id, transaction.user_id);
+        return Err(AppError::Forbidden("You do not have access to this transaction".to_string()));
+    }
+
+    Ok(transaction)
+}
@@ -0,0 +1,134 @@
+use std::env;
+use std::io::ErrorKind;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use actix_web::web::Bytes;
+use async_trait::async_trait;
+use object_store::aws::{AmazonS3, AmazonS3Builder};
+use object_store::path::Path as ObjectPath;
+use object_store::{ObjectStore, PutPayload};
+use tokio::fs;
+
+use crate::utils::constants::uploads::DEFAULT_ATTACHMENT_DIR;
+use crate::utils::errors::AppError;
+
+/// Where attachment files are kept. Keys are generated by the application and are
+/// `/`-separated relative paths.
+#[async_trait]
+pub trait AttachmentStorage: Send + Sync {
+    async fn put(&self, key: &str, content_type: &str, data: Bytes) -> Result<(), AppError>;
+    async fn get(&self, key: &str) -> Result<Bytes, AppError>;
+    /// Deleting a file that is already gone succeeds.
+    async fn delete(&self, key: &str) -> Result<(), AppError>;
+}
+
+/// Builds the backend named by `ATTACHMENT_STORAGE`: `local` (the default) keeps files
+/// under `ATTACHMENT_DIR`, and `s3` keeps them in the `ATTACHMENT_S3_BUCKET` bucket of
+/// any S3-compatible service, configured through the usual `AWS_*` variables.
+pub fn storage_from_env() -> Result<Arc<dyn AttachmentStorage>, String> {
+    match env::var("ATTACHMENT_STORAGE").as_deref() {
+        Err(_) | Ok("local") => {
+            let root = env::var("ATTACHMENT_DIR").unwrap_or_else(|_| DEFAULT_ATTACHMENT_DIR.to_string());
+            Ok(Arc::new(LocalStorage::new(root)))
+        }
+        Ok("s3") => {
+            let bucket = env::var("ATTACHMENT_S3_BUCKET")
+                .map_err(|_| "ATTACHMENT_S3_BUCKET must be set for S3 attachment storage".to_string())?;
+            Ok(Arc::new(S3Storage::new(&bucket)?))
+        }
+        Ok(other) => Err(format!("Unknown ATTACHMENT_STORAGE '{}'; expected 'local' or 's3'", other)),
+    }
+}
+
+/// Keeps files in a directory of the local filesystem.
+pub struct LocalStorage {
+    root: PathBuf,
+}
+
+impl LocalStorage {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        LocalStorage { root: root.into() }
+    }
+}
+
+#[async_trait]
+impl AttachmentStorage for LocalStorage {
+    async fn put(&self, key: &str, _content_type: &str, data: Bytes) -> Result<(), AppError> {
+        let path = self.root.join(key);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).await.map_err(storage_error)?;
+        }
+        // Written aside and renamed, so a file is never seen half-written
+        let partial = path.with_extension("partial");
+        fs::write(&partial, &data).await.map_err(storage_error)?;
+        fs::rename(&partial, &path).await.map_err(storage_error)
+    }
+
+    async fn get(&self, key: &str) -> Result<Bytes, AppError> {
+        match fs::read(self.root.join(key)).await {
+            Ok(data) => Ok(Bytes::from(data)),
+            Err(e) if e.kind() == ErrorKind::NotFound => Err(missing_file(key)),
+            Err(e) => Err(storage_error(e)),
+        }
+    }
+
+    async fn delete(&self, key: &str) -> Result<(), AppError> {
+        match fs::remove_file(self.root.join(key)).await {
+            Err(e) if e.kind() != ErrorKind::NotFound => Err(storage_error(e)),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Keeps files in a bucket of Amazon S3 or a compatible service such as MinIO. Requests
+/// use path-style addressing, which every compatible service accepts.
+pub struct S3Storage {
+    store: AmazonS3,
+}
+
+impl S3Storage {
+    pub fn new(bucket: &str) -> Result<Self, String> {
+        let store = AmazonS3Builder::from_env()
+            .with_bucket_name(bucket)
+            .build()
+            .map_err(|e| format!("Invalid S3 attachment storage configuration: {}", e))?;
+        Ok(S3Storage { store })
+    }
+}
+
+#[async_trait]
+impl AttachmentStorage for S3Storage {
+    async fn put(&self, key: &str, content_type: &str, data: Bytes) -> Result<(), AppError> {
+        let attributes = object_store::Attributes::from_iter([(object_store::Attribute::ContentType, content_type.to_string())]);
+        let options = object_store::PutOptions { attributes, ..Default::default() };
+        self.store
+            .put_opts(&ObjectPath::from(key), PutPayload::from(data), options)
+            .await
+            .map_err(storage_error)?;
+        Ok(())
+    }
+
+    async fn get(&self, key: &str) -> Result<Bytes, AppError> {
+        match self.store.get(&ObjectPath::from(key)).await {
+            Ok(result) => result.bytes().await.map_err(storage_error),
+            Err(object_store::Error::NotFound { .. }) => Err(missing_file(key)),
+            Err(e) => Err(storage_error(e)),
+        }
+    }
+
+    async fn delete(&self, key: &str) -> Result<(), AppError> {
+        match self.store.delete(&ObjectPath::from(key)).await {
+            Err(e) if !matches!(e, object_store::Error::NotFound { .. }) => Err(storage_error(e)),
+            _ => Ok(()),
+        }
+    }
+}
+
+fn storage_error(error: impl std::fmt::Display) -> AppError {
+    AppError::InternalServerError(format!("Attachment storage error: {}", error))
+}
+
+fn missing_file(key: &str) -> AppError {
+    AppError::InternalServerError(format!("Attachment file {} is missing from storage", key))
+}
@@ -0,0 +1,43 @@
+use bigdecimal::BigDecimal;
+use chrono::{Datelike, NaiveDate, Utc};
+use serde::Deserialize;
+use validator::{Validate, ValidationError};
+
+use crate::utils::errors::AppError;
+use crate::utils::money::{deserialize_amount, has_money_scale};
+
+#[derive(Deserialize, Validate)]
+pub struct BudgetRequest {
+    #[serde(deserialize_with = "deserialize_amount")]
+    #[validate(custom(function = "validate_limit"))]
+    pub amount: BigDecimal,
+    #[serde(default)]
+    pub rollover: bool,
+}
+
+/// Parses a budget period path segment: a month as `YYYY-MM`, or `current` for this
+/// month. Returns the first day of the month.
+pub fn parse_period(raw: &str) -> Result<NaiveDate, AppError> {
+    if raw == "current" {
+        let today = Utc::now().date_naive();
+        return Ok(today.with_day(1).unwrap_or(today));
+    }
+
+    NaiveDate::parse_from_str(&format!("{}-01", raw), "%Y-%m-%d").map_err(|_| {
+        AppError::invalid_field("period", "period", "Period must be a month such as 2024-03, or current")
+    })
+}
+
+fn validate_limit(amount: &BigDecimal) -> Result<(), ValidationError> {
+    if *amount <= BigDecimal::from(0) || *amount > BigDecimal::from(1_000_000) {
+        let mut error = ValidationError::new("range");
+        error.message = Some("Budget must be greater than 0 and at most 1,000,000".into());
+        return Err(error);
+    }
+    if !has_money_scale(amount) {
+        let mut error = ValidationError::new("scale");
+        error.message = Some("Budget cannot have more than 2 decimal places".into());
+        return Err(error);
+    }
+    Ok(())
+}
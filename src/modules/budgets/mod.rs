@@ -0,0 +1,5 @@
+pub mod dtos;
+pub mod models;
+pub mod repositories;
+pub mod routes;
+pub mod services;
\ No newline at end of file
@@ -0,0 +1,90 @@
+use bigdecimal::BigDecimal;
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Serialize, Serializer};
+use std::fmt;
+
+use crate::utils::money::serialize_amount;
+
+/// Serializes the first day of a budget month as `YYYY-MM`.
+fn serialize_period<S>(period: &NaiveDate, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    serializer.collect_str(&period.format("%Y-%m"))
+}
+
+#[derive(Serialize)]
+pub struct Budget {
+    pub id: i32,
+    pub user_id: i32,
+    pub category_id: i32,
+    #[serde(serialize_with = "serialize_period")]
+    pub period: NaiveDate,
+    #[serde(serialize_with = "serialize_amount")]
+    pub amount: BigDecimal,
+    pub rollover: bool,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl fmt::Display for Budget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Budget {{ id: {}, user_id: {}, category_id: {}, period: {}, amount: {:.2}, rollover: {} }}",
+            self.id,
+            self.user_id,
+            self.category_id,
+            self.period.format("%Y-%m"),
+            self.amount,
+            self.rollover
+        )
+    }
+}
+
+/// A budget month together with what was spent in its category (subcategories
+/// included) during that month.
+pub struct BudgetSpending {
+    pub budget_id: i32,
+    pub category_id: i32,
+    pub category_name: String,
+    pub period: NaiveDate,
+    pub amount: BigDecimal,
+    pub rollover: bool,
+    pub spent: BigDecimal,
+}
+
+/// Progress of one category against its budget. `available` is the month's limit plus
+/// whatever the previous month rolled over; `remaining` goes negative once overspent.
+#[derive(Serialize)]
+pub struct BudgetProgress {
+    pub budget_id: i32,
+    pub category_id: i32,
+    pub category_name: String,
+    #[serde(serialize_with = "serialize_amount")]
+    pub limit: BigDecimal,
+    pub rollover: bool,
+    #[serde(serialize_with = "serialize_amount")]
+    pub carried_over: BigDecimal,
+    #[serde(serialize_with = "serialize_amount")]
+    pub available: BigDecimal,
+    #[serde(serialize_with = "serialize_amount")]
+    pub spent: BigDecimal,
+    #[serde(serialize_with = "serialize_amount")]
+    pub remaining: BigDecimal,
+    #[serde(serialize_with = "serialize_amount")]
+    pub percent_used: BigDecimal,
+}
+
+#[derive(Serialize)]
+pub struct BudgetReport {
+    #[serde(serialize_with = "serialize_period")]
+    pub period: NaiveDate,
+    pub budgets: Vec<BudgetProgress>,
+    #[serde(serialize_with = "serialize_amount")]
+    pub total_available: BigDecimal,
+    #[serde(serialize_with = "serialize_amount")]
+    pub total_spent: BigDecimal,
+    #[serde(serialize_with = "serialize_amount")]
+    pub total_remaining: BigDecimal,
+}
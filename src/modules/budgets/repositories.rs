@@ -0,0 +1,87 @@
+use bigdecimal::BigDecimal;
+use chrono::NaiveDate;
+use sqlx::PgPool;
+
+use crate::modules::budgets::models::{Budget, BudgetSpending};
+
+/// Creates the budget for a category and month, or replaces the one already set.
+pub async fn upsert_budget(
+    pool: &PgPool,
+    user_id: i32,
+    category_id: i32,
+    period: NaiveDate,
+    amount: &BigDecimal,
+    rollover: bool,
+) -> Result<Budget, sqlx::Error> {
+    sqlx::query_as!(
+        Budget,
+        r#"
+        INSERT INTO budgets (user_id, category_id, period, amount, rollover)
+        VALUES ($1, $2, $3, $4, $5)
+        ON CONFLICT (category_id, period) DO UPDATE SET amount = EXCLUDED.amount, rollover = EXCLUDED.rollover
+        RETURNING id, user_id, category_id, period, amount, rollover, created_at, updated_at
+        "#,
+        user_id,
+        category_id,
+        period,
+        amount,
+        rollover
+    )
+        .fetch_one(pool)
+        .await
+}
+
+pub async fn delete_budget(
+    pool: &PgPool,
+    user_id: i32,
+    category_id: i32,
+    period: NaiveDate,
+) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query!(
+        "DELETE FROM budgets WHERE user_id = $1 AND category_id = $2 AND period = $3",
+        user_id,
+        category_id,
+        period
+    )
+        .execute(pool)
+        .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// Every budget month of a user up to and including `period`, ordered by category and
+/// month, with the net amount spent in the category and its subcategories that month.
+pub async fn find_budget_spending_until(
+    pool: &PgPool,
+    user_id: i32,
+    period: NaiveDate,
+) -> Result<Vec<BudgetSpending>, sqlx::Error> {
+    sqlx::query_as!(
+        BudgetSpending,
+        r#"
+        SELECT
+            b.id as budget_id,
+            b.category_id,
+            c.name as category_name,
+            b.period,
+            b.amount,
+            b.rollover,
+            COALESCE((
+                SELECT -SUM(t.amount)
+                FROM transaction_lines t
+                WHERE t.user_id = b.user_id
+                    AND t.occurred_on >= b.period
+                    AND t.occurred_on < (b.period + INTERVAL '1 month')::date
+                    AND t.category_id IN (SELECT id FROM categories WHERE id = b.category_id OR parent_id = b.category_id)
+            ), 0) as "spent!"
+        FROM budgets b
+        JOIN categories c ON c.id = b.category_id
+        WHERE b.user_id = $1 AND b.period <= $2
+        ORDER BY b.category_id, b.period
+        "#,
+        user_id,
+        period
+    )
+        .fetch_all(pool)
+        .await
+}
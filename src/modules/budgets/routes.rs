@@ -0,0 +1,83 @@
+use actix_web::{http::StatusCode, web, HttpResponse, HttpRequest};
+use validator::Validate;
+
+use crate::modules::budgets::dtos::{parse_period, BudgetRequest};
+use crate::modules::budgets::services::{set_user_budget, remove_user_budget, get_user_budget_report};
+use crate::utils::response::GenericResponse;
+use crate::utils::errors::AppError;
+use crate::middleware::jwt::{request_claims, JwtMiddleware};
+use crate::AppState;
+
+pub fn init(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/budgets")
+            .service(
+                web::resource("/{period}")
+                    .wrap(JwtMiddleware)
+                    .route(web::get().to(budget_report))
+            )
+            .service(
+                web::resource("/{period}/{category_id}")
+                    .wrap(JwtMiddleware)
+                    .route(web::put().to(put_budget))
+                    .route(web::delete().to(delete_budget))
+            ),
+    );
+}
+
+async fn budget_report(
+    state: web::Data<AppState>,
+    path: web::Path<String>,
+    req: HttpRequest,
+) -> Result<HttpResponse, AppError> {
+    let claims = request_claims(&req)?;
+    let period = parse_period(&path)?;
+
+    let report = get_user_budget_report(&state.db, claims.user_id, period).await?;
+
+    Ok(HttpResponse::Ok().json(GenericResponse {
+        status: StatusCode::OK.as_u16(),
+        data: Some(report),
+        message: "Budgets retrieved successfully".to_string(),
+    }))
+}
+
+async fn put_budget(
+    state: web::Data<AppState>,
+    path: web::Path<(String, i32)>,
+    budget: web::Json<BudgetRequest>,
+    req: HttpRequest,
+) -> Result<HttpResponse, AppError> {
+    budget.validate()?;
+    let claims = request_claims(&req)?;
+    let (period, category_id) = path.into_inner();
+    let period = parse_period(&period)?;
+
+    let budget = set_user_budget(&state.db, claims.user_id, period, category_id, &budget).await?;
+    log::info!("Budget set by user {}: {}", claims.user_id, budget);
+
+    Ok(HttpResponse::Ok().json(GenericResponse {
+        status: StatusCode::OK.as_u16(),
+        data: Some(budget),
+        message: "Budget saved successfully".to_string(),
+    }))
+}
+
+async fn delete_budget(
+    state: web::Data<AppState>,
+    path: web::Path<(String, i32)>,
+    req: HttpRequest,
+) -> Result<HttpResponse, AppError> {
+    let claims = request_claims(&req)?;
+    let (period, category_id) = path.into_inner();
+    let period = parse_period(&period)?;
+
+    remove_user_budget(&state.db, claims.user_id, period, category_id).await?;
+    log::info!("Budget for category {} in {} deleted by user {}", category_id, period.format("%Y-%m"), claims.user_id);
+
+    Ok(HttpResponse::Ok().json(GenericResponse {
+        status: StatusCode::OK.as_u16(),
+        data: None::<()>,
+        message: "Budget deleted successfully".to_string(),
+    }))
+}
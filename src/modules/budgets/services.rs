@@ -0,0 +1,100 @@
+use bigdecimal::BigDecimal;
+use chrono::{Months, NaiveDate};
+use sqlx::PgPool;
+
+use crate::modules::budgets::dtos::BudgetRequest;
+use crate::modules::budgets::models::{Budget, BudgetProgress, BudgetReport};
+use crate::modules::budgets::repositories::{upsert_budget, delete_budget, find_budget_spending_until};
+use crate::modules::categories::models::CategoryKind;
+use crate::modules::categories::repositories::find_category_by_id;
+use crate::utils::errors::AppError;
+
+pub async fn set_user_budget(
+    pool: &PgPool,
+    user_id: i32,
+    period: NaiveDate,
+    category_id: i32,
+    request: &BudgetRequest,
+) -> Result<Budget, AppError> {
+    let category = match find_category_by_id(pool, category_id).await? {
+        Some(category) if category.user_id == user_id => category,
+        _ => return Err(AppError::NotFound(format!("Category {} not found", category_id))),
+    };
+    if category.kind != CategoryKind::Expense {
+        return Err(AppError::invalid_field(
+            "category_id",
+            "kind",
+            "Budgets can only be set on EXPENSE categories",
+        ));
+    }
+
+    Ok(upsert_budget(pool, user_id, category_id, period, &request.amount, request.rollover).await?)
+}
+
+pub async fn remove_user_budget(
+    pool: &PgPool,
+    user_id: i32,
+    period: NaiveDate,
+    category_id: i32,
+) -> Result<(), AppError> {
+    if !delete_budget(pool, user_id, category_id, period).await? {
+        return Err(AppError::NotFound(format!(
+            "No budget for category {} in {}",
+            category_id,
+            period.format("%Y-%m")
+        )));
+    }
+    Ok(())
+}
+
+/// Reports spending against every budget set for `period`. A budget marked `rollover`
+/// carries its unspent amount into the budget of the following month, if one is set;
+/// overspending is never carried.
+pub async fn get_user_budget_report(pool: &PgPool, user_id: i32, period: NaiveDate) -> Result<BudgetReport, AppError> {
+    let zero = BigDecimal::from(0);
+    let mut budgets = Vec::new();
+    let mut carry = zero.clone();
+    let mut previous: Option<(i32, NaiveDate)> = None;
+
+    // Rows come ordered by category and month, so carries can be chained in one pass
+    for row in find_budget_spending_until(pool, user_id, period).await? {
+        let follows = previous.is_some_and(|(category_id, month)| {
+            category_id == row.category_id && month.checked_add_months(Months::new(1)) == Some(row.period)
+        });
+        let carried_over = if follows { carry.clone() } else { zero.clone() };
+        let available = &row.amount + &carried_over;
+        let remaining = &available - &row.spent;
+
+        carry = if row.rollover && remaining > zero { remaining.clone() } else { zero.clone() };
+        previous = Some((row.category_id, row.period));
+
+        if row.period == period {
+            let percent_used = (&row.spent * BigDecimal::from(100) / &available).round(2);
+            budgets.push(BudgetProgress {
+                budget_id: row.budget_id,
+                category_id: row.category_id,
+                category_name: row.category_name,
+                limit: row.amount,
+                rollover: row.rollover,
+                carried_over,
+                available,
+                spent: row.spent,
+                remaining,
+                percent_used,
+            });
+        }
+    }
+    budgets.sort_by_key(|budget| budget.category_name.to_lowercase());
+
+    let total_available = budgets.iter().map(|b| &b.available).sum::<BigDecimal>();
+    let total_spent = budgets.iter().map(|b| &b.spent).sum::<BigDecimal>();
+    let total_remaining = &total_available - &total_spent;
+
+    Ok(BudgetReport {
+        period,
+        budgets,
+        total_available,
+        total_spent,
+        total_remaining,
+    })
+}
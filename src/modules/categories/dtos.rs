@@ -0,0 +1,38 @@
+use serde::Deserialize;
+use validator::{Validate, ValidationError};
+
+use crate::modules::categories::models::CategoryKind;
+
+#[derive(Deserialize, Validate)]
+pub struct CreateCategoryRequest {
+    #[validate(length(min = 1, max = 50, message = "Name must be between 1 and 50 characters"))]
+    pub name: String,
+    pub kind: CategoryKind,
+    pub parent_id: Option<i32>,
+    #[validate(length(min = 1, max = 50, message = "Icon must be between 1 and 50 characters"))]
+    pub icon: Option<String>,
+    #[validate(custom(function = "validate_color"))]
+    pub color: Option<String>,
+}
+
+#[derive(Deserialize, Validate)]
+pub struct UpdateCategoryRequest {
+    #[validate(length(min = 1, max = 50, message = "Name must be between 1 and 50 characters"))]
+    pub name: Option<String>,
+    #[validate(length(min = 1, max = 50, message = "Icon must be between 1 and 50 characters"))]
+    pub icon: Option<String>,
+    #[validate(custom(function = "validate_color"))]
+    pub color: Option<String>,
+}
+
+fn validate_color(color: &str) -> Result<(), ValidationError> {
+    let valid = color.len() == 7
+        && color.starts_with('#')
+        && color[1..].chars().all(|c| c.is_ascii_hexdigit());
+    if !valid {
+        let mut error = ValidationError::new("color");
+        error.message = Some("Color must be a hex code such as #E67E22".into());
+        return Err(error);
+    }
+    Ok(())
+}
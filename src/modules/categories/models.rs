@@ -0,0 +1,70 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, sqlx::Type)]
+#[serde(rename_all = "UPPERCASE")]
+#[sqlx(type_name = "VARCHAR", rename_all = "UPPERCASE")]
+pub enum CategoryKind {
+    Income,
+    Expense,
+}
+
+impl CategoryKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CategoryKind::Income => "INCOME",
+            CategoryKind::Expense => "EXPENSE",
+        }
+    }
+}
+
+impl fmt::Display for CategoryKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+#[derive(Serialize, Deserialize, sqlx::FromRow)]
+pub struct Category {
+    pub id: i32,
+    pub user_id: i32,
+    pub parent_id: Option<i32>,
+    pub name: String,
+    pub kind: CategoryKind,
+    pub icon: Option<String>,
+    pub color: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl fmt::Display for Category {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Category {{ id: {}, user_id: {}, parent_id: {}, name: {}, kind: {} }}",
+            self.id,
+            self.user_id,
+            self.parent_id.map_or_else(|| "None".to_string(), |id| id.to_string()),
+            self.name,
+            self.kind
+        )
+    }
+}
+
+/// A top-level category with its subcategories.
+#[derive(Serialize)]
+pub struct CategoryTree {
+    #[serde(flatten)]
+    pub category: Category,
+    pub children: Vec<Category>,
+}
+
+/// An entry of the category set every new user starts with.
+pub struct DefaultCategory {
+    pub name: &'static str,
+    pub kind: CategoryKind,
+    pub icon: &'static str,
+    pub color: &'static str,
+    pub children: &'static [&'static str],
+}
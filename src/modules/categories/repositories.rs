@@ -0,0 +1,90 @@
+use sqlx::{PgExecutor, PgPool};
+
+use crate::modules::categories::models::{Category, CategoryKind};
+
+pub async fn insert_category(
+    executor: impl PgExecutor<'_>,
+    user_id: i32,
+    parent_id: Option<i32>,
+    name: &str,
+    kind: CategoryKind,
+    icon: Option<&str>,
+    color: Option<&str>,
+) -> Result<Category, sqlx::Error> {
+    sqlx::query_as!(
+        Category,
+        "INSERT INTO categories (user_id, parent_id, name, kind, icon, color) VALUES ($1, $2, $3, $4, $5, $6) RETURNING id, user_id, parent_id, name, kind as \"kind: CategoryKind\", icon, color, created_at, updated_at",
+        user_id,
+        parent_id,
+        name,
+        kind.as_str(),
+        icon,
+        color
+    )
+        .fetch_one(executor)
+        .await
+}
+
+pub async fn find_category_by_id(pool: &PgPool, id: i32) -> Result<Option<Category>, sqlx::Error> {
+    sqlx::query_as!(
+        Category,
+        "SELECT id, user_id, parent_id, name, kind as \"kind: CategoryKind\", icon, color, created_at, updated_at FROM categories WHERE id = $1",
+        id
+    )
+        .fetch_optional(pool)
+        .await
+}
+
+/// All of a user's categories, parents before their children and each level by name.
+pub async fn find_categories_by_user_id(pool: &PgPool, user_id: i32) -> Result<Vec<Category>, sqlx::Error> {
+    sqlx::query_as!(
+        Category,
+        "SELECT id, user_id, parent_id, name, kind as \"kind: CategoryKind\", icon, color, created_at, updated_at FROM categories WHERE user_id = $1 ORDER BY parent_id NULLS FIRST, kind, LOWER(name)",
+        user_id
+    )
+        .fetch_all(pool)
+        .await
+}
+
+pub async fn update_category(
+    pool: &PgPool,
+    id: i32,
+    name: &str,
+    icon: Option<&str>,
+    color: Option<&str>,
+) -> Result<Option<Category>, sqlx::Error> {
+    sqlx::query_as!(
+        Category,
+        "UPDATE categories SET name = $2, icon = $3, color = $4 WHERE id = $1 RETURNING id, user_id, parent_id, name, kind as \"kind: CategoryKind\", icon, color, created_at, updated_at",
+        id,
+        name,
+        icon,
+        color
+    )
+        .fetch_optional(pool)
+        .await
+}
+
+/// Number of subcategories and of transactions that refer to a category.
+pub async fn count_category_references(pool: &PgPool, id: i32) -> Result<(i64, i64), sqlx::Error> {
+    let record = sqlx::query!(
+        r#"
+        SELECT
+            (SELECT COUNT(*) FROM categories WHERE parent_id = $1) as "children!",
+            (SELECT COUNT(DISTINCT transaction_id) FROM transaction_lines WHERE category_id = $1) as "transactions!"
+        "#,
+        id
+    )
+        .fetch_one(pool)
+        .await?;
+
+    Ok((record.children, record.transactions))
+}
+
+pub async fn delete_category(pool: &PgPool, id: i32) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query!("DELETE FROM categories WHERE id = $1", id)
+        .execute(pool)
+        .await?;
+
+    Ok(result.rows_affected() > 0)
+}
@@ -0,0 +1,111 @@
+use actix_web::{http::StatusCode, web, HttpResponse, HttpRequest};
+use validator::Validate;
+
+use crate::modules::categories::dtos::{CreateCategoryRequest, UpdateCategoryRequest};
+use crate::modules::categories::services::{
+    create_user_category, list_user_categories, get_accessible_category, update_user_category, remove_user_category,
+};
+use crate::utils::response::GenericResponse;
+use crate::utils::errors::AppError;
+use crate::middleware::jwt::{request_claims, JwtMiddleware};
+use crate::AppState;
+
+pub fn init(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/categories")
+            .service(
+                web::resource("")
+                    .wrap(JwtMiddleware)
+                    .route(web::post().to(add_category))
+                    .route(web::get().to(list_categories))
+            )
+            .service(
+                web::resource("/{id}")
+                    .wrap(JwtMiddleware)
+                    .route(web::get().to(get_category))
+                    .route(web::patch().to(patch_category))
+                    .route(web::delete().to(delete_category))
+            ),
+    );
+}
+
+async fn add_category(
+    state: web::Data<AppState>,
+    category: web::Json<CreateCategoryRequest>,
+    req: HttpRequest,
+) -> Result<HttpResponse, AppError> {
+    category.validate()?;
+    let claims = request_claims(&req)?;
+
+    let category = create_user_category(&state.db, claims.user_id, &category).await?;
+    log::info!("Category created for user {}: {}", claims.user_id, category);
+
+    Ok(HttpResponse::Created().json(GenericResponse {
+        status: StatusCode::CREATED.as_u16(),
+        data: Some(category),
+        message: "Category created successfully".to_string(),
+    }))
+}
+
+async fn list_categories(state: web::Data<AppState>, req: HttpRequest) -> Result<HttpResponse, AppError> {
+    let claims = request_claims(&req)?;
+    let categories = list_user_categories(&state.db, claims.user_id).await?;
+
+    Ok(HttpResponse::Ok().json(GenericResponse {
+        status: StatusCode::OK.as_u16(),
+        data: Some(categories),
+        message: "Categories retrieved successfully".to_string(),
+    }))
+}
+
+async fn get_category(
+    state: web::Data<AppState>,
+    path: web::Path<i32>,
+    req: HttpRequest,
+) -> Result<HttpResponse, AppError> {
+    let claims = request_claims(&req)?;
+    let category = get_accessible_category(&state.db, &claims, path.into_inner()).await?;
+
+    Ok(HttpResponse::Ok().json(GenericResponse {
+        status: StatusCode::OK.as_u16(),
+        data: Some(category),
+        message: "Category retrieved successfully".to_string(),
+    }))
+}
+
+async fn patch_category(
+    state: web::Data<AppState>,
+    path: web::Path<i32>,
+    category: web::Json<UpdateCategoryRequest>,
+    req: HttpRequest,
+) -> Result<HttpResponse, AppError> {
+    category.validate()?;
+    let claims = request_claims(&req)?;
+
+    let category = update_user_category(&state.db, &claims, path.into_inner(), &category).await?;
+    log::info!("Category updated by user {}: {}", claims.user_id, category);
+
+    Ok(HttpResponse::Ok().json(GenericResponse {
+        status: StatusCode::OK.as_u16(),
+        data: Some(category),
+        message: "Category updated successfully".to_string(),
+    }))
+}
+
+async fn delete_category(
+    state: web::Data<AppState>,
+    path: web::Path<i32>,
+    req: HttpRequest,
+) -> Result<HttpResponse, AppError> {
+    let claims = request_claims(&req)?;
+    let id = path.into_inner();
+
+    remove_user_category(&state.db, &claims, id).await?;
+    log::info!("Category {} deleted by user {}", id, claims.user_id);
+
+    Ok(HttpResponse::Ok().json(GenericResponse {
+        status: StatusCode::OK.as_u16(),
+        data: None::<()>,
+        message: "Category deleted successfully".to_string(),
+    }))
+}
@@ -0,0 +1,184 @@
+use log::warn;
+use sqlx::{PgConnection, PgPool};
+
+use crate::modules::categories::dtos::{CreateCategoryRequest, UpdateCategoryRequest};
+use crate::modules::categories::models::{Category, CategoryKind, CategoryTree, DefaultCategory};
+use crate::modules::categories::repositories::{
+    insert_category, find_category_by_id, find_categories_by_user_id, update_category, count_category_references,
+    delete_category,
+};
+use crate::modules::transactions::models::TransactionKind;
+use crate::utils::errors::AppError;
+use crate::utils::jwt::Claims;
+
+/// The categories every user starts with.
+const DEFAULT_CATEGORIES: &[DefaultCategory] = &[
+    DefaultCategory { name: "Food", kind: CategoryKind::Expense, icon: "utensils", color: "#E67E22", children: &["Groceries", "Restaurants"] },
+    DefaultCategory { name: "Housing", kind: CategoryKind::Expense, icon: "home", color: "#8E44AD", children: &["Rent", "Utilities"] },
+    DefaultCategory { name: "Transportation", kind: CategoryKind::Expense, icon: "car", color: "#2980B9", children: &["Fuel", "Public Transit"] },
+    DefaultCategory { name: "Health", kind: CategoryKind::Expense, icon: "heart", color: "#C0392B", children: &[] },
+    DefaultCategory { name: "Entertainment", kind: CategoryKind::Expense, icon: "film", color: "#16A085", children: &[] },
+    DefaultCategory { name: "Shopping", kind: CategoryKind::Expense, icon: "shopping-bag", color: "#D35400", children: &[] },
+    DefaultCategory { name: "Other Expenses", kind: CategoryKind::Expense, icon: "more-horizontal", color: "#7F8C8D", children: &[] },
+    DefaultCategory { name: "Salary", kind: CategoryKind::Income, icon: "briefcase", color: "#27AE60", children: &[] },
+    DefaultCategory { name: "Other Income", kind: CategoryKind::Income, icon: "plus-circle", color: "#2ECC71", children: &[] },
+];
+
+/// Creates the default category set for a newly registered user.
+pub async fn seed_default_categories(conn: &mut PgConnection, user_id: i32) -> Result<(), sqlx::Error> {
+    for default in DEFAULT_CATEGORIES {
+        let parent = insert_category(
+            &mut *conn,
+            user_id,
+            None,
+            default.name,
+            default.kind,
+            Some(default.icon),
+            Some(default.color),
+        )
+            .await?;
+
+        for child in default.children {
+            insert_category(&mut *conn, user_id, Some(parent.id), child, default.kind, None, None).await?;
+        }
+    }
+    Ok(())
+}
+
+pub async fn create_user_category(
+    pool: &PgPool,
+    user_id: i32,
+    request: &CreateCategoryRequest,
+) -> Result<Category, AppError> {
+    if let Some(parent_id) = request.parent_id {
+        let parent = match find_category_by_id(pool, parent_id).await? {
+            Some(parent) if parent.user_id == user_id => parent,
+            _ => return Err(AppError::invalid_field("parent_id", "category", "Category not found")),
+        };
+        if parent.parent_id.is_some() {
+            return Err(AppError::invalid_field(
+                "parent_id",
+                "depth",
+                "Categories can only be nested one level deep",
+            ));
+        }
+        if parent.kind != request.kind {
+            return Err(AppError::invalid_field(
+                "parent_id",
+                "kind",
+                "A subcategory must have the same kind as its parent",
+            ));
+        }
+    }
+
+    Ok(insert_category(
+        pool,
+        user_id,
+        request.parent_id,
+        request.name.trim(),
+        request.kind,
+        request.icon.as_deref(),
+        request.color.as_deref(),
+    )
+        .await?)
+}
+
+/// Lists a user's categories as top-level categories with their subcategories.
+pub async fn list_user_categories(pool: &PgPool, user_id: i32) -> Result<Vec<CategoryTree>, AppError> {
+    let mut trees: Vec<CategoryTree> = Vec::new();
+    for category in find_categories_by_user_id(pool, user_id).await? {
+        match category.parent_id {
+            None => trees.push(CategoryTree { category, children: Vec::new() }),
+            Some(parent_id) => {
+                if let Some(tree) = trees.iter_mut().find(|tree| tree.category.id == parent_id) {
+                    tree.children.push(category);
+                }
+            }
+        }
+    }
+    Ok(trees)
+}
+
+/// Loads category `id` for its owner or an admin; other users get 403, not 404.
+pub async fn get_accessible_category(pool: &PgPool, claims: &Claims, id: i32) -> Result<Category, AppError> {
+    let category = find_category_by_id(pool, id)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("Category {} not found", id)))?;
+
+    if category.user_id != claims.user_id && !claims.is_admin() {
+        warn!("User {} attempted to access category {} owned by user {}", claims.user_id, id, category.user_id);
+        return Err(AppError::Forbidden("You do not have access to this category".to_string()));
+    }
+
+    Ok(category)
+}
+
+pub async fn update_user_category(
+    pool: &PgPool,
+    claims: &Claims,
+    id: i32,
+    request: &UpdateCategoryRequest,
+) -> Result<Category, AppError> {
+    let existing = get_accessible_category(pool, claims, id).await?;
+
+    let name = request.name.as_deref().map(str::trim).unwrap_or(&existing.name);
+    let icon = request.icon.as_deref().or(existing.icon.as_deref());
+    let color = request.color.as_deref().or(existing.color.as_deref());
+
+    update_category(pool, id, name, icon, color)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("Category {} not found", id)))
+}
+
+/// Deletes a category that has neither subcategories nor transactions.
+pub async fn remove_user_category(pool: &PgPool, claims: &Claims, id: i32) -> Result<(), AppError> {
+    get_accessible_category(pool, claims, id).await?;
+
+    let (children, transactions) = count_category_references(pool, id).await?;
+    if children > 0 {
+        return Err(AppError::Conflict("Category still has subcategories".to_string()));
+    }
+    if transactions > 0 {
+        return Err(AppError::Conflict(format!("Category is used by {} transactions", transactions)));
+    }
+
+    if !delete_category(pool, id).await? {
+        return Err(AppError::NotFound(format!("Category {} not found", id)));
+    }
+    Ok(())
+}
+
+/// Checks that a transaction of `kind` for `owner_id` may be filed under `category_id`:
+/// the category must belong to that user and match income vs. expense. Adjustments may
+/// use either kind; transfers are not categorized.
+pub async fn ensure_category_accepts_transactions(
+    pool: &PgPool,
+    owner_id: i32,
+    category_id: i32,
+    kind: TransactionKind,
+) -> Result<Category, AppError> {
+    let category = match find_category_by_id(pool, category_id).await? {
+        Some(category) if category.user_id == owner_id => category,
+        _ => return Err(AppError::invalid_field("category_id", "category", "Category not found")),
+    };
+
+    let expected = match kind {
+        TransactionKind::Income => Some(CategoryKind::Income),
+        TransactionKind::Expense => Some(CategoryKind::Expense),
+        TransactionKind::Adjustment => None,
+        TransactionKind::Transfer => {
+            return Err(AppError::invalid_field("category_id", "transfer", "Transfers are not categorized"));
+        }
+    };
+    if let Some(expected) = expected
+        && expected != category.kind
+    {
+        return Err(AppError::invalid_field(
+            "category_id",
+            "kind",
+            &format!("{} transactions need a category of kind {}", kind, expected),
+        ));
+    }
+
+    Ok(category)
+}
@@ -0,0 +1,16 @@
+use serde::Deserialize;
+
+use crate::modules::duplicates::models::DuplicateStatus;
+
+#[derive(Deserialize)]
+pub struct DuplicateListQuery {
+    #[serde(default)]
+    pub status: DuplicateStatus,
+}
+
+/// Which transaction of a pair survives a merge. Defaults to the one imported from a
+/// statement if only one was, and otherwise to the original.
+#[derive(Deserialize)]
+pub struct MergeQuery {
+    pub keep_id: Option<i32>,
+}
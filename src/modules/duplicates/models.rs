@@ -0,0 +1,60 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+use crate::modules::transactions::models::Transaction;
+
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize, sqlx::Type)]
+#[serde(rename_all = "UPPERCASE")]
+#[sqlx(type_name = "VARCHAR", rename_all = "UPPERCASE")]
+pub enum DuplicateStatus {
+    #[default]
+    Pending,
+    Dismissed,
+}
+
+impl DuplicateStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DuplicateStatus::Pending => "PENDING",
+            DuplicateStatus::Dismissed => "DISMISSED",
+        }
+    }
+}
+
+impl fmt::Display for DuplicateStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+/// Two transactions suspected to record the same movement of money; `original_id` is
+/// the older one.
+pub struct DuplicatePair {
+    pub id: i32,
+    pub user_id: i32,
+    pub original_id: i32,
+    pub duplicate_id: i32,
+    pub status: DuplicateStatus,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// A transaction that matches another one's account, kind, amount and (roughly) date;
+/// whether their descriptions are alike is decided afterwards.
+pub struct DuplicateCandidate {
+    pub transaction_id: i32,
+    pub transaction_description: Option<String>,
+    pub other_id: i32,
+    pub other_description: Option<String>,
+}
+
+/// A pair as listed to its owner, with both transactions.
+#[derive(Serialize)]
+pub struct DuplicatePairDetails {
+    pub id: i32,
+    pub status: DuplicateStatus,
+    pub original: Transaction,
+    pub duplicate: Transaction,
+    pub created_at: DateTime<Utc>,
+}
@@ -0,0 +1,205 @@
+use sqlx::PgPool;
+use sqlx::types::Json;
+
+use crate::modules::duplicates::models::{DuplicateCandidate, DuplicatePair, DuplicateStatus};
+use crate::modules::transactions::models::{Transaction, TransactionKind, TransactionSplit};
+
+/// Transactions of the same account, kind and signed amount as any of `ids`, dated at most
+/// `window_days` apart. Transfers are never candidates, and neither are two transactions
+/// that were both imported with a bank id, since those ids tell them apart. Nor are two
+/// that were both posted by recurring schedules, like the days of a daily one.
+pub async fn find_duplicate_candidates(
+    pool: &PgPool,
+    ids: &[i32],
+    window_days: i32,
+) -> Result<Vec<DuplicateCandidate>, sqlx::Error> {
+    sqlx::query_as!(
+        DuplicateCandidate,
+        r#"
+        SELECT
+            t.id as transaction_id,
+            t.description as transaction_description,
+            o.id as other_id,
+            o.description as other_description
+        FROM transactions t
+        JOIN transactions o
+            ON o.account_id = t.account_id
+            AND o.kind = t.kind
+            AND o.amount = t.amount
+            AND o.id <> t.id
+            AND o.occurred_on BETWEEN t.occurred_on - $2::int AND t.occurred_on + $2::int
+        WHERE t.id = ANY($1)
+            AND t.kind <> 'TRANSFER'
+            AND (t.external_id IS NULL OR o.external_id IS NULL)
+            AND NOT (
+                EXISTS (SELECT 1 FROM recurring_occurrences r WHERE r.transaction_id = t.id)
+                AND EXISTS (SELECT 1 FROM recurring_occurrences r WHERE r.transaction_id = o.id)
+            )
+        "#,
+        ids,
+        window_days
+    )
+        .fetch_all(pool)
+        .await
+}
+
+/// Records pairs as pending, leaving pairs flagged before (including dismissed ones)
+/// as they are. Returns the pairs that are new.
+pub async fn insert_duplicate_pairs(
+    pool: &PgPool,
+    user_id: i32,
+    pairs: &[(i32, i32)],
+) -> Result<Vec<(i32, i32)>, sqlx::Error> {
+    let (original_ids, duplicate_ids): (Vec<i32>, Vec<i32>) = pairs.iter().copied().unzip();
+    let records = sqlx::query!(
+        r#"
+        INSERT INTO duplicate_pairs (user_id, original_id, duplicate_id)
+        SELECT $1, original_id, duplicate_id FROM UNNEST($2::int[], $3::int[]) AS p(original_id, duplicate_id)
+        ON CONFLICT (original_id, duplicate_id) DO NOTHING
+        RETURNING original_id, duplicate_id
+        "#,
+        user_id,
+        &original_ids,
+        &duplicate_ids
+    )
+        .fetch_all(pool)
+        .await?;
+
+    Ok(records.into_iter().map(|r| (r.original_id, r.duplicate_id)).collect())
+}
+
+pub async fn find_duplicate_pairs_by_user_id(
+    pool: &PgPool,
+    user_id: i32,
+    status: DuplicateStatus,
+) -> Result<Vec<DuplicatePair>, sqlx::Error> {
+    sqlx::query_as!(
+        DuplicatePair,
+        r#"SELECT id, user_id, original_id, duplicate_id, status as "status: DuplicateStatus", created_at, updated_at FROM duplicate_pairs WHERE user_id = $1 AND status = $2 ORDER BY id"#,
+        user_id,
+        status.as_str()
+    )
+        .fetch_all(pool)
+        .await
+}
+
+pub async fn find_duplicate_pair_by_id(pool: &PgPool, id: i32) -> Result<Option<DuplicatePair>, sqlx::Error> {
+    sqlx::query_as!(
+        DuplicatePair,
+        r#"SELECT id, user_id, original_id, duplicate_id, status as "status: DuplicateStatus", created_at, updated_at FROM duplicate_pairs WHERE id = $1"#,
+        id
+    )
+        .fetch_optional(pool)
+        .await
+}
+
+pub async fn update_duplicate_pair_status(
+    pool: &PgPool,
+    id: i32,
+    status: DuplicateStatus,
+) -> Result<Option<DuplicatePair>, sqlx::Error> {
+    sqlx::query_as!(
+        DuplicatePair,
+        r#"UPDATE duplicate_pairs SET status = $2 WHERE id = $1 RETURNING id, user_id, original_id, duplicate_id, status as "status: DuplicateStatus", created_at, updated_at"#,
+        id,
+        status.as_str()
+    )
+        .fetch_optional(pool)
+        .await
+}
+
+/// Deletes `discard` and fills in what `keep` lacks from it: category, time, bank id and
+/// description; `keep` also gets every tag and attachment `discard` had, and its splits
+/// if `keep` is neither split nor categorized. Occurrences of recurring transactions
+/// posted as `discard` are relinked to `keep`, and every pair `discard` was in goes with it.
+pub async fn merge_transactions(pool: &PgPool, keep: &Transaction, discard: &Transaction) -> Result<Transaction, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+
+    sqlx::query!(
+        "UPDATE recurring_occurrences SET transaction_id = $1 WHERE transaction_id = $2",
+        keep.id,
+        discard.id
+    )
+        .execute(&mut *tx)
+        .await?;
+
+    sqlx::query!(
+        "INSERT INTO transaction_tags (transaction_id, tag_id) SELECT $1, tag_id FROM transaction_tags WHERE transaction_id = $2 ON CONFLICT DO NOTHING",
+        keep.id,
+        discard.id
+    )
+        .execute(&mut *tx)
+        .await?;
+
+    sqlx::query!(
+        "UPDATE attachments SET transaction_id = $1 WHERE transaction_id = $2",
+        keep.id,
+        discard.id
+    )
+        .execute(&mut *tx)
+        .await?;
+
+    // A transaction split by neither takes the other's lines; the amounts are the same
+    sqlx::query!(
+        r#"
+        UPDATE transaction_splits SET transaction_id = $1
+        WHERE transaction_id = $2
+            AND NOT EXISTS (SELECT 1 FROM transaction_splits WHERE transaction_id = $1)
+            AND (SELECT category_id FROM transactions WHERE id = $1) IS NULL
+        "#,
+        keep.id,
+        discard.id
+    )
+        .execute(&mut *tx)
+        .await?;
+
+    // Deleted first, so that its bank id is free to move to the transaction kept
+    sqlx::query!("DELETE FROM transactions WHERE id = $1", discard.id)
+        .execute(&mut *tx)
+        .await?;
+
+    let record = sqlx::query!(
+        r#"
+        UPDATE transactions SET
+            category_id = CASE
+                WHEN EXISTS (SELECT 1 FROM transaction_splits WHERE transaction_id = $1) THEN NULL
+                ELSE COALESCE(category_id, $2)
+            END,
+            occurred_time = COALESCE(occurred_time, $3),
+            external_id = COALESCE(external_id, $4),
+            description = CASE WHEN COALESCE(description, '') = '' THEN $5 ELSE description END
+        WHERE id = $1
+        RETURNING id, user_id, account_id, kind as "kind: TransactionKind", amount, description, category_id, occurred_on, occurred_time, counterpart_id, external_id,
+            ARRAY(SELECT g.name FROM transaction_tags tt JOIN tags g ON g.id = tt.tag_id WHERE tt.transaction_id = transactions.id ORDER BY g.name) as "tags!",
+            COALESCE((SELECT json_agg(json_build_object('category_id', s.category_id, 'amount', s.amount::text, 'memo', s.memo) ORDER BY s.id) FROM transaction_splits s WHERE s.transaction_id = transactions.id), '[]') as "splits!: Json<Vec<TransactionSplit>>",
+            created_at, updated_at
+        "#,
+        keep.id,
+        discard.category_id,
+        discard.occurred_time,
+        discard.external_id,
+        discard.description
+    )
+        .fetch_one(&mut *tx)
+        .await?;
+
+    tx.commit().await?;
+
+    Ok(Transaction {
+        id: record.id,
+        user_id: record.user_id,
+        account_id: record.account_id,
+        kind: record.kind,
+        amount: record.amount,
+        description: record.description,
+        category_id: record.category_id,
+        occurred_on: record.occurred_on,
+        occurred_time: record.occurred_time,
+        counterpart_id: record.counterpart_id,
+        external_id: record.external_id,
+        tags: record.tags,
+        splits: record.splits.0,
+        created_at: record.created_at.expect("created_at is NOT NULL"),
+        updated_at: record.updated_at,
+    })
+}
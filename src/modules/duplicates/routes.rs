@@ -0,0 +1,77 @@
+use actix_web::{http::StatusCode, web, HttpResponse, HttpRequest};
+
+use crate::modules::duplicates::dtos::{DuplicateListQuery, MergeQuery};
+use crate::modules::duplicates::services::{list_user_duplicates, merge_user_duplicate, dismiss_user_duplicate};
+use crate::utils::response::GenericResponse;
+use crate::utils::errors::AppError;
+use crate::middleware::jwt::{request_claims, JwtMiddleware};
+use crate::AppState;
+
+pub fn init(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/duplicates")
+            .service(
+                web::resource("")
+                    .wrap(JwtMiddleware)
+                    .route(web::get().to(list_duplicates))
+            )
+            .service(
+                web::resource("/{id}/merge")
+                    .wrap(JwtMiddleware)
+                    .route(web::post().to(merge_duplicate))
+            )
+            .service(
+                web::resource("/{id}/dismiss")
+                    .wrap(JwtMiddleware)
+                    .route(web::post().to(dismiss_duplicate))
+            ),
+    );
+}
+
+async fn list_duplicates(
+    state: web::Data<AppState>,
+    query: web::Query<DuplicateListQuery>,
+    req: HttpRequest,
+) -> Result<HttpResponse, AppError> {
+    let claims = request_claims(&req)?;
+    let pairs = list_user_duplicates(&state.db, claims.user_id, query.status).await?;
+
+    Ok(HttpResponse::Ok().json(GenericResponse {
+        status: StatusCode::OK.as_u16(),
+        data: Some(pairs),
+        message: "Duplicate transactions retrieved successfully".to_string(),
+    }))
+}
+
+async fn merge_duplicate(
+    state: web::Data<AppState>,
+    path: web::Path<i32>,
+    query: web::Query<MergeQuery>,
+    req: HttpRequest,
+) -> Result<HttpResponse, AppError> {
+    let claims = request_claims(&req)?;
+    let transaction = merge_user_duplicate(&state.db, claims.user_id, path.into_inner(), &query).await?;
+    log::info!("Duplicate transactions merged by user {} into {}", claims.user_id, transaction);
+
+    Ok(HttpResponse::Ok().json(GenericResponse {
+        status: StatusCode::OK.as_u16(),
+        data: Some(transaction),
+        message: "Duplicate transactions merged successfully".to_string(),
+    }))
+}
+
+async fn dismiss_duplicate(
+    state: web::Data<AppState>,
+    path: web::Path<i32>,
+    req: HttpRequest,
+) -> Result<HttpResponse, AppError> {
+    let claims = request_claims(&req)?;
+    let pair = dismiss_user_duplicate(&state.db, claims.user_id, path.into_inner()).await?;
+    log::info!("Duplicate pair {} dismissed by user {}", pair.id, claims.user_id);
+
+    Ok(HttpResponse::Ok().json(GenericResponse {
+        status: StatusCode::OK.as_u16(),
+        data: Some(pair),
+        message: "Duplicate pair dismissed successfully".to_string(),
+    }))
+}
@@ -0,0 +1,169 @@
+use std::collections::{HashMap, HashSet};
+
+use sqlx::PgPool;
+
+use crate::modules::duplicates::dtos::MergeQuery;
+use crate::modules::duplicates::models::{DuplicatePair, DuplicatePairDetails, DuplicateStatus};
+use crate::modules::duplicates::repositories::{
+    find_duplicate_candidates, insert_duplicate_pairs, find_duplicate_pairs_by_user_id, find_duplicate_pair_by_id,
+    update_duplicate_pair_status, merge_transactions,
+};
+use crate::modules::attachments::repositories::count_attachments_by_transaction_id;
+use crate::modules::transactions::models::Transaction;
+use crate::modules::transactions::repositories::find_transactions_by_ids;
+use crate::utils::constants::matching::{DUPLICATE_MIN_WORD_OVERLAP, DUPLICATE_WINDOW_DAYS};
+use crate::utils::constants::uploads::MAX_ATTACHMENTS_PER_TRANSACTION;
+use crate::utils::errors::AppError;
+
+/// Flags every pair formed by one of `ids` and another of the user's transactions in the
+/// same account, of the same kind and amount, dated within a few days of it and with a
+/// similar description. Returns the newly flagged pairs as `(original_id, duplicate_id)`;
+/// pairs flagged before, including dismissed ones, are left as they are.
+pub async fn flag_duplicates(pool: &PgPool, user_id: i32, ids: &[i32]) -> Result<Vec<(i32, i32)>, AppError> {
+    if ids.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let mut pairs: Vec<(i32, i32)> = find_duplicate_candidates(pool, ids, DUPLICATE_WINDOW_DAYS)
+        .await?
+        .into_iter()
+        .filter(|candidate| {
+            descriptions_match(
+                candidate.transaction_description.as_deref(),
+                candidate.other_description.as_deref(),
+            )
+        })
+        .map(|candidate| {
+            (
+                candidate.transaction_id.min(candidate.other_id),
+                candidate.transaction_id.max(candidate.other_id),
+            )
+        })
+        .collect();
+    pairs.sort_unstable();
+    pairs.dedup();
+    if pairs.is_empty() {
+        return Ok(pairs);
+    }
+
+    Ok(insert_duplicate_pairs(pool, user_id, &pairs).await?)
+}
+
+/// Descriptions match when most words of the shorter one appear in the other, so a
+/// hand-typed "Starbucks" matches a bank's "CARD PURCHASE STARBUCKS #1234 SEATTLE". A
+/// missing description matches any other.
+fn descriptions_match(description: Option<&str>, other: Option<&str>) -> bool {
+    let words = description_words(description.unwrap_or_default());
+    let other_words = description_words(other.unwrap_or_default());
+    if words.is_empty() || other_words.is_empty() {
+        return true;
+    }
+
+    let shared = words.intersection(&other_words).count();
+    shared as f64 / words.len().min(other_words.len()) as f64 >= DUPLICATE_MIN_WORD_OVERLAP
+}
+
+fn description_words(description: &str) -> HashSet<String> {
+    description
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect()
+}
+
+pub async fn list_user_duplicates(
+    pool: &PgPool,
+    user_id: i32,
+    status: DuplicateStatus,
+) -> Result<Vec<DuplicatePairDetails>, AppError> {
+    let pairs = find_duplicate_pairs_by_user_id(pool, user_id, status).await?;
+    let ids: Vec<i32> = pairs.iter().flat_map(|pair| [pair.original_id, pair.duplicate_id]).collect();
+    let transactions: HashMap<i32, Transaction> = find_transactions_by_ids(pool, &ids)
+        .await?
+        .into_iter()
+        .map(|transaction| (transaction.id, transaction))
+        .collect();
+
+    Ok(pairs
+        .into_iter()
+        .filter_map(|pair| {
+            Some(DuplicatePairDetails {
+                id: pair.id,
+                status: pair.status,
+                original: transactions.get(&pair.original_id)?.clone(),
+                duplicate: transactions.get(&pair.duplicate_id)?.clone(),
+                created_at: pair.created_at,
+            })
+        })
+        .collect())
+}
+
+/// Loads a pair owned by the user, with both of its transactions.
+async fn get_user_duplicate(pool: &PgPool, user_id: i32, id: i32) -> Result<(DuplicatePair, Transaction, Transaction), AppError> {
+    let pair = find_duplicate_pair_by_id(pool, id)
+        .await?
+        .filter(|pair| pair.user_id == user_id)
+        .ok_or_else(|| AppError::NotFound("Duplicate pair not found".to_string()))?;
+
+    let mut transactions = find_transactions_by_ids(pool, &[pair.original_id, pair.duplicate_id]).await?;
+    transactions.sort_by_key(|transaction| transaction.id);
+    let [original, duplicate]: [Transaction; 2] = transactions
+        .try_into()
+        .map_err(|_| AppError::NotFound("Duplicate pair not found".to_string()))?;
+
+    Ok((pair, original, duplicate))
+}
+
+/// Merges a pair into the transaction named by `keep_id`, deleting the other one after
+/// copying over whatever the kept one lacks. The kept one takes the other's attachments
+/// too, so together they must not have more than a transaction can.
+pub async fn merge_user_duplicate(
+    pool: &PgPool,
+    user_id: i32,
+    id: i32,
+    query: &MergeQuery,
+) -> Result<Transaction, AppError> {
+    let (_, original, duplicate) = get_user_duplicate(pool, user_id, id).await?;
+
+    let keep_original = match query.keep_id {
+        Some(keep_id) if keep_id == original.id => true,
+        Some(keep_id) if keep_id == duplicate.id => false,
+        Some(_) => {
+            return Err(AppError::invalid_field(
+                "keep_id",
+                "pair",
+                "keep_id must be one of the pair's two transactions",
+            ));
+        }
+        // A transaction imported from a statement carries the bank's own record of it
+        None => original.external_id.is_some() || duplicate.external_id.is_none(),
+    };
+    let (keep, discard) = if keep_original { (original, duplicate) } else { (duplicate, original) };
+    let attachments = count_attachments_by_transaction_id(pool, keep.id).await?
+        + count_attachments_by_transaction_id(pool, discard.id).await?;
+    if attachments > MAX_ATTACHMENTS_PER_TRANSACTION {
+        return Err(AppError::Conflict(format!(
+            "Merged, the transactions would have {} attachments, but a transaction can have at most {}",
+            attachments, MAX_ATTACHMENTS_PER_TRANSACTION
+        )));
+    }
+
+    Ok(merge_transactions(pool, &keep, &discard).await?)
+}
+
+/// Marks a pair as not being duplicates, so it is no longer listed as pending and is not
+/// flagged again.
+pub async fn dismiss_user_duplicate(pool: &PgPool, user_id: i32, id: i32) -> Result<DuplicatePairDetails, AppError> {
+    let (pair, original, duplicate) = get_user_duplicate(pool, user_id, id).await?;
+    let pair = update_duplicate_pair_status(pool, pair.id, DuplicateStatus::Dismissed)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Duplicate pair not found".to_string()))?;
+
+    Ok(DuplicatePairDetails {
+        id: pair.id,
+        status: pair.status,
+        original,
+        duplicate,
+        created_at: pair.created_at,
+    })
+}
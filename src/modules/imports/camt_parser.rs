@@ -0,0 +1,345 @@
+use std::str::FromStr;
+
+use bigdecimal::BigDecimal;
+use chrono::{Days, NaiveDate};
+use roxmltree::{Document, Node};
+
+use crate::modules::imports::models::{ImportRow, ParsedStatement, StatementBalances};
+use crate::utils::errors::AppError;
+
+/// Parses an ISO 20022 camt.053 statement or camt.054 notification (any schema version).
+/// Every booked entry becomes a row, with the bank's entry reference as external id.
+/// camt.053 statements also carry the opening and closing balances.
+pub fn parse_camt(content: &str) -> Result<ParsedStatement, AppError> {
+    let document = Document::parse(content)
+        .map_err(|e| AppError::invalid_field("file", "xml", &format!("The file is not valid XML: {}", e)))?;
+    let reports: Vec<Node> = document
+        .descendants()
+        .filter(|node| matches!(node.tag_name().name(), "Stmt" | "Ntfctn"))
+        .collect();
+    if reports.is_empty() {
+        return Err(AppError::invalid_field("file", "camt", "The file is not a camt.053 or camt.054 statement"));
+    }
+
+    let mut statement = ParsedStatement::default();
+    for report in reports {
+        if let Some(currency) = text_at(report, &["Acct", "Ccy"]) {
+            match &statement.currency {
+                Some(previous) if previous != currency => {
+                    return Err(AppError::invalid_field(
+                        "file",
+                        "currency",
+                        "The file holds statements for accounts in different currencies",
+                    ));
+                }
+                _ => statement.currency = Some(currency.to_string()),
+            }
+        }
+
+        let first_row = statement.rows.len();
+        for entry in children(report, "Ntry") {
+            // Pending and informational entries are not on the account yet
+            let status = text_at(entry, &["Sts"]).or_else(|| text_at(entry, &["Sts", "Cd"]));
+            if status.is_some_and(|status| status != "BOOK") {
+                continue;
+            }
+            let line = document.text_pos_at(entry.range().start).row as usize;
+            statement.push(parse_entry(entry, line))?;
+        }
+
+        if let Some(balances) = parse_balances(report, &statement.rows[first_row..])? {
+            // Consecutive statements in one file are checked as one period
+            statement.balances = Some(match statement.balances.take() {
+                Some(previous) => StatementBalances {
+                    from: previous.from,
+                    opening: previous.opening,
+                    to: balances.to,
+                    closing: balances.closing,
+                },
+                None => balances,
+            });
+        }
+    }
+
+    Ok(statement)
+}
+
+fn parse_entry(entry: Node, line: usize) -> ImportRow {
+    let mut row = ImportRow::new(line);
+
+    let booking_date = child(entry, "BookgDt").and_then(date_of);
+    row.value_date = child(entry, "ValDt").and_then(date_of);
+    row.occurred_on = booking_date.or(row.value_date);
+    if row.occurred_on.is_none() {
+        row.errors.push("Booking date is missing".to_string());
+    }
+
+    match signed_amount(entry) {
+        Ok(amount) => row.set_signed_amount(amount),
+        Err(error) => row.errors.push(error),
+    }
+
+    // A batch entry lists its transactions separately; their details only describe the
+    // entry when there is exactly one.
+    let details: Vec<Node> = child(entry, "NtryDtls")
+        .map(|entry_details| children(entry_details, "TxDtls").collect())
+        .unwrap_or_default();
+    let transaction = match details[..] {
+        [transaction] => Some(transaction),
+        _ => None,
+    };
+
+    row.external_id = text_at(entry, &["AcctSvcrRef"])
+        .or_else(|| text_at(entry, &["NtryRef"]))
+        .or_else(|| transaction.and_then(|transaction| text_at(transaction, &["Refs", "AcctSvcrRef"])))
+        .map(|reference| reference.chars().take(255).collect());
+    row.bank_transaction_code = bank_transaction_code(entry);
+
+    // The counterparty is the creditor of money going out and the debtor of money coming in
+    let party = if text_at(entry, &["CdtDbtInd"]) == Some("DBIT") { "Cdtr" } else { "Dbtr" };
+    row.counterparty = transaction
+        .and_then(|transaction| {
+            text_at(transaction, &["RltdPties", party, "Nm"])
+                .or_else(|| text_at(transaction, &["RltdPties", party, "Pty", "Nm"]))
+        })
+        .map(str::to_string);
+
+    let remittance = transaction
+        .and_then(|transaction| {
+            let lines: Vec<&str> = child(transaction, "RmtInf")
+                .map(|info| children(info, "Ustrd").filter_map(text).collect())
+                .unwrap_or_default();
+            if lines.is_empty() {
+                text_at(transaction, &["AddtlTxInf"]).map(str::to_string)
+            } else {
+                Some(lines.join(" "))
+            }
+        })
+        .or_else(|| text_at(entry, &["AddtlNtryInf"]).map(str::to_string));
+
+    let description = match (&row.counterparty, remittance) {
+        (Some(counterparty), Some(remittance)) => format!("{} - {}", counterparty, remittance),
+        (Some(counterparty), None) => counterparty.clone(),
+        (None, Some(remittance)) => remittance,
+        (None, None) => String::new(),
+    };
+    row.description = description.chars().take(255).collect();
+
+    row
+}
+
+/// Reads the `OPBD` (or `PRCD`) and `CLBD` balances and checks that the booked entries
+/// account for the difference between them.
+fn parse_balances(report: Node, rows: &[ImportRow]) -> Result<Option<StatementBalances>, AppError> {
+    let mut opening = None;
+    let mut closing = None;
+    for balance in children(report, "Bal") {
+        let code = text_at(balance, &["Tp", "CdOrPrtry", "Cd"]);
+        let (Ok(amount), Some(date)) = (signed_amount(balance), child(balance, "Dt").and_then(date_of)) else {
+            continue;
+        };
+        match code {
+            // An opening balance holds at the start of its day, a previous day's closing one
+            // at the end of its day, so from the start of the next
+            Some("OPBD") if opening.is_none() => opening = Some((amount, date)),
+            Some("PRCD") if opening.is_none() => opening = Some((amount, date + Days::new(1))),
+            Some("CLBD") => closing = Some((amount, date)),
+            _ => {}
+        }
+    }
+    let (Some((opening, opening_date)), Some((closing, closing_date))) = (opening, closing) else {
+        return Ok(None);
+    };
+
+    let amounts: Option<Vec<BigDecimal>> = rows.iter().map(ImportRow::signed_amount).collect();
+    if let Some(amounts) = amounts {
+        let total: BigDecimal = amounts.iter().sum();
+        if &opening + &total != closing {
+            return Err(AppError::invalid_field(
+                "file",
+                "balance",
+                &format!(
+                    "The statement's entries ({:.2}) don't account for the change from its opening balance ({:.2}) to its closing balance ({:.2})",
+                    total, opening, closing
+                ),
+            ));
+        }
+    }
+
+    Ok(Some(StatementBalances {
+        from: opening_date,
+        to: closing_date,
+        opening,
+        closing,
+    }))
+}
+
+/// `Amt` signed by `CdtDbtInd`: negative for debits.
+fn signed_amount(node: Node) -> Result<BigDecimal, String> {
+    let amount = text_at(node, &["Amt"]).ok_or_else(|| "Amount is missing".to_string())?;
+    let amount = BigDecimal::from_str(amount).map_err(|_| format!("Amount '{}' is not a number", amount))?;
+    match text_at(node, &["CdtDbtInd"]) {
+        Some("CRDT") => Ok(amount),
+        Some("DBIT") => Ok(-amount),
+        _ => Err("Credit/debit indicator is missing".to_string()),
+    }
+}
+
+/// The ISO code as `Domain/Family/SubFamily` (e.g. `PMNT/RCDT/ESCT`), or else the bank's
+/// proprietary code.
+fn bank_transaction_code(entry: Node) -> Option<String> {
+    let code = child(entry, "BkTxCd")?;
+    let iso: Option<Vec<&str>> = [
+        &["Domn", "Cd"][..],
+        &["Domn", "Fmly", "Cd"][..],
+        &["Domn", "Fmly", "SubFmlyCd"][..],
+    ]
+    .iter()
+    .map(|path| text_at(code, path))
+    .collect();
+
+    iso.map(|parts| parts.join("/"))
+        .or_else(|| text_at(code, &["Prtry", "Cd"]).map(str::to_string))
+}
+
+/// A `Dt` or `DtTm` child's date.
+fn date_of(node: Node) -> Option<NaiveDate> {
+    text_at(node, &["Dt"]).or_else(|| text_at(node, &["DtTm"])).and_then(parse_date)
+}
+
+fn parse_date(value: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(value.get(..10)?, "%Y-%m-%d").ok()
+}
+
+fn child<'a, 'input>(node: Node<'a, 'input>, name: &str) -> Option<Node<'a, 'input>> {
+    node.children().find(|child| child.tag_name().name() == name)
+}
+
+fn children<'a, 'input>(node: Node<'a, 'input>, name: &'static str) -> impl Iterator<Item = Node<'a, 'input>> {
+    node.children().filter(move |child| child.tag_name().name() == name)
+}
+
+fn text<'a>(node: Node<'a, '_>) -> Option<&'a str> {
+    node.text().map(str::trim).filter(|text| !text.is_empty())
+}
+
+/// Trimmed text of the element reached by following `path` down from `node`.
+fn text_at<'a>(node: Node<'a, '_>, path: &[&str]) -> Option<&'a str> {
+    path.iter().try_fold(node, |node, name| child(node, name)).and_then(text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::modules::transactions::models::TransactionKind;
+
+    fn amount(value: &str) -> BigDecimal {
+        BigDecimal::from_str(value).unwrap()
+    }
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    /// A camt.053 statement opening at `opening` and closing at `closing`: a card payment
+    /// to a shop, a batch of two incoming transfers and a pending entry.
+    fn statement(opening: &str, closing: &str) -> String {
+        format!(
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<Document xmlns="urn:iso:std:iso:20022:tech:xsd:camt.053.001.08">
+<BkToCstmrStmt><Stmt>
+<Acct><Ccy>EUR</Ccy></Acct>
+<Bal><Tp><CdOrPrtry><Cd>PRCD</Cd></CdOrPrtry></Tp><Amt Ccy="EUR">{opening}</Amt><CdtDbtInd>CRDT</CdtDbtInd><Dt><Dt>2025-02-28</Dt></Dt></Bal>
+<Bal><Tp><CdOrPrtry><Cd>CLBD</Cd></CdOrPrtry></Tp><Amt Ccy="EUR">{closing}</Amt><CdtDbtInd>CRDT</CdtDbtInd><Dt><Dt>2025-03-31</Dt></Dt></Bal>
+<Ntry>
+  <Amt Ccy="EUR">42.10</Amt><CdtDbtInd>DBIT</CdtDbtInd><Sts><Cd>BOOK</Cd></Sts>
+  <BookgDt><Dt>2025-03-03</Dt></BookgDt><ValDt><DtTm>2025-03-02T10:00:00</DtTm></ValDt>
+  <AcctSvcrRef>REF-1</AcctSvcrRef>
+  <BkTxCd><Domn><Cd>PMNT</Cd><Fmly><Cd>CCRD</Cd><SubFmlyCd>POSD</SubFmlyCd></Fmly></Domn></BkTxCd>
+  <NtryDtls><TxDtls>
+    <RltdPties><Cdtr><Pty><Nm>Grocer</Nm></Pty></Cdtr><Dbtr><Nm>Me</Nm></Dbtr></RltdPties>
+    <RmtInf><Ustrd>Card 1234</Ustrd><Ustrd>Groceries</Ustrd></RmtInf>
+  </TxDtls></NtryDtls>
+</Ntry>
+<Ntry>
+  <Amt Ccy="EUR">300.00</Amt><CdtDbtInd>CRDT</CdtDbtInd><Sts>BOOK</Sts>
+  <ValDt><Dt>2025-03-10</Dt></ValDt><NtryRef>BATCH-7</NtryRef>
+  <BkTxCd><Prtry><Cd>NTRF</Cd></Prtry></BkTxCd>
+  <NtryDtls><TxDtls><RltdPties><Dbtr><Nm>Alice</Nm></Dbtr></RltdPties></TxDtls>
+  <TxDtls><RltdPties><Dbtr><Nm>Bob</Nm></Dbtr></RltdPties></TxDtls></NtryDtls>
+  <AddtlNtryInf>Collected transfers</AddtlNtryInf>
+</Ntry>
+<Ntry>
+  <Amt Ccy="EUR">9.99</Amt><CdtDbtInd>DBIT</CdtDbtInd><Sts><Cd>PDNG</Cd></Sts><BookgDt><Dt>2025-03-31</Dt></BookgDt>
+</Ntry>
+</Stmt></BkToCstmrStmt>
+</Document>"#
+        )
+    }
+
+    #[test]
+    fn booked_entries_become_rows() {
+        let statement = parse_camt(&statement("100.00", "357.90")).unwrap();
+        assert_eq!(statement.currency.as_deref(), Some("EUR"));
+
+        let rows = statement.rows;
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0].line, 7);
+        assert_eq!(rows[0].occurred_on, Some(date(2025, 3, 3)));
+        assert_eq!(rows[0].value_date, Some(date(2025, 3, 2)));
+        assert_eq!((rows[0].kind, rows[0].amount.clone()), (Some(TransactionKind::Expense), Some(amount("42.10"))));
+        assert_eq!(rows[0].external_id.as_deref(), Some("REF-1"));
+        assert_eq!(rows[0].bank_transaction_code.as_deref(), Some("PMNT/CCRD/POSD"));
+        assert_eq!(rows[0].counterparty.as_deref(), Some("Grocer"));
+        assert_eq!(rows[0].description, "Grocer - Card 1234 Groceries");
+
+        // A batch is dated by its value date and described by the entry itself
+        assert_eq!(rows[1].occurred_on, Some(date(2025, 3, 10)));
+        assert_eq!(rows[1].kind, Some(TransactionKind::Income));
+        assert_eq!(rows[1].external_id.as_deref(), Some("BATCH-7"));
+        assert_eq!(rows[1].bank_transaction_code.as_deref(), Some("NTRF"));
+        assert_eq!(rows[1].counterparty, None);
+        assert_eq!(rows[1].description, "Collected transfers");
+    }
+
+    #[test]
+    fn balances_cover_the_statement_period() {
+        let balances = parse_camt(&statement("100.00", "357.90")).unwrap().balances.unwrap();
+
+        // The previous day's closing balance holds from the start of the next day
+        assert_eq!(balances.from, date(2025, 3, 1));
+        assert_eq!(balances.to, date(2025, 3, 31));
+        assert_eq!(balances.opening, amount("100.00"));
+        assert_eq!(balances.closing, amount("357.90"));
+    }
+
+    #[test]
+    fn entries_must_account_for_the_balances() {
+        assert!(matches!(parse_camt(&statement("100.00", "400.00")), Err(AppError::Validation(_))));
+    }
+
+    #[test]
+    fn notifications_have_no_balances() {
+        let content = r#"<Document><BkToCstmrDbtCdtNtfctn><Ntfctn>
+<Ntry><Amt>5</Amt><CdtDbtInd>CRDT</CdtDbtInd><BookgDt><Dt>2025-03-01</Dt></BookgDt></Ntry>
+<Ntry><Amt>five</Amt><CdtDbtInd>CRDT</CdtDbtInd></Ntry>
+<Ntry><Amt>5</Amt><BookgDt><Dt>2025-03-01</Dt></BookgDt></Ntry>
+</Ntfctn></BkToCstmrDbtCdtNtfctn></Document>"#;
+
+        let statement = parse_camt(content).unwrap();
+        assert!(statement.balances.is_none());
+        assert!(statement.rows[0].is_valid());
+        assert_eq!(statement.rows[1].errors, ["Booking date is missing", "Amount 'five' is not a number"]);
+        assert_eq!(statement.rows[2].errors, ["Credit/debit indicator is missing"]);
+    }
+
+    #[test]
+    fn other_files_are_rejected() {
+        assert!(matches!(parse_camt("not xml"), Err(AppError::Validation(_))));
+        assert!(matches!(parse_camt("<Document><pain/></Document>"), Err(AppError::Validation(_))));
+
+        let mixed = "<Document><Stmt><Acct><Ccy>EUR</Ccy></Acct></Stmt>\
+            <Stmt><Acct><Ccy>USD</Ccy></Acct></Stmt></Document>";
+        assert!(matches!(parse_camt(mixed), Err(AppError::Validation(_))));
+    }
+}
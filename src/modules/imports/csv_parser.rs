@@ -0,0 +1,331 @@
+use std::str::FromStr;
+
+use bigdecimal::{BigDecimal, Zero};
+use chrono::NaiveDate;
+use csv::{ReaderBuilder, StringRecord, Trim};
+
+use crate::modules::imports::dtos::{AmountSign, ColumnRef, CsvMapping};
+use crate::modules::imports::models::{ImportRow, ParsedStatement};
+use crate::utils::errors::AppError;
+
+enum AmountColumns {
+    Signed(usize, AmountSign),
+    Split { debit: Option<usize>, credit: Option<usize> },
+}
+
+struct Columns {
+    date: usize,
+    description: Option<usize>,
+    category: Option<usize>,
+    external_id: Option<usize>,
+    amount: AmountColumns,
+}
+
+/// Parses decoded CSV text into rows. Problems with a single row are recorded on that row;
+/// a mapping that doesn't fit the file or a malformed file fails the whole upload.
+pub fn parse_csv(content: &str, mapping: &CsvMapping) -> Result<ParsedStatement, AppError> {
+    let content = skip_lines(content, mapping.skip_rows);
+    let mut reader = ReaderBuilder::new()
+        .delimiter(mapping.delimiter as u8)
+        .has_headers(false)
+        .flexible(true)
+        .trim(Trim::All)
+        .from_reader(content.as_bytes());
+    let mut records = reader.records();
+
+    let header = if mapping.has_header {
+        match records.next() {
+            Some(record) => Some(record.map_err(malformed_csv)?),
+            None => return Ok(ParsedStatement::default()),
+        }
+    } else {
+        None
+    };
+    let columns = resolve_columns(mapping, header.as_ref())?;
+
+    let mut statement = ParsedStatement::default();
+    for record in records {
+        let record = record.map_err(malformed_csv)?;
+        if record.iter().all(str::is_empty) {
+            continue;
+        }
+
+        let line = record.position().map_or(0, |position| position.line() as usize) + mapping.skip_rows;
+        statement.push(parse_record(&record, line, &columns, mapping))?;
+    }
+
+    Ok(statement)
+}
+
+fn skip_lines(content: &str, count: usize) -> &str {
+    let mut rest = content;
+    for _ in 0..count {
+        match rest.find('\n') {
+            Some(end) => rest = &rest[end + 1..],
+            None => return "",
+        }
+    }
+    rest
+}
+
+fn malformed_csv(error: csv::Error) -> AppError {
+    AppError::invalid_field("file", "csv", &format!("The file is not valid CSV: {}", error))
+}
+
+fn resolve_columns(mapping: &CsvMapping, header: Option<&StringRecord>) -> Result<Columns, AppError> {
+    let resolve = |column: &ColumnRef, field: &'static str| -> Result<usize, AppError> {
+        match column {
+            ColumnRef::Index(index) => Ok(*index),
+            ColumnRef::Name(name) => header
+                .and_then(|header| {
+                    let name = name.trim().to_lowercase();
+                    header.iter().position(|title| title.to_lowercase() == name)
+                })
+                .ok_or_else(|| {
+                    let message = match header {
+                        Some(_) => format!("Column '{}' is not in the file's header", name),
+                        None => "Columns can only be referenced by name when the file has a header".to_string(),
+                    };
+                    AppError::invalid_field(field, "column", &message)
+                }),
+        }
+    };
+    let resolve_optional = |column: &Option<ColumnRef>, field: &'static str| -> Result<Option<usize>, AppError> {
+        column.as_ref().map(|column| resolve(column, field)).transpose()
+    };
+
+    let amount = match (&mapping.amount_column, &mapping.debit_column, &mapping.credit_column) {
+        (Some(column), None, None) => AmountColumns::Signed(resolve(column, "amount_column")?, mapping.amount_sign),
+        (None, None, None) => {
+            return Err(AppError::invalid_field(
+                "amount_column",
+                "required",
+                "Map either amount_column or debit_column and credit_column",
+            ));
+        }
+        (None, debit, credit) => AmountColumns::Split {
+            debit: resolve_optional(debit, "debit_column")?,
+            credit: resolve_optional(credit, "credit_column")?,
+        },
+        (Some(_), _, _) => {
+            return Err(AppError::invalid_field(
+                "amount_column",
+                "amount_columns",
+                "Map either amount_column or debit_column and credit_column, not both",
+            ));
+        }
+    };
+
+    Ok(Columns {
+        date: resolve(&mapping.date_column, "date_column")?,
+        description: resolve_optional(&mapping.description_column, "description_column")?,
+        category: resolve_optional(&mapping.category_column, "category_column")?,
+        external_id: resolve_optional(&mapping.external_id_column, "external_id_column")?,
+        amount,
+    })
+}
+
+fn parse_record(record: &StringRecord, line: usize, columns: &Columns, mapping: &CsvMapping) -> ImportRow {
+    let mut row = ImportRow::new(line);
+    let field = |index: usize| record.get(index).unwrap_or_default();
+
+    match field(columns.date) {
+        "" => row.errors.push("Date is missing".to_string()),
+        value => match NaiveDate::parse_from_str(value, &mapping.date_format) {
+            Ok(date) => row.occurred_on = Some(date),
+            Err(_) => row
+                .errors
+                .push(format!("Date '{}' is not a valid date in the format '{}'", value, mapping.date_format)),
+        },
+    }
+
+    if let Some(index) = columns.description {
+        row.description = field(index).to_string();
+        if row.description.chars().count() > 255 {
+            row.errors.push("Description cannot exceed 255 characters".to_string());
+        }
+    }
+    if let Some(index) = columns.category {
+        row.category = Some(field(index).to_string()).filter(|name| !name.is_empty());
+    }
+    if let Some(index) = columns.external_id {
+        row.external_id = Some(field(index).to_string()).filter(|id| !id.is_empty());
+        if row.external_id.as_ref().is_some_and(|id| id.chars().count() > 255) {
+            row.errors.push("External id cannot exceed 255 characters".to_string());
+        }
+    }
+
+    let signed = match &columns.amount {
+        AmountColumns::Signed(index, sign) => {
+            parse_amount(field(*index), mapping.decimal_separator).map(|amount| match amount {
+                Some(amount) if *sign == AmountSign::PositiveIsExpense => Some(-amount),
+                amount => amount,
+            })
+        }
+        AmountColumns::Split { debit, credit } => {
+            // Statements often print 0.00 in the column a row doesn't use
+            let read = |column: Option<usize>| match column {
+                Some(index) => parse_amount(field(index), mapping.decimal_separator)
+                    .map(|amount| amount.filter(|amount| !amount.is_zero())),
+                None => Ok(None),
+            };
+            match (read(*debit), read(*credit)) {
+                (Err(error), _) | (_, Err(error)) => Err(error),
+                (Ok(Some(_)), Ok(Some(_))) => Err("Row has both a debit and a credit amount".to_string()),
+                (Ok(Some(debit)), Ok(None)) => Ok(Some(-debit.abs())),
+                (Ok(None), Ok(credit)) => Ok(credit.map(|credit| credit.abs())),
+            }
+        }
+    };
+
+    match signed {
+        Ok(Some(amount)) => row.set_signed_amount(amount),
+        Ok(None) => row.errors.push("Amount is missing".to_string()),
+        Err(error) => row.errors.push(error),
+    }
+
+    row
+}
+
+/// Reads an amount as written on a statement: thousands separators, currency symbols,
+/// a leading `+` and accounting-style parentheses for negatives are accepted. Empty cells
+/// are `None`.
+pub(crate) fn parse_amount(raw: &str, decimal_separator: char) -> Result<Option<BigDecimal>, String> {
+    let thousands_separator = if decimal_separator == ',' { '.' } else { ',' };
+    let (text, negative) = match raw.strip_prefix('(').and_then(|text| text.strip_suffix(')')) {
+        Some(text) => (text, true),
+        None => (raw, false),
+    };
+    let normalized: String = text
+        .chars()
+        .filter(|c| *c != thousands_separator && !c.is_whitespace() && !"$€£¥".contains(*c))
+        .map(|c| if c == decimal_separator { '.' } else { c })
+        .collect();
+    if normalized.is_empty() {
+        return Ok(None);
+    }
+
+    let amount = BigDecimal::from_str(normalized.trim_start_matches('+'))
+        .map_err(|_| format!("Amount '{}' is not a number", raw))?;
+
+    Ok(Some(if negative { -amount } else { amount }))
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::modules::transactions::models::TransactionKind;
+
+    fn mapping(fields: serde_json::Value) -> CsvMapping {
+        serde_json::from_value(fields).unwrap()
+    }
+
+    fn amount(value: &str) -> BigDecimal {
+        BigDecimal::from_str(value).unwrap()
+    }
+
+    #[test]
+    fn named_columns_are_found_in_the_header_after_skipped_lines() {
+        let content = "Bank of Examples\nExported 2025-03-31\nDate,Details,Reference,Amount\n\
+            2025-03-01,Coffee,A1,-3.50\n,,,\n2025-03-02,Salary,A2,2500.00\n";
+        let mapping = mapping(json!({
+            "skip_rows": 2,
+            "date_column": "date",
+            "description_column": " DETAILS ",
+            "external_id_column": "Reference",
+            "amount_column": "Amount",
+        }));
+
+        let rows = parse_csv(content, &mapping).unwrap().rows;
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0].line, 4);
+        assert_eq!(rows[0].occurred_on, NaiveDate::from_ymd_opt(2025, 3, 1));
+        assert_eq!(rows[0].description, "Coffee");
+        assert_eq!(rows[0].external_id.as_deref(), Some("A1"));
+        assert_eq!(rows[0].kind, Some(TransactionKind::Expense));
+        assert_eq!(rows[0].amount, Some(amount("3.50")));
+        assert_eq!(rows[1].line, 6);
+        assert_eq!(rows[1].kind, Some(TransactionKind::Income));
+    }
+
+    #[test]
+    fn columns_are_found_by_position_without_a_header() {
+        let content = "01/03/2025;Rent;-950,00\n";
+        let mapping = mapping(json!({
+            "delimiter": ";",
+            "has_header": false,
+            "date_column": 0,
+            "date_format": "%d/%m/%Y",
+            "description_column": 1,
+            "amount_column": 2,
+            "decimal_separator": ",",
+        }));
+
+        let rows = parse_csv(content, &mapping).unwrap().rows;
+        assert_eq!(rows[0].occurred_on, NaiveDate::from_ymd_opt(2025, 3, 1));
+        assert_eq!(rows[0].description, "Rent");
+        assert_eq!(rows[0].amount, Some(amount("950.00")));
+    }
+
+    #[test]
+    fn mappings_that_do_not_fit_the_file_are_rejected() {
+        let content = "Date,Amount\n2025-03-01,1.00\n";
+        let missing = mapping(json!({ "date_column": "Posted", "amount_column": "Amount" }));
+        assert!(matches!(parse_csv(content, &missing), Err(AppError::Validation(_))));
+
+        let headerless = mapping(json!({ "has_header": false, "date_column": "Date", "amount_column": 1 }));
+        assert!(matches!(parse_csv(content, &headerless), Err(AppError::Validation(_))));
+
+        let no_amount = mapping(json!({ "date_column": 0 }));
+        assert!(matches!(parse_csv(content, &no_amount), Err(AppError::Validation(_))));
+
+        let both = mapping(json!({ "date_column": 0, "amount_column": 1, "debit_column": 1 }));
+        assert!(matches!(parse_csv(content, &both), Err(AppError::Validation(_))));
+    }
+
+    #[test]
+    fn debit_and_credit_columns_sign_the_amount() {
+        let content = "Date,Debit,Credit\n2025-03-01,12.00,0.00\n2025-03-02,,40.00\n\
+            2025-03-03,5.00,5.00\n2025-03-04,,\n";
+        let mapping = mapping(json!({ "date_column": "Date", "debit_column": "Debit", "credit_column": "Credit" }));
+
+        let rows = parse_csv(content, &mapping).unwrap().rows;
+        assert_eq!((rows[0].kind, rows[0].amount.clone()), (Some(TransactionKind::Expense), Some(amount("12.00"))));
+        assert_eq!((rows[1].kind, rows[1].amount.clone()), (Some(TransactionKind::Income), Some(amount("40.00"))));
+        assert_eq!(rows[2].errors, ["Row has both a debit and a credit amount"]);
+        assert_eq!(rows[3].errors, ["Amount is missing"]);
+    }
+
+    #[test]
+    fn credit_card_statements_have_positive_charges() {
+        let content = "Date,Amount\n2025-03-01,20.00\n2025-03-02,-100.00\n";
+        let mapping = mapping(json!({ "date_column": 0, "amount_column": 1, "amount_sign": "POSITIVE_IS_EXPENSE" }));
+
+        let rows = parse_csv(content, &mapping).unwrap().rows;
+        assert_eq!(rows[0].kind, Some(TransactionKind::Expense));
+        assert_eq!(rows[1].kind, Some(TransactionKind::Income));
+    }
+
+    #[test]
+    fn bad_cells_are_errors_on_their_row() {
+        let content = "Date,Amount\n2025-02-30,1.00\n2025-03-01,ten\n2025-03-02,0.00\n";
+        let mapping = mapping(json!({ "date_column": 0, "amount_column": 1 }));
+
+        let rows = parse_csv(content, &mapping).unwrap().rows;
+        assert_eq!(rows[0].errors, ["Date '2025-02-30' is not a valid date in the format '%Y-%m-%d'"]);
+        assert_eq!(rows[1].errors, ["Amount 'ten' is not a number"]);
+        assert_eq!(rows[2].errors, ["Amount cannot be zero"]);
+    }
+
+    #[test]
+    fn amounts_are_read_as_statements_write_them() {
+        assert_eq!(parse_amount("$1,234.56", '.'), Ok(Some(amount("1234.56"))));
+        assert_eq!(parse_amount("1.234,56 €", ','), Ok(Some(amount("1234.56"))));
+        assert_eq!(parse_amount("(45.00)", '.'), Ok(Some(amount("-45.00"))));
+        assert_eq!(parse_amount("+7", '.'), Ok(Some(amount("7"))));
+        assert_eq!(parse_amount("", '.'), Ok(None));
+        assert!(parse_amount("12abc", '.').is_err());
+    }
+}
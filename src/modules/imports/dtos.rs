@@ -0,0 +1,109 @@
+use serde::Deserialize;
+use validator::{Validate, ValidationError};
+
+#[derive(Deserialize)]
+pub struct ImportQuery {
+    pub account_id: i32,
+    #[serde(default)]
+    pub dry_run: bool,
+    /// Import a statement even if its balances don't match the account's.
+    #[serde(default)]
+    pub ignore_balance: bool,
+}
+
+#[derive(Deserialize)]
+pub struct QifQuery {
+    /// Read dates as day/month/year instead of the US month/day/year.
+    #[serde(default)]
+    pub day_first: bool,
+}
+
+/// A CSV column, either by its header name or by its zero-based position.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum ColumnRef {
+    Index(usize),
+    Name(String),
+}
+
+/// Which sign a single amount column uses for money leaving the account.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum AmountSign {
+    /// Bank statements: withdrawals are negative.
+    #[default]
+    NegativeIsExpense,
+    /// Most credit card statements: charges are positive, payments negative.
+    PositiveIsExpense,
+}
+
+/// How the columns of an uploaded CSV file map onto transactions. Amounts come either
+/// from one signed `amount_column` or from separate `debit_column`/`credit_column`s.
+#[derive(Deserialize, Validate)]
+pub struct CsvMapping {
+    #[serde(default = "default_delimiter")]
+    #[validate(custom(function = "validate_delimiter"))]
+    pub delimiter: char,
+    #[serde(default = "default_encoding")]
+    pub encoding: String,
+    #[serde(default = "default_has_header")]
+    pub has_header: bool,
+    /// Lines to skip before the header (or the first row), e.g. a bank's preamble.
+    #[serde(default)]
+    #[validate(range(max = 100, message = "Skip rows cannot exceed 100"))]
+    pub skip_rows: usize,
+    pub date_column: ColumnRef,
+    #[serde(default = "default_date_format")]
+    #[validate(length(min = 1, max = 50, message = "Date format must be between 1 and 50 characters"))]
+    pub date_format: String,
+    pub description_column: Option<ColumnRef>,
+    pub category_column: Option<ColumnRef>,
+    /// A bank reference unique to each transaction, used to skip rows imported before.
+    pub external_id_column: Option<ColumnRef>,
+    pub amount_column: Option<ColumnRef>,
+    #[serde(default)]
+    pub amount_sign: AmountSign,
+    pub debit_column: Option<ColumnRef>,
+    pub credit_column: Option<ColumnRef>,
+    #[serde(default = "default_decimal_separator")]
+    #[validate(custom(function = "validate_decimal_separator"))]
+    pub decimal_separator: char,
+}
+
+fn default_delimiter() -> char {
+    ','
+}
+
+fn default_encoding() -> String {
+    "utf-8".to_string()
+}
+
+fn default_has_header() -> bool {
+    true
+}
+
+fn default_date_format() -> String {
+    "%Y-%m-%d".to_string()
+}
+
+fn default_decimal_separator() -> char {
+    '.'
+}
+
+fn validate_delimiter(delimiter: &char) -> Result<(), ValidationError> {
+    if !delimiter.is_ascii() || delimiter.is_ascii_alphanumeric() || *delimiter == '"' || *delimiter == '\n' {
+        let mut error = ValidationError::new("delimiter");
+        error.message = Some("Delimiter must be a single ASCII punctuation or whitespace character".into());
+        return Err(error);
+    }
+    Ok(())
+}
+
+fn validate_decimal_separator(separator: &char) -> Result<(), ValidationError> {
+    if *separator != '.' && *separator != ',' {
+        let mut error = ValidationError::new("decimal_separator");
+        error.message = Some("Decimal separator must be '.' or ','".into());
+        return Err(error);
+    }
+    Ok(())
+}
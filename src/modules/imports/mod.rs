@@ -0,0 +1,9 @@
+pub mod camt_parser;
+pub mod csv_parser;
+pub mod dtos;
+pub mod models;
+pub mod ofx_parser;
+pub mod qif_parser;
+pub mod repositories;
+pub mod routes;
+pub mod services;
\ No newline at end of file
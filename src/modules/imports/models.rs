@@ -0,0 +1,205 @@
+use bigdecimal::{BigDecimal, Zero};
+use chrono::NaiveDate;
+use serde::Serialize;
+
+use crate::modules::transactions::models::{TransactionDraft, TransactionKind};
+use crate::utils::constants::uploads::MAX_IMPORT_ROWS;
+use crate::utils::errors::AppError;
+use crate::utils::money::{has_money_scale, serialize_amount, serialize_optional_amount};
+
+/// One statement line parsed from an uploaded file. `amount` is the positive API amount
+/// and `kind` the direction it moves money in. Rows with `errors` are never imported, and
+/// neither are `duplicate` ones, whose `external_id` was already imported into the account.
+#[derive(Serialize)]
+pub struct ImportRow {
+    /// Line of the file the row was read from, counting from 1.
+    pub line: usize,
+    pub occurred_on: Option<NaiveDate>,
+    pub kind: Option<TransactionKind>,
+    #[serde(serialize_with = "serialize_optional_amount")]
+    pub amount: Option<BigDecimal>,
+    pub description: String,
+    /// Category name as written in the file, and the category it was matched to.
+    pub category: Option<String>,
+    pub category_id: Option<i32>,
+    /// Tags the row is imported with, from the rule that applied to it.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub applied_rule_id: Option<i32>,
+    /// The bank's identifier for the transaction, such as an OFX FITID.
+    pub external_id: Option<String>,
+    /// Details only some statement formats carry; shown in the preview but not stored.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub value_date: Option<NaiveDate>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub counterparty: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bank_transaction_code: Option<String>,
+    pub duplicate: bool,
+    /// Set once the row has been imported.
+    pub transaction_id: Option<i32>,
+    /// Transactions the imported row may duplicate; they are flagged for review.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub suspected_duplicate_of: Vec<i32>,
+    pub errors: Vec<String>,
+}
+
+impl ImportRow {
+    pub fn new(line: usize) -> Self {
+        ImportRow {
+            line,
+            occurred_on: None,
+            kind: None,
+            amount: None,
+            description: String::new(),
+            category: None,
+            category_id: None,
+            tags: Vec::new(),
+            applied_rule_id: None,
+            external_id: None,
+            value_date: None,
+            counterparty: None,
+            bank_transaction_code: None,
+            duplicate: false,
+            transaction_id: None,
+            suspected_duplicate_of: Vec::new(),
+            errors: Vec::new(),
+        }
+    }
+
+    pub fn is_valid(&self) -> bool {
+        self.errors.is_empty()
+    }
+
+    /// Sets `kind` and `amount` from an amount signed the way statements sign them:
+    /// negative for money leaving the account.
+    pub fn set_signed_amount(&mut self, amount: BigDecimal) {
+        if amount.is_zero() {
+            self.errors.push("Amount cannot be zero".to_string());
+        } else if amount.abs() > BigDecimal::from(1_000_000) {
+            self.errors.push("Amount cannot exceed 1,000,000".to_string());
+        } else if !has_money_scale(&amount) {
+            self.errors.push("Amount cannot have more than 2 decimal places".to_string());
+        } else {
+            self.kind = Some(if amount < BigDecimal::zero() {
+                TransactionKind::Expense
+            } else {
+                TransactionKind::Income
+            });
+            self.amount = Some(amount.abs());
+        }
+    }
+
+    /// The amount signed as it is stored: negative for money leaving the account.
+    pub fn signed_amount(&self) -> Option<BigDecimal> {
+        Some(self.kind?.signed_amount(self.amount.as_ref()?))
+    }
+
+    /// The transaction this row becomes, or `None` if it is not to be imported.
+    pub fn draft(&self, account_id: i32) -> Option<TransactionDraft> {
+        if !self.is_valid() || self.duplicate {
+            return None;
+        }
+        Some(TransactionDraft {
+            account_id,
+            kind: self.kind?,
+            amount: self.amount.clone()?,
+            category_id: self.category_id,
+            description: self.description.clone(),
+            occurred_on: Some(self.occurred_on?),
+            occurred_time: None,
+            external_id: self.external_id.clone(),
+            tags: self.tags.clone(),
+            splits: Vec::new(),
+        })
+    }
+}
+
+/// The signed balances a statement opens and closes with, and the days it covers.
+pub struct StatementBalances {
+    pub from: NaiveDate,
+    pub to: NaiveDate,
+    pub opening: BigDecimal,
+    pub closing: BigDecimal,
+}
+
+/// Rows read from a statement file, with the currency and balances the statement
+/// declares if any.
+#[derive(Default)]
+pub struct ParsedStatement {
+    pub currency: Option<String>,
+    pub balances: Option<StatementBalances>,
+    pub rows: Vec<ImportRow>,
+}
+
+impl ParsedStatement {
+    pub fn push(&mut self, row: ImportRow) -> Result<(), AppError> {
+        if self.rows.len() == MAX_IMPORT_ROWS {
+            return Err(AppError::invalid_field(
+                "file",
+                "rows",
+                &format!("A file can contain at most {} transactions", MAX_IMPORT_ROWS),
+            ));
+        }
+        self.rows.push(row);
+        Ok(())
+    }
+}
+
+/// Outcome of an import. A dry run only parses and validates; otherwise every new row is
+/// imported together, and nothing is imported while any of them has errors.
+#[derive(Serialize)]
+pub struct ImportReport {
+    pub account_id: i32,
+    pub dry_run: bool,
+    pub total_rows: usize,
+    pub valid_rows: usize,
+    pub invalid_rows: usize,
+    pub duplicate_rows: usize,
+    pub imported: usize,
+    /// Imported rows that may duplicate a transaction recorded before or another row.
+    pub suspected_duplicates: usize,
+    pub balance_check: Option<BalanceCheck>,
+    pub rows: Vec<ImportRow>,
+}
+
+impl ImportReport {
+    pub fn new(account_id: i32, dry_run: bool, rows: Vec<ImportRow>) -> Self {
+        let duplicate_rows = rows.iter().filter(|row| row.duplicate).count();
+        let invalid_rows = rows.iter().filter(|row| !row.duplicate && !row.is_valid()).count();
+        ImportReport {
+            account_id,
+            dry_run,
+            total_rows: rows.len(),
+            valid_rows: rows.len() - duplicate_rows - invalid_rows,
+            invalid_rows,
+            duplicate_rows,
+            imported: 0,
+            suspected_duplicates: 0,
+            balance_check: None,
+            rows,
+        }
+    }
+
+    pub fn balance_mismatch(&self) -> bool {
+        self.balance_check.as_ref().is_some_and(|check| !check.matches)
+    }
+}
+
+/// A statement's balances next to the account's: its balance at the start of `from`, and
+/// at the end of `to` once the statement's new rows are imported.
+#[derive(Serialize)]
+pub struct BalanceCheck {
+    pub from: NaiveDate,
+    pub to: NaiveDate,
+    #[serde(serialize_with = "serialize_amount")]
+    pub statement_opening: BigDecimal,
+    #[serde(serialize_with = "serialize_amount")]
+    pub account_opening: BigDecimal,
+    #[serde(serialize_with = "serialize_amount")]
+    pub statement_closing: BigDecimal,
+    #[serde(serialize_with = "serialize_amount")]
+    pub account_closing: BigDecimal,
+    pub matches: bool,
+}
@@ -0,0 +1,184 @@
+use std::collections::HashMap;
+
+use chrono::NaiveDate;
+
+use crate::modules::imports::csv_parser::parse_amount;
+use crate::modules::imports::models::{ImportRow, ParsedStatement};
+use crate::utils::errors::AppError;
+
+/// Parses an OFX/QFX statement, either OFX 1.x (SGML, where leaf elements have no closing
+/// tag) or OFX 2.x (XML). Every `<STMTTRN>` becomes a row, with its FITID as external id.
+pub fn parse_ofx(content: &str) -> Result<ParsedStatement, AppError> {
+    let start = content
+        .to_ascii_uppercase()
+        .find("<OFX>")
+        .ok_or_else(|| AppError::invalid_field("file", "ofx", "The file is not an OFX statement"))?;
+
+    let mut statement = ParsedStatement::default();
+    let mut transaction: Option<(usize, HashMap<String, String>)> = None;
+    let mut line = 1 + content[..start].matches('\n').count();
+    let mut counted_to = start;
+    let mut position = start;
+
+    while let Some(open) = content[position..].find('<') {
+        let tag_start = position + open + 1;
+        let Some(close) = content[tag_start..].find('>') else {
+            break;
+        };
+        let tag = content[tag_start..tag_start + close].trim();
+        position = tag_start + close + 1;
+        let text_end = content[position..].find('<').map_or(content.len(), |end| position + end);
+        let text = content[position..text_end].trim();
+
+        line += content[counted_to..tag_start].matches('\n').count();
+        counted_to = tag_start;
+
+        if tag.starts_with('?') || tag.starts_with('!') {
+            continue;
+        }
+        if let Some(name) = tag.strip_prefix('/') {
+            if name.eq_ignore_ascii_case("STMTTRN")
+                && let Some((line, fields)) = transaction.take()
+            {
+                statement.push(parse_transaction(line, &fields))?;
+            }
+            continue;
+        }
+
+        let name = tag.to_ascii_uppercase();
+        match name.as_str() {
+            "STMTTRN" => {
+                // An unclosed transaction ends where the next one starts
+                if let Some((line, fields)) = transaction.replace((line, HashMap::new())) {
+                    statement.push(parse_transaction(line, &fields))?;
+                }
+            }
+            "CURDEF" if statement.currency.is_none() && !text.is_empty() => {
+                statement.currency = Some(text.to_ascii_uppercase());
+            }
+            _ => {
+                if let Some((_, fields)) = transaction.as_mut()
+                    && !text.is_empty()
+                {
+                    fields.entry(name).or_insert_with(|| unescape(text));
+                }
+            }
+        }
+    }
+    if let Some((line, fields)) = transaction.take() {
+        statement.push(parse_transaction(line, &fields))?;
+    }
+
+    Ok(statement)
+}
+
+fn parse_transaction(line: usize, fields: &HashMap<String, String>) -> ImportRow {
+    let mut row = ImportRow::new(line);
+
+    // DTPOSTED is YYYYMMDD, optionally followed by a time and time zone
+    match fields.get("DTPOSTED") {
+        None => row.errors.push("Date is missing".to_string()),
+        Some(value) => match value.get(..8).and_then(|date| NaiveDate::parse_from_str(date, "%Y%m%d").ok()) {
+            Some(date) => row.occurred_on = Some(date),
+            None => row.errors.push(format!("Date '{}' is not a valid OFX date", value)),
+        },
+    }
+
+    match fields.get("TRNAMT") {
+        None => row.errors.push("Amount is missing".to_string()),
+        Some(value) => {
+            let decimal_separator = if value.contains(',') && !value.contains('.') { ',' } else { '.' };
+            match parse_amount(value, decimal_separator) {
+                Ok(Some(amount)) => row.set_signed_amount(amount),
+                Ok(None) => row.errors.push("Amount is missing".to_string()),
+                Err(error) => row.errors.push(error),
+            }
+        }
+    }
+
+    let description = match (fields.get("NAME"), fields.get("MEMO")) {
+        (Some(name), Some(memo)) if !memo.eq_ignore_ascii_case(name) => format!("{} - {}", name, memo),
+        (Some(name), _) => name.clone(),
+        (None, Some(memo)) => memo.clone(),
+        (None, None) => String::new(),
+    };
+    row.description = description.chars().take(255).collect();
+    row.external_id = fields.get("FITID").map(|id| id.chars().take(255).collect());
+
+    row
+}
+
+fn unescape(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&nbsp;", " ")
+        .replace("&amp;", "&")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use bigdecimal::BigDecimal;
+
+    use super::*;
+    use crate::modules::transactions::models::TransactionKind;
+
+    fn amount(value: &str) -> BigDecimal {
+        BigDecimal::from_str(value).unwrap()
+    }
+
+    #[test]
+    fn sgml_statements_have_unclosed_leaf_elements() {
+        let content = "OFXHEADER:100\nDATA:OFXSGML\n\n<OFX>\n<BANKMSGSRSV1><STMTTRNRS><STMTRS>\n<CURDEF>eur\n\
+            <BANKTRANLIST>\n<STMTTRN>\n<TRNTYPE>DEBIT\n<DTPOSTED>20250301120000[-5:EST]\n<TRNAMT>-42.10\n\
+            <FITID>2025030101\n<NAME>Grocer &amp; Sons\n<MEMO>Card 1234\n</STMTTRN>\n\
+            <STMTTRN>\n<DTPOSTED>20250302\n<TRNAMT>1500,00\n<FITID>2025030202\n<NAME>PAYROLL\n<MEMO>payroll\n\
+            </BANKTRANLIST></STMTRS></STMTTRNRS></BANKMSGSRSV1>\n</OFX>\n";
+
+        let statement = parse_ofx(content).unwrap();
+        assert_eq!(statement.currency.as_deref(), Some("EUR"));
+        let rows = statement.rows;
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0].line, 8);
+        assert_eq!(rows[0].occurred_on, NaiveDate::from_ymd_opt(2025, 3, 1));
+        assert_eq!((rows[0].kind, rows[0].amount.clone()), (Some(TransactionKind::Expense), Some(amount("42.10"))));
+        assert_eq!(rows[0].description, "Grocer & Sons - Card 1234");
+        assert_eq!(rows[0].external_id.as_deref(), Some("2025030101"));
+        // Not closed before the list ends, and with a decimal comma
+        assert_eq!((rows[1].kind, rows[1].amount.clone()), (Some(TransactionKind::Income), Some(amount("1500.00"))));
+        assert_eq!(rows[1].description, "PAYROLL");
+    }
+
+    #[test]
+    fn xml_statements_are_read_the_same_way() {
+        let content = r#"<?xml version="1.0" encoding="UTF-8"?>
+<?OFX OFXHEADER="200" VERSION="220"?>
+<OFX><CREDITCARDMSGSRSV1><CCSTMTTRNRS><CCSTMTRS><CURDEF>USD</CURDEF><BANKTRANLIST>
+<STMTTRN><DTPOSTED>20250305</DTPOSTED><TRNAMT>-9.99</TRNAMT><FITID>X9</FITID><MEMO>Streaming</MEMO></STMTTRN>
+</BANKTRANLIST></CCSTMTRS></CCSTMTTRNRS></CREDITCARDMSGSRSV1></OFX>"#;
+
+        let statement = parse_ofx(content).unwrap();
+        assert_eq!(statement.currency.as_deref(), Some("USD"));
+        assert_eq!(statement.rows.len(), 1);
+        assert_eq!(statement.rows[0].line, 4);
+        assert_eq!(statement.rows[0].description, "Streaming");
+        assert_eq!(statement.rows[0].amount, Some(amount("9.99")));
+    }
+
+    #[test]
+    fn bad_fields_are_errors_on_their_row() {
+        let content = "<OFX><STMTTRN><DTPOSTED>2025<TRNAMT>abc</STMTTRN><STMTTRN><FITID>1</STMTTRN></OFX>";
+
+        let rows = parse_ofx(content).unwrap().rows;
+        assert_eq!(rows[0].errors, ["Date '2025' is not a valid OFX date", "Amount 'abc' is not a number"]);
+        assert_eq!(rows[1].errors, ["Date is missing", "Amount is missing"]);
+    }
+
+    #[test]
+    fn other_files_are_rejected() {
+        assert!(matches!(parse_ofx("Date,Amount\n"), Err(AppError::Validation(_))));
+    }
+}
@@ -0,0 +1,211 @@
+use std::collections::HashMap;
+
+use chrono::NaiveDate;
+
+use crate::modules::imports::csv_parser::parse_amount;
+use crate::modules::imports::models::{ImportRow, ParsedStatement};
+use crate::utils::errors::AppError;
+
+/// Account types whose records are transactions; other sections (account lists,
+/// categories, memorized payees, ...) are skipped.
+const TRANSACTION_TYPES: [&str; 5] = ["bank", "cash", "ccard", "oth a", "oth l"];
+
+/// Parses a QIF file. QIF has no transaction ids, so each row gets one derived from its
+/// date, amount and position among the file's rows with the same date and amount; the same
+/// transaction exported again by the bank gets the same id.
+pub fn parse_qif(content: &str, day_first: bool) -> Result<ParsedStatement, AppError> {
+    let mut statement = ParsedStatement::default();
+    let mut has_transactions = false;
+    let mut in_transactions = false;
+    let mut record: Vec<(usize, &str)> = Vec::new();
+
+    for (index, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        if let Some(header) = line.strip_prefix('!') {
+            let header = header.to_ascii_lowercase();
+            if let Some(kind) = header.strip_prefix("type:") {
+                in_transactions = TRANSACTION_TYPES.contains(&kind.trim());
+                has_transactions |= in_transactions;
+            } else if header.starts_with("account") {
+                in_transactions = false;
+            }
+            continue;
+        }
+        if !in_transactions {
+            continue;
+        }
+
+        if line == "^" {
+            if !record.is_empty() {
+                statement.push(parse_record(&record, day_first))?;
+                record.clear();
+            }
+        } else {
+            record.push((index + 1, line));
+        }
+    }
+    if !record.is_empty() {
+        statement.push(parse_record(&record, day_first))?;
+    }
+    if !has_transactions {
+        return Err(AppError::invalid_field("file", "qif", "The file is not a QIF bank or card statement"));
+    }
+
+    let mut occurrences: HashMap<String, usize> = HashMap::new();
+    for row in statement.rows.iter_mut() {
+        if let (Some(date), Some(kind), Some(amount)) = (row.occurred_on, row.kind, &row.amount) {
+            let key = format!("QIF:{}:{:.2}", date.format("%Y%m%d"), kind.signed_amount(amount));
+            let occurrence = occurrences.entry(key.clone()).or_default();
+            *occurrence += 1;
+            row.external_id = Some(format!("{}:{}", key, occurrence));
+        }
+    }
+
+    Ok(statement)
+}
+
+fn parse_record(record: &[(usize, &str)], day_first: bool) -> ImportRow {
+    let mut row = ImportRow::new(record[0].0);
+    let mut fields: HashMap<char, &str> = HashMap::new();
+    for (_, line) in record {
+        let mut chars = line.chars();
+        if let Some(code) = chars.next() {
+            // Split lines (S, E, $) repeat per split; only the first of each field is kept
+            fields.entry(code).or_insert(chars.as_str().trim());
+        }
+    }
+
+    match fields.get(&'D') {
+        None => row.errors.push("Date is missing".to_string()),
+        Some(value) => match parse_date(value, day_first) {
+            Some(date) => row.occurred_on = Some(date),
+            None => row.errors.push(format!("Date '{}' is not a valid QIF date", value)),
+        },
+    }
+
+    match fields.get(&'T').or(fields.get(&'U')) {
+        None => row.errors.push("Amount is missing".to_string()),
+        Some(value) => match parse_amount(value, '.') {
+            Ok(Some(amount)) => row.set_signed_amount(amount),
+            Ok(None) => row.errors.push("Amount is missing".to_string()),
+            Err(error) => row.errors.push(error),
+        },
+    }
+
+    // Categories look like "Food:Groceries/Class"; transfers name an account as "[Savings]"
+    row.category = fields
+        .get(&'L')
+        .filter(|category| !category.starts_with('['))
+        .and_then(|category| category.split('/').next())
+        .and_then(|category| category.rsplit(':').next())
+        .map(|category| category.trim().to_string())
+        .filter(|category| !category.is_empty());
+
+    let description = match (fields.get(&'P'), fields.get(&'M')) {
+        (Some(payee), Some(memo)) if !memo.eq_ignore_ascii_case(payee) => format!("{} - {}", payee, memo),
+        (Some(payee), _) => payee.to_string(),
+        (None, Some(memo)) => memo.to_string(),
+        (None, None) => String::new(),
+    };
+    row.description = description.chars().take(255).collect();
+
+    row
+}
+
+/// Reads QIF dates such as `3/ 1/24`, `03/01/2024`, `3/1'24` or `2024-03-01`. Month comes
+/// before day unless `day_first` is set, and two-digit years fall in 1970-2069.
+fn parse_date(value: &str, day_first: bool) -> Option<NaiveDate> {
+    let normalized: String = value
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .map(|c| if matches!(c, '\'' | '-' | '.') { '/' } else { c })
+        .collect();
+    let parts: Vec<&str> = normalized.split('/').collect();
+    let [first, second, third] = parts[..] else {
+        return None;
+    };
+
+    if first.len() == 4 {
+        return NaiveDate::from_ymd_opt(first.parse().ok()?, second.parse().ok()?, third.parse().ok()?);
+    }
+    let (month, day) = if day_first { (second, first) } else { (first, second) };
+    let year: i32 = third.parse().ok()?;
+    let year = match third.len() {
+        1 | 2 if year < 70 => 2000 + year,
+        1 | 2 => 1900 + year,
+        _ => year,
+    };
+    NaiveDate::from_ymd_opt(year, month.parse().ok()?, day.parse().ok()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use bigdecimal::BigDecimal;
+
+    use super::*;
+    use crate::modules::transactions::models::TransactionKind;
+
+    fn date(year: i32, month: u32, day: u32) -> Option<NaiveDate> {
+        NaiveDate::from_ymd_opt(year, month, day)
+    }
+
+    #[test]
+    fn bank_records_become_rows() {
+        let content = "!Type:Bank\nD3/ 1/25\nT-1,234.50\nPLandlord\nMMarch rent\nLHousing:Rent/Home\n^\n\
+            D03/02'25\nU2500.00\nPEmployer\nLIncome:Salary\n^\nD3/2/25\nT10.00\nL[Savings]\n^\n";
+
+        let rows = parse_qif(content, false).unwrap().rows;
+        assert_eq!(rows.len(), 3);
+        assert_eq!(rows[0].line, 2);
+        assert_eq!(rows[0].occurred_on, date(2025, 3, 1));
+        assert_eq!(rows[0].kind, Some(TransactionKind::Expense));
+        assert_eq!(rows[0].amount, Some(BigDecimal::from_str("1234.50").unwrap()));
+        assert_eq!(rows[0].description, "Landlord - March rent");
+        assert_eq!(rows[0].category.as_deref(), Some("Rent"));
+        assert_eq!(rows[1].line, 8);
+        assert_eq!(rows[1].occurred_on, date(2025, 3, 2));
+        assert_eq!(rows[1].kind, Some(TransactionKind::Income));
+        assert_eq!(rows[1].category.as_deref(), Some("Salary"));
+        // Transfers name an account, not a category
+        assert_eq!(rows[2].category, None);
+    }
+
+    #[test]
+    fn ids_number_rows_with_the_same_date_and_amount() {
+        let content = "!Type:CCard\nD2025-03-01\nT-5.00\n^\nD2025-03-01\nT-5.00\n^\nD2025-03-01\nT5.00\n^\n";
+
+        let rows = parse_qif(content, false).unwrap().rows;
+        let ids: Vec<_> = rows.iter().map(|row| row.external_id.as_deref()).collect();
+        assert_eq!(
+            ids,
+            [Some("QIF:20250301:-5.00:1"), Some("QIF:20250301:-5.00:2"), Some("QIF:20250301:5.00:1")]
+        );
+    }
+
+    #[test]
+    fn other_sections_are_skipped() {
+        let content = "!Type:Cat\nNFood\nE\n^\n!Account\nNChecking\nTBank\n^\n!Type:Bank\nD1/5/25\nT-3\n^\n\
+            !Type:Memorized\nKC\nT-9\n^\n";
+
+        let rows = parse_qif(content, false).unwrap().rows;
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].occurred_on, date(2025, 1, 5));
+        assert!(matches!(parse_qif("!Type:Cat\nNFood\n^\n", false), Err(AppError::Validation(_))));
+    }
+
+    #[test]
+    fn dates_are_read_in_the_order_asked_for() {
+        assert_eq!(parse_date("3/ 1/25", false), date(2025, 3, 1));
+        assert_eq!(parse_date("3/ 1/25", true), date(2025, 1, 3));
+        assert_eq!(parse_date("12/31'99", false), date(1999, 12, 31));
+        assert_eq!(parse_date("1.2.2024", true), date(2024, 2, 1));
+        assert_eq!(parse_date("2024-02-29", false), date(2024, 2, 29));
+        assert_eq!(parse_date("13/1/25", false), None);
+        assert_eq!(parse_date("3/1", false), None);
+    }
+}
@@ -0,0 +1,38 @@
+use sqlx::PgPool;
+
+use crate::modules::transactions::models::{Transaction, TransactionDraft};
+use crate::modules::transactions::repositories::insert_transaction;
+
+/// Inserts a whole import in one database transaction, so either every row is recorded or none is.
+pub async fn insert_imported_transactions(
+    pool: &PgPool,
+    user_id: i32,
+    drafts: &[TransactionDraft],
+) -> Result<Vec<Transaction>, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+
+    let mut transactions = Vec::with_capacity(drafts.len());
+    for draft in drafts {
+        transactions.push(insert_transaction(&mut tx, user_id, draft).await?);
+    }
+
+    tx.commit().await?;
+    Ok(transactions)
+}
+
+/// The ones among `external_ids` already recorded on the account.
+pub async fn find_imported_external_ids(
+    pool: &PgPool,
+    account_id: i32,
+    external_ids: &[String],
+) -> Result<Vec<String>, sqlx::Error> {
+    let records = sqlx::query!(
+        "SELECT external_id as \"external_id!\" FROM transactions WHERE account_id = $1 AND external_id = ANY($2)",
+        account_id,
+        external_ids
+    )
+        .fetch_all(pool)
+        .await?;
+
+    Ok(records.into_iter().map(|r| r.external_id).collect())
+}
@@ -0,0 +1,183 @@
+use std::collections::HashMap;
+
+use actix_multipart::{Multipart, MultipartError};
+use actix_web::{http::StatusCode, web, HttpResponse, HttpRequest};
+use futures::TryStreamExt;
+use validator::Validate;
+
+use crate::modules::imports::dtos::{CsvMapping, ImportQuery, QifQuery};
+use crate::modules::imports::models::ImportReport;
+use crate::modules::imports::services::{import_camt, import_csv, import_ofx, import_qif};
+use crate::utils::constants::uploads::MAX_UPLOAD_BYTES;
+use crate::utils::response::GenericResponse;
+use crate::utils::errors::AppError;
+use crate::middleware::jwt::{request_claims, JwtMiddleware};
+use crate::AppState;
+
+pub fn init(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/imports")
+            .service(
+                web::resource("/csv")
+                    .wrap(JwtMiddleware)
+                    .route(web::post().to(post_csv_import))
+            )
+            .service(
+                web::resource("/ofx")
+                    .wrap(JwtMiddleware)
+                    .route(web::post().to(post_ofx_import))
+            )
+            .service(
+                web::resource("/qif")
+                    .wrap(JwtMiddleware)
+                    .route(web::post().to(post_qif_import))
+            )
+            .service(
+                web::resource("/camt")
+                    .wrap(JwtMiddleware)
+                    .route(web::post().to(post_camt_import))
+            ),
+    );
+}
+
+async fn post_csv_import(
+    state: web::Data<AppState>,
+    query: web::Query<ImportQuery>,
+    payload: Multipart,
+    req: HttpRequest,
+) -> Result<HttpResponse, AppError> {
+    let claims = request_claims(&req)?;
+    let mut form = read_form(payload).await?;
+
+    let file = take_part(&mut form, "file")?;
+    let mapping: CsvMapping = serde_json::from_slice(&take_part(&mut form, "mapping")?)
+        .map_err(|e| AppError::invalid_field("mapping", "json", &format!("Invalid mapping: {}", e)))?;
+    mapping.validate()?;
+
+    let report = import_csv(&state.db, claims.user_id, &query, &file, &mapping).await?;
+    log::info!(
+        "CSV import for user {} into account {}: {} rows, {} invalid, {} duplicate, {} imported",
+        claims.user_id, report.account_id, report.total_rows, report.invalid_rows, report.duplicate_rows, report.imported
+    );
+
+    Ok(import_response(report, &query))
+}
+
+async fn post_ofx_import(
+    state: web::Data<AppState>,
+    query: web::Query<ImportQuery>,
+    payload: Multipart,
+    req: HttpRequest,
+) -> Result<HttpResponse, AppError> {
+    let claims = request_claims(&req)?;
+    let file = take_part(&mut read_form(payload).await?, "file")?;
+
+    let report = import_ofx(&state.db, claims.user_id, &query, &file).await?;
+    log::info!(
+        "OFX import for user {} into account {}: {} rows, {} invalid, {} duplicate, {} imported",
+        claims.user_id, report.account_id, report.total_rows, report.invalid_rows, report.duplicate_rows, report.imported
+    );
+
+    Ok(import_response(report, &query))
+}
+
+async fn post_qif_import(
+    state: web::Data<AppState>,
+    query: web::Query<ImportQuery>,
+    qif_query: web::Query<QifQuery>,
+    payload: Multipart,
+    req: HttpRequest,
+) -> Result<HttpResponse, AppError> {
+    let claims = request_claims(&req)?;
+    let file = take_part(&mut read_form(payload).await?, "file")?;
+
+    let report = import_qif(&state.db, claims.user_id, &query, &qif_query, &file).await?;
+    log::info!(
+        "QIF import for user {} into account {}: {} rows, {} invalid, {} duplicate, {} imported",
+        claims.user_id, report.account_id, report.total_rows, report.invalid_rows, report.duplicate_rows, report.imported
+    );
+
+    Ok(import_response(report, &query))
+}
+
+async fn post_camt_import(
+    state: web::Data<AppState>,
+    query: web::Query<ImportQuery>,
+    payload: Multipart,
+    req: HttpRequest,
+) -> Result<HttpResponse, AppError> {
+    let claims = request_claims(&req)?;
+    let file = take_part(&mut read_form(payload).await?, "file")?;
+
+    let report = import_camt(&state.db, claims.user_id, &query, &file).await?;
+    log::info!(
+        "camt import for user {} into account {}: {} rows, {} invalid, {} duplicate, {} imported",
+        claims.user_id, report.account_id, report.total_rows, report.invalid_rows, report.duplicate_rows, report.imported
+    );
+
+    Ok(import_response(report, &query))
+}
+
+fn import_response(report: ImportReport, query: &ImportQuery) -> HttpResponse {
+    let (status, message) = if report.dry_run {
+        (StatusCode::OK, "Import preview generated successfully".to_string())
+    } else if report.invalid_rows > 0 {
+        (
+            StatusCode::BAD_REQUEST,
+            format!("{} rows have errors; nothing was imported", report.invalid_rows),
+        )
+    } else if report.balance_mismatch() && !query.ignore_balance {
+        (
+            StatusCode::CONFLICT,
+            "The statement's balances don't match the account's; nothing was imported".to_string(),
+        )
+    } else {
+        (
+            StatusCode::CREATED,
+            format!(
+                "{} transactions imported successfully, {} already imported, {} suspected duplicates to review",
+                report.imported, report.duplicate_rows, report.suspected_duplicates
+            ),
+        )
+    };
+
+    HttpResponse::build(status).json(GenericResponse {
+        status: status.as_u16(),
+        data: Some(report),
+        message,
+    })
+}
+
+/// Reads every part of a multipart form into memory, keyed by field name.
+async fn read_form(mut payload: Multipart) -> Result<HashMap<String, Vec<u8>>, AppError> {
+    let mut form = HashMap::new();
+    let mut total_bytes = 0;
+
+    while let Some(mut field) = payload.try_next().await.map_err(multipart_error)? {
+        let name = field.name().unwrap_or_default().to_string();
+        let mut data = Vec::new();
+        while let Some(chunk) = field.try_next().await.map_err(multipart_error)? {
+            total_bytes += chunk.len();
+            if total_bytes > MAX_UPLOAD_BYTES {
+                return Err(AppError::invalid_field(
+                    "file",
+                    "size",
+                    &format!("Uploads cannot exceed {} MB", MAX_UPLOAD_BYTES / (1024 * 1024)),
+                ));
+            }
+            data.extend_from_slice(&chunk);
+        }
+        form.insert(name, data);
+    }
+
+    Ok(form)
+}
+
+fn take_part(form: &mut HashMap<String, Vec<u8>>, name: &'static str) -> Result<Vec<u8>, AppError> {
+    form.remove(name)
+        .ok_or_else(|| AppError::invalid_field(name, "required", &format!("The form is missing the '{}' part", name)))
+}
+
+fn multipart_error(error: MultipartError) -> AppError {
+    AppError::invalid_field("file", "multipart", &format!("Invalid multipart upload: {}", error))
+}
@@ -0,0 +1,255 @@
+use std::collections::{HashMap, HashSet};
+
+use bigdecimal::BigDecimal;
+use chrono::Days;
+use encoding_rs::{Encoding, WINDOWS_1252};
+use log::warn;
+use sqlx::PgPool;
+
+use crate::modules::accounts::models::Account;
+use crate::modules::accounts::repositories::calculate_account_balance_before;
+use crate::modules::accounts::services::ensure_account_accepts_transactions;
+use crate::modules::categories::models::{Category, CategoryKind};
+use crate::modules::categories::repositories::find_categories_by_user_id;
+use crate::modules::duplicates::services::flag_duplicates;
+use crate::modules::imports::camt_parser::parse_camt;
+use crate::modules::imports::csv_parser::parse_csv;
+use crate::modules::imports::dtos::{CsvMapping, ImportQuery, QifQuery};
+use crate::modules::imports::models::{BalanceCheck, ImportReport, ImportRow, ParsedStatement, StatementBalances};
+use crate::modules::imports::ofx_parser::parse_ofx;
+use crate::modules::imports::qif_parser::parse_qif;
+use crate::modules::imports::repositories::{find_imported_external_ids, insert_imported_transactions};
+use crate::modules::rules::models::{first_action, Rule, RuleSubject};
+use crate::modules::rules::repositories::find_rules_by_user_id;
+use crate::modules::transactions::models::{TransactionDraft, TransactionKind};
+use crate::utils::errors::AppError;
+
+pub async fn import_csv(
+    pool: &PgPool,
+    user_id: i32,
+    query: &ImportQuery,
+    file: &[u8],
+    mapping: &CsvMapping,
+) -> Result<ImportReport, AppError> {
+    let account = ensure_account_accepts_transactions(pool, user_id, query.account_id, "account_id").await?;
+    let content = decode_upload(file, &mapping.encoding)?;
+    let statement = parse_csv(&content, mapping)?;
+
+    import_statement(pool, user_id, query, &account, statement).await
+}
+
+pub async fn import_ofx(
+    pool: &PgPool,
+    user_id: i32,
+    query: &ImportQuery,
+    file: &[u8],
+) -> Result<ImportReport, AppError> {
+    let account = ensure_account_accepts_transactions(pool, user_id, query.account_id, "account_id").await?;
+    let statement = parse_ofx(&decode_statement(file))?;
+
+    import_statement(pool, user_id, query, &account, statement).await
+}
+
+pub async fn import_qif(
+    pool: &PgPool,
+    user_id: i32,
+    query: &ImportQuery,
+    qif_query: &QifQuery,
+    file: &[u8],
+) -> Result<ImportReport, AppError> {
+    let account = ensure_account_accepts_transactions(pool, user_id, query.account_id, "account_id").await?;
+    let statement = parse_qif(&decode_statement(file), qif_query.day_first)?;
+
+    import_statement(pool, user_id, query, &account, statement).await
+}
+
+pub async fn import_camt(
+    pool: &PgPool,
+    user_id: i32,
+    query: &ImportQuery,
+    file: &[u8],
+) -> Result<ImportReport, AppError> {
+    let account = ensure_account_accepts_transactions(pool, user_id, query.account_id, "account_id").await?;
+    // XML declares its own encoding, and camt files are UTF-8 in practice
+    let statement = parse_camt(&decode_statement(file))?;
+
+    import_statement(pool, user_id, query, &account, statement).await
+}
+
+/// Decodes an uploaded file from the encoding named by `label` (any WHATWG label, such as
+/// `utf-8`, `windows-1252` or `iso-8859-1`). A byte order mark overrides the label.
+fn decode_upload(bytes: &[u8], label: &str) -> Result<String, AppError> {
+    let encoding = Encoding::for_label(label.trim().as_bytes())
+        .ok_or_else(|| AppError::invalid_field("encoding", "encoding", &format!("Unknown encoding '{}'", label)))?;
+
+    let (content, encoding, had_errors) = encoding.decode(bytes);
+    if had_errors {
+        return Err(AppError::invalid_field(
+            "file",
+            "encoding",
+            &format!("The file is not valid {}", encoding.name()),
+        ));
+    }
+    Ok(content.into_owned())
+}
+
+/// Decodes a bank statement that doesn't say how it is encoded. Banks write these either
+/// as UTF-8 or in the Windows-1252 code page, which every byte sequence is valid in.
+fn decode_statement(bytes: &[u8]) -> String {
+    decode_upload(bytes, "utf-8").unwrap_or_else(|_| WINDOWS_1252.decode(bytes).0.into_owned())
+}
+
+/// Matches category names and flags rows imported before, then, unless this is a dry run,
+/// imports every new row into the account at once. Nothing is imported while any new row
+/// has errors, or while the statement's balances don't match the account's unless the
+/// caller chose to ignore them.
+async fn import_statement(
+    pool: &PgPool,
+    user_id: i32,
+    query: &ImportQuery,
+    account: &Account,
+    statement: ParsedStatement,
+) -> Result<ImportReport, AppError> {
+    let ParsedStatement { currency, balances, mut rows } = statement;
+    if rows.is_empty() {
+        return Err(AppError::invalid_field("file", "empty", "The file contains no transactions"));
+    }
+    if let Some(currency) = currency
+        && currency != account.currency
+    {
+        return Err(AppError::invalid_field(
+            "file",
+            "currency",
+            &format!("The statement is in {} but the account holds {}", currency, account.currency),
+        ));
+    }
+
+    let categories = find_categories_by_user_id(pool, user_id).await?;
+    let rules = find_rules_by_user_id(pool, user_id).await?;
+    for row in rows.iter_mut() {
+        match_category(row, &categories);
+        apply_rules(row, &rules, account.id);
+    }
+
+    // A row is a duplicate if its id was imported before or appeared earlier in the file
+    let external_ids: Vec<String> = rows.iter().filter_map(|row| row.external_id.clone()).collect();
+    let mut seen: HashSet<String> = find_imported_external_ids(pool, account.id, &external_ids)
+        .await?
+        .into_iter()
+        .collect();
+    for row in rows.iter_mut() {
+        if let Some(external_id) = &row.external_id {
+            row.duplicate = !seen.insert(external_id.clone());
+        }
+    }
+
+    let mut report = ImportReport::new(account.id, query.dry_run, rows);
+    if let Some(balances) = &balances {
+        report.balance_check = Some(check_balances(pool, account, balances, &report.rows).await?);
+    }
+    if report.dry_run || report.invalid_rows > 0 || (report.balance_mismatch() && !query.ignore_balance) {
+        return Ok(report);
+    }
+
+    let (indexes, drafts): (Vec<usize>, Vec<TransactionDraft>) = report
+        .rows
+        .iter()
+        .enumerate()
+        .filter_map(|(index, row)| row.draft(account.id).map(|draft| (index, draft)))
+        .unzip();
+    let transactions = insert_imported_transactions(pool, user_id, &drafts).await?;
+    for (index, transaction) in indexes.into_iter().zip(&transactions) {
+        report.rows[index].transaction_id = Some(transaction.id);
+    }
+    report.imported = transactions.len();
+
+    // A duplicate check that fails doesn't undo the import
+    let ids: Vec<i32> = transactions.iter().map(|transaction| transaction.id).collect();
+    match flag_duplicates(pool, user_id, &ids).await {
+        Ok(pairs) => {
+            let mut suspected: HashMap<i32, Vec<i32>> = HashMap::new();
+            for (original_id, duplicate_id) in pairs {
+                suspected.entry(duplicate_id).or_default().push(original_id);
+            }
+            for row in report.rows.iter_mut() {
+                if let Some(original_ids) = row.transaction_id.and_then(|id| suspected.remove(&id)) {
+                    row.suspected_duplicate_of = original_ids;
+                    report.suspected_duplicates += 1;
+                }
+            }
+        }
+        Err(e) => warn!("Failed to check imported transactions for duplicates: {:?}", e),
+    }
+
+    Ok(report)
+}
+
+/// Compares a statement's opening balance with the account's at the start of the statement,
+/// and its closing balance with what the account would hold at the end of it once the new
+/// rows are imported.
+async fn check_balances(
+    pool: &PgPool,
+    account: &Account,
+    balances: &StatementBalances,
+    rows: &[ImportRow],
+) -> Result<BalanceCheck, AppError> {
+    let account_opening = calculate_account_balance_before(pool, account.id, balances.from).await?;
+    let new_rows: BigDecimal = rows
+        .iter()
+        .filter(|row| !row.duplicate)
+        .filter_map(ImportRow::signed_amount)
+        .sum();
+    let account_closing = calculate_account_balance_before(pool, account.id, balances.to + Days::new(1)).await? + new_rows;
+
+    Ok(BalanceCheck {
+        from: balances.from,
+        to: balances.to,
+        matches: account_opening == balances.opening && account_closing == balances.closing,
+        statement_opening: balances.opening.clone(),
+        account_opening,
+        statement_closing: balances.closing.clone(),
+        account_closing,
+    })
+}
+
+/// Gives a row the tags of the first of the user's rules that applies to it, and its
+/// category when the file didn't name one.
+fn apply_rules(row: &mut ImportRow, rules: &[Rule], account_id: i32) {
+    let (Some(kind), Some(amount)) = (row.kind, &row.amount) else {
+        return;
+    };
+    let subject = RuleSubject {
+        account_id,
+        kind,
+        amount,
+        description: &row.description,
+        category_id: row.category_id,
+        split: false,
+    };
+    if let Some(action) = first_action(rules, &subject) {
+        action.apply(&mut row.category_id, &mut row.tags);
+        row.applied_rule_id = Some(action.rule.id);
+    }
+}
+
+/// Files a row under the user's category of the same name (ignoring case) and of the
+/// row's kind. An unknown or ambiguous name is a row error.
+fn match_category(row: &mut ImportRow, categories: &[Category]) {
+    let (Some(name), Some(kind)) = (&row.category, row.kind) else {
+        return;
+    };
+    let kind = match kind {
+        TransactionKind::Income => CategoryKind::Income,
+        _ => CategoryKind::Expense,
+    };
+
+    let name_key = name.to_lowercase();
+    let mut matches = categories
+        .iter()
+        .filter(|category| category.kind == kind && category.name.to_lowercase() == name_key);
+    match (matches.next(), matches.next()) {
+        (Some(category), None) => row.category_id = Some(category.id),
+        (Some(_), Some(_)) => row.errors.push(format!("Category '{}' matches more than one category", name)),
+        (None, _) => row.errors.push(format!("No {} category named '{}'", kind, name)),
+    }
+}
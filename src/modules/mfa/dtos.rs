@@ -0,0 +1,31 @@
+use serde::Deserialize;
+use validator::Validate;
+
+/// A code from the authenticator app, or one of the recovery codes.
+#[derive(Deserialize, Validate)]
+pub struct CodeRequest {
+    #[validate(length(min = 1, max = 64, message = "Code must be between 1 and 64 characters"))]
+    pub code: String,
+}
+
+#[derive(Deserialize, Validate)]
+pub struct MfaLoginRequest {
+    #[validate(length(min = 1, message = "MFA token cannot be empty"))]
+    pub mfa_token: String,
+    #[validate(length(min = 1, max = 64, message = "Code must be between 1 and 64 characters"))]
+    pub code: String,
+}
+
+#[derive(Deserialize, Validate)]
+pub struct MfaEnrollRequest {
+    #[validate(length(min = 1, message = "MFA token cannot be empty"))]
+    pub mfa_token: String,
+    /// Issued by another admin through `POST /admin/users/{id}/mfa-enrollment-token`.
+    #[validate(length(min = 1, message = "Enrollment token cannot be empty"))]
+    pub enrollment_token: String,
+}
+
+#[derive(Deserialize)]
+pub struct SecuritySettingsRequest {
+    pub require_admin_mfa: bool,
+}
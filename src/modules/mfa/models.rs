@@ -0,0 +1,148 @@
+use serde::Serialize;
+use totp_rs::{Algorithm, Secret, TOTP};
+
+use crate::utils::constants::auth::TOTP_ISSUER;
+
+/// Seconds per TOTP time step, the RFC 6238 default that authenticator apps assume.
+pub const TOTP_STEP_SECONDS: u64 = 30;
+
+/// A user's TOTP authenticator, pending until `enabled_at` is set.
+pub struct UserTotp {
+    pub user_id: i32,
+    pub secret: String,
+    pub enabled_at: Option<chrono::DateTime<chrono::Utc>>,
+    pub last_used_step: Option<i64>,
+}
+
+impl UserTotp {
+    pub fn is_enabled(&self) -> bool {
+        self.enabled_at.is_some()
+    }
+
+    /// The time step `code` is valid for, if it matches this authenticator within one
+    /// step of `now` and is newer than the last code accepted.
+    pub fn matching_step(&self, code: &str, now: u64) -> Option<i64> {
+        let totp = totp_for(&self.secret, "")?;
+        let current = now / TOTP_STEP_SECONDS;
+        (current.saturating_sub(1)..=current + 1)
+            .filter(|step| self.last_used_step.is_none_or(|last| *step as i64 > last))
+            .find(|step| totp.check(code, step * TOTP_STEP_SECONDS))
+            .map(|step| step as i64)
+    }
+}
+
+/// What a user needs to add an authenticator: the secret to type in, and the same as an
+/// `otpauth://` URI to show as a QR code.
+#[derive(Serialize)]
+pub struct TotpEnrollment {
+    pub secret: String,
+    pub otpauth_uri: String,
+}
+
+/// Returned by a login that passed the password check but still needs a second factor.
+/// `mfa_token` identifies the login when the code is sent. With `enrollment_required`,
+/// the user is an admin who must add an authenticator before they can log in, with an
+/// enrollment token from another admin.
+#[derive(Serialize)]
+pub struct MfaChallenge {
+    pub mfa_token: String,
+    pub token_type: &'static str,
+    /// Seconds until `mfa_token` expires.
+    pub expires_in: i64,
+    pub enrollment_required: bool,
+}
+
+/// A login waiting for its second factor, as looked up when the code arrives.
+pub struct MfaChallengeRecord {
+    pub id: i32,
+    pub user_id: i32,
+    pub attempts: i32,
+    pub expires_at: chrono::DateTime<chrono::Utc>,
+}
+
+/// Lets the admin it was issued for add an authenticator while logging in. Works once.
+#[derive(Serialize)]
+pub struct MfaEnrollmentToken {
+    pub user_id: i32,
+    pub enrollment_token: String,
+    /// Seconds until `enrollment_token` expires.
+    pub expires_in: i64,
+}
+
+#[derive(Serialize)]
+pub struct RecoveryCodes {
+    pub recovery_codes: Vec<String>,
+}
+
+#[derive(Serialize)]
+pub struct SecuritySettings {
+    pub require_admin_mfa: bool,
+    pub updated_at: chrono::DateTime<chrono::Utc>,
+}
+
+/// The RFC 6238 generator for a base32 `secret`: SHA-1, six digits, 30-second steps.
+pub fn totp_for(secret: &str, account_name: &str) -> Option<TOTP> {
+    let secret = Secret::Encoded(secret.to_string()).to_bytes().ok()?;
+    Some(TOTP::new_unchecked(
+        Algorithm::SHA1,
+        6,
+        0,
+        TOTP_STEP_SECONDS,
+        secret,
+        Some(TOTP_ISSUER.to_string()),
+        account_name.to_string(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The RFC 6238 test secret, "12345678901234567890", in base32.
+    const SECRET: &str = "GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ";
+    const NOW: u64 = 1_111_111_109;
+
+    fn authenticator(last_used_step: Option<i64>) -> UserTotp {
+        UserTotp { user_id: 1, secret: SECRET.to_string(), enabled_at: None, last_used_step }
+    }
+
+    fn code_for_step(step: u64) -> String {
+        totp_for(SECRET, "").unwrap().generate(step * TOTP_STEP_SECONDS)
+    }
+
+    #[test]
+    fn codes_match_the_rfc_test_vectors() {
+        assert_eq!(authenticator(None).matching_step("287082", 59), Some(1));
+        assert_eq!(authenticator(None).matching_step("081804", NOW), Some((NOW / TOTP_STEP_SECONDS) as i64));
+    }
+
+    #[test]
+    fn codes_one_step_either_side_are_accepted() {
+        let current = NOW / TOTP_STEP_SECONDS;
+        let totp = authenticator(None);
+
+        for step in [current - 1, current, current + 1] {
+            assert_eq!(totp.matching_step(&code_for_step(step), NOW), Some(step as i64));
+        }
+        assert_eq!(totp.matching_step(&code_for_step(current - 2), NOW), None);
+        assert_eq!(totp.matching_step(&code_for_step(current + 2), NOW), None);
+        assert_eq!(totp.matching_step("not a code", NOW), None);
+    }
+
+    #[test]
+    fn codes_cannot_be_replayed() {
+        let current = NOW / TOTP_STEP_SECONDS;
+        let totp = authenticator(Some(current as i64));
+
+        assert_eq!(totp.matching_step(&code_for_step(current), NOW), None);
+        assert_eq!(totp.matching_step(&code_for_step(current - 1), NOW), None);
+        assert_eq!(totp.matching_step(&code_for_step(current + 1), NOW), Some(current as i64 + 1));
+    }
+
+    #[test]
+    fn invalid_secrets_match_nothing() {
+        let totp = UserTotp { secret: "not base32!".to_string(), ..authenticator(None) };
+
+        assert_eq!(totp.matching_step("081804", NOW), None);
+    }
+}
@@ -0,0 +1,289 @@
+use chrono::{DateTime, Utc};
+use sqlx::{PgConnection, PgExecutor, PgPool};
+
+use crate::modules::mfa::models::{MfaChallengeRecord, SecuritySettings, UserTotp};
+
+/// Looks up a user's authenticator and locks it until the end of the transaction, so that
+/// the same code can't be accepted twice at once.
+pub async fn find_user_totp_for_update(conn: &mut PgConnection, user_id: i32) -> Result<Option<UserTotp>, sqlx::Error> {
+    sqlx::query_as!(
+        UserTotp,
+        "SELECT user_id, secret, enabled_at, last_used_step FROM user_totp WHERE user_id = $1 FOR UPDATE",
+        user_id
+    )
+        .fetch_optional(conn)
+        .await
+}
+
+pub async fn is_totp_enabled(executor: impl PgExecutor<'_>, user_id: i32) -> Result<bool, sqlx::Error> {
+    sqlx::query_scalar!(
+        r#"SELECT EXISTS (SELECT 1 FROM user_totp WHERE user_id = $1 AND enabled_at IS NOT NULL) as "enabled!""#,
+        user_id
+    )
+        .fetch_one(executor)
+        .await
+}
+
+/// Stores a new pending secret for a user, replacing any earlier one that was never
+/// confirmed. Returns false, changing nothing, if the user's authenticator is enabled.
+pub async fn upsert_pending_totp(executor: impl PgExecutor<'_>, user_id: i32, secret: &str) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query!(
+        r#"
+        INSERT INTO user_totp (user_id, secret) VALUES ($1, $2)
+        ON CONFLICT (user_id) DO UPDATE
+            SET secret = EXCLUDED.secret, last_used_step = NULL, created_at = NOW()
+            WHERE user_totp.enabled_at IS NULL
+        "#,
+        user_id,
+        secret
+    )
+        .execute(executor)
+        .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+pub async fn enable_totp(executor: impl PgExecutor<'_>, user_id: i32, step: i64) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        "UPDATE user_totp SET enabled_at = NOW(), last_used_step = $2 WHERE user_id = $1",
+        user_id,
+        step
+    )
+        .execute(executor)
+        .await?;
+    Ok(())
+}
+
+pub async fn update_totp_last_used_step(executor: impl PgExecutor<'_>, user_id: i32, step: i64) -> Result<(), sqlx::Error> {
+    sqlx::query!("UPDATE user_totp SET last_used_step = $2 WHERE user_id = $1", user_id, step)
+        .execute(executor)
+        .await?;
+    Ok(())
+}
+
+pub async fn delete_user_totp(executor: impl PgExecutor<'_>, user_id: i32) -> Result<(), sqlx::Error> {
+    sqlx::query!("DELETE FROM user_totp WHERE user_id = $1", user_id)
+        .execute(executor)
+        .await?;
+    Ok(())
+}
+
+/// Replaces all of a user's recovery codes, used or not, with the given hashes.
+pub async fn replace_recovery_codes(conn: &mut PgConnection, user_id: i32, code_hashes: &[String]) -> Result<(), sqlx::Error> {
+    sqlx::query!("DELETE FROM recovery_codes WHERE user_id = $1", user_id)
+        .execute(&mut *conn)
+        .await?;
+    sqlx::query!(
+        "INSERT INTO recovery_codes (user_id, code_hash) SELECT $1, UNNEST($2::TEXT[])",
+        user_id,
+        code_hashes
+    )
+        .execute(&mut *conn)
+        .await?;
+    Ok(())
+}
+
+/// Uses up one of a user's recovery codes. Returns false if no unused code has this hash.
+pub async fn use_recovery_code(executor: impl PgExecutor<'_>, user_id: i32, code_hash: &str) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query!(
+        r#"
+        UPDATE recovery_codes SET used_at = NOW()
+        WHERE id = (
+            SELECT id FROM recovery_codes
+            WHERE user_id = $1 AND code_hash = $2 AND used_at IS NULL
+            LIMIT 1
+        )
+        "#,
+        user_id,
+        code_hash
+    )
+        .execute(executor)
+        .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+pub async fn delete_recovery_codes(executor: impl PgExecutor<'_>, user_id: i32) -> Result<(), sqlx::Error> {
+    sqlx::query!("DELETE FROM recovery_codes WHERE user_id = $1", user_id)
+        .execute(executor)
+        .await?;
+    Ok(())
+}
+
+pub async fn insert_mfa_challenge(
+    executor: impl PgExecutor<'_>,
+    user_id: i32,
+    token_hash: &str,
+    expires_at: DateTime<Utc>,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        "INSERT INTO mfa_challenges (user_id, token_hash, expires_at) VALUES ($1, $2, $3)",
+        user_id,
+        token_hash,
+        expires_at
+    )
+        .execute(executor)
+        .await?;
+    Ok(())
+}
+
+/// Looks up a pending login by the hash of its token and locks it until the end of the
+/// transaction, so that attempts are counted one at a time.
+pub async fn find_mfa_challenge_for_update(
+    conn: &mut PgConnection,
+    token_hash: &str,
+) -> Result<Option<MfaChallengeRecord>, sqlx::Error> {
+    sqlx::query_as!(
+        MfaChallengeRecord,
+        "SELECT id, user_id, attempts, expires_at FROM mfa_challenges WHERE token_hash = $1 FOR UPDATE",
+        token_hash
+    )
+        .fetch_optional(conn)
+        .await
+}
+
+/// Counts a wrong code against a pending login, and returns the attempts made so far.
+pub async fn record_mfa_attempt(executor: impl PgExecutor<'_>, id: i32) -> Result<i32, sqlx::Error> {
+    sqlx::query_scalar!("UPDATE mfa_challenges SET attempts = attempts + 1 WHERE id = $1 RETURNING attempts", id)
+        .fetch_one(executor)
+        .await
+}
+
+pub async fn delete_mfa_challenge(executor: impl PgExecutor<'_>, id: i32) -> Result<(), sqlx::Error> {
+    sqlx::query!("DELETE FROM mfa_challenges WHERE id = $1", id)
+        .execute(executor)
+        .await?;
+    Ok(())
+}
+
+pub async fn insert_mfa_failure(executor: impl PgExecutor<'_>, user_id: i32) -> Result<(), sqlx::Error> {
+    sqlx::query!("INSERT INTO mfa_failures (user_id) VALUES ($1)", user_id)
+        .execute(executor)
+        .await?;
+    Ok(())
+}
+
+pub async fn count_mfa_failures_since(
+    executor: impl PgExecutor<'_>,
+    user_id: i32,
+    since: DateTime<Utc>,
+) -> Result<i64, sqlx::Error> {
+    sqlx::query_scalar!(
+        r#"SELECT COUNT(*) as "count!" FROM mfa_failures WHERE user_id = $1 AND created_at > $2"#,
+        user_id,
+        since
+    )
+        .fetch_one(executor)
+        .await
+}
+
+pub async fn delete_mfa_failures(executor: impl PgExecutor<'_>, user_id: i32) -> Result<(), sqlx::Error> {
+    sqlx::query!("DELETE FROM mfa_failures WHERE user_id = $1", user_id)
+        .execute(executor)
+        .await?;
+    Ok(())
+}
+
+pub async fn purge_mfa_failures_before(pool: &PgPool, before: DateTime<Utc>) -> Result<u64, sqlx::Error> {
+    let result = sqlx::query!("DELETE FROM mfa_failures WHERE created_at <= $1", before)
+        .execute(pool)
+        .await?;
+
+    Ok(result.rows_affected())
+}
+
+pub async fn purge_expired_mfa_challenges(pool: &PgPool) -> Result<u64, sqlx::Error> {
+    let result = sqlx::query!("DELETE FROM mfa_challenges WHERE expires_at < NOW()")
+        .execute(pool)
+        .await?;
+
+    Ok(result.rows_affected())
+}
+
+/// Stores a new enrollment token for a user, in place of any they were issued before.
+pub async fn replace_mfa_enrollment_token(
+    conn: &mut PgConnection,
+    user_id: i32,
+    token_hash: &str,
+    issued_by: i32,
+    expires_at: DateTime<Utc>,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!("DELETE FROM mfa_enrollment_tokens WHERE user_id = $1", user_id)
+        .execute(&mut *conn)
+        .await?;
+    sqlx::query!(
+        "INSERT INTO mfa_enrollment_tokens (user_id, token_hash, issued_by, expires_at) VALUES ($1, $2, $3, $4)",
+        user_id,
+        token_hash,
+        issued_by,
+        expires_at
+    )
+        .execute(conn)
+        .await?;
+    Ok(())
+}
+
+/// Uses up a user's enrollment token if it is theirs and unexpired, and returns whether
+/// it was.
+pub async fn use_mfa_enrollment_token(
+    executor: impl PgExecutor<'_>,
+    user_id: i32,
+    token_hash: &str,
+) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query!(
+        "DELETE FROM mfa_enrollment_tokens WHERE user_id = $1 AND token_hash = $2 AND expires_at > NOW()",
+        user_id,
+        token_hash
+    )
+        .execute(executor)
+        .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+pub async fn purge_expired_mfa_enrollment_tokens(pool: &PgPool) -> Result<u64, sqlx::Error> {
+    let result = sqlx::query!("DELETE FROM mfa_enrollment_tokens WHERE expires_at < NOW()")
+        .execute(pool)
+        .await?;
+
+    Ok(result.rows_affected())
+}
+
+pub async fn get_security_settings(executor: impl PgExecutor<'_>) -> Result<SecuritySettings, sqlx::Error> {
+    sqlx::query_as!(SecuritySettings, "SELECT require_admin_mfa, updated_at FROM security_settings")
+        .fetch_one(executor)
+        .await
+}
+
+pub async fn update_security_settings(
+    executor: impl PgExecutor<'_>,
+    require_admin_mfa: bool,
+) -> Result<SecuritySettings, sqlx::Error> {
+    sqlx::query_as!(
+        SecuritySettings,
+        "UPDATE security_settings SET require_admin_mfa = $1, updated_at = NOW() RETURNING require_admin_mfa, updated_at",
+        require_admin_mfa
+    )
+        .fetch_one(executor)
+        .await
+}
+
+/// Ends every open session of the admins who have no authenticator enabled, and returns
+/// how many were ended.
+pub async fn revoke_sessions_of_admins_without_totp(executor: impl PgExecutor<'_>) -> Result<u64, sqlx::Error> {
+    let result = sqlx::query!(
+        r#"
+        UPDATE sessions SET revoked_at = NOW()
+        WHERE revoked_at IS NULL
+          AND user_id IN (
+              SELECT u.id FROM users u
+              WHERE u.role = 'ADMIN'
+                AND NOT EXISTS (SELECT 1 FROM user_totp t WHERE t.user_id = u.id AND t.enabled_at IS NOT NULL)
+          )
+        "#
+    )
+        .execute(executor)
+        .await?;
+
+    Ok(result.rows_affected())
+}
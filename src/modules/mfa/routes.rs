@@ -0,0 +1,133 @@
+use actix_web::{http::StatusCode, web, HttpRequest, HttpResponse};
+use validator::Validate;
+
+use crate::middleware::jwt::{request_claims, JwtMiddleware};
+use crate::middleware::rbac::RbacMiddleware;
+use crate::modules::mfa::dtos::{CodeRequest, SecuritySettingsRequest};
+use crate::modules::mfa::services::{
+    start_totp_enrollment, confirm_totp_enrollment, disable_totp, regenerate_recovery_codes, read_security_settings,
+    change_security_settings,
+};
+use crate::utils::errors::AppError;
+use crate::utils::response::GenericResponse;
+use crate::utils::roles::Role;
+use crate::AppState;
+
+pub fn init(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/mfa")
+            .service(
+                web::resource("/totp")
+                    .wrap(JwtMiddleware)
+                    .route(web::post().to(enroll))
+            )
+            .service(
+                web::resource("/totp/verify")
+                    .wrap(JwtMiddleware)
+                    .route(web::post().to(verify))
+            )
+            .service(
+                web::resource("/totp/disable")
+                    .wrap(JwtMiddleware)
+                    .route(web::post().to(disable))
+            )
+            .service(
+                web::resource("/recovery-codes")
+                    .wrap(JwtMiddleware)
+                    .route(web::post().to(regenerate))
+            ),
+    );
+    cfg.service(
+        web::resource("/admin/security")
+            .wrap(RbacMiddleware {
+                allowed_roles: vec![Role::ADMIN]
+            })
+            .wrap(JwtMiddleware)
+            .route(web::get().to(get_settings))
+            .route(web::put().to(put_settings))
+    );
+}
+
+async fn enroll(state: web::Data<AppState>, req: HttpRequest) -> Result<HttpResponse, AppError> {
+    let claims = request_claims(&req)?;
+    let enrollment = start_totp_enrollment(&state.db, &claims).await?;
+
+    Ok(HttpResponse::Ok().json(GenericResponse {
+        status: StatusCode::OK.as_u16(),
+        data: Some(enrollment),
+        message: "Add this secret to your authenticator app, then verify a code from it".to_string(),
+    }))
+}
+
+async fn verify(
+    state: web::Data<AppState>,
+    body: web::Json<CodeRequest>,
+    req: HttpRequest,
+) -> Result<HttpResponse, AppError> {
+    body.validate()?;
+    let claims = request_claims(&req)?;
+    let recovery_codes = confirm_totp_enrollment(&state.db, &claims, &body.code).await?;
+
+    Ok(HttpResponse::Ok().json(GenericResponse {
+        status: StatusCode::OK.as_u16(),
+        data: Some(recovery_codes),
+        message: "Two-factor authentication enabled; store the recovery codes somewhere safe".to_string(),
+    }))
+}
+
+async fn disable(
+    state: web::Data<AppState>,
+    body: web::Json<CodeRequest>,
+    req: HttpRequest,
+) -> Result<HttpResponse, AppError> {
+    body.validate()?;
+    let claims = request_claims(&req)?;
+    disable_totp(&state.db, &claims, &body.code).await?;
+
+    Ok(HttpResponse::Ok().json(GenericResponse {
+        status: StatusCode::OK.as_u16(),
+        data: None::<()>,
+        message: "Two-factor authentication disabled".to_string(),
+    }))
+}
+
+async fn regenerate(
+    state: web::Data<AppState>,
+    body: web::Json<CodeRequest>,
+    req: HttpRequest,
+) -> Result<HttpResponse, AppError> {
+    body.validate()?;
+    let claims = request_claims(&req)?;
+    let recovery_codes = regenerate_recovery_codes(&state.db, &claims, &body.code).await?;
+
+    Ok(HttpResponse::Ok().json(GenericResponse {
+        status: StatusCode::OK.as_u16(),
+        data: Some(recovery_codes),
+        message: "Recovery codes regenerated; the old ones no longer work".to_string(),
+    }))
+}
+
+async fn get_settings(state: web::Data<AppState>) -> Result<HttpResponse, AppError> {
+    let settings = read_security_settings(&state.db).await?;
+
+    Ok(HttpResponse::Ok().json(GenericResponse {
+        status: StatusCode::OK.as_u16(),
+        data: Some(settings),
+        message: "Security settings retrieved successfully".to_string(),
+    }))
+}
+
+async fn put_settings(
+    state: web::Data<AppState>,
+    body: web::Json<SecuritySettingsRequest>,
+    req: HttpRequest,
+) -> Result<HttpResponse, AppError> {
+    let claims = request_claims(&req)?;
+    let settings = change_security_settings(&state.db, &claims, body.require_admin_mfa).await?;
+
+    Ok(HttpResponse::Ok().json(GenericResponse {
+        status: StatusCode::OK.as_u16(),
+        data: Some(settings),
+        message: "Security settings updated successfully".to_string(),
+    }))
+}
@@ -0,0 +1,261 @@
+use chrono::{Duration, Utc};
+use sqlx::{PgConnection, PgExecutor, PgPool};
+use log::{info, warn};
+
+use crate::modules::mfa::models::{
+    totp_for, MfaChallenge, MfaEnrollmentToken, RecoveryCodes, SecuritySettings, TotpEnrollment, UserTotp,
+};
+use crate::modules::mfa::repositories::{
+    find_user_totp_for_update, is_totp_enabled, upsert_pending_totp, enable_totp, update_totp_last_used_step,
+    delete_user_totp, replace_recovery_codes, use_recovery_code, delete_recovery_codes, insert_mfa_challenge,
+    get_security_settings, update_security_settings, revoke_sessions_of_admins_without_totp,
+    replace_mfa_enrollment_token,
+};
+use crate::modules::users::repositories::find_user_by_id;
+use crate::utils::constants::auth::{
+    MFA_CHALLENGE_EXPIRY_SECONDS, MFA_ENROLLMENT_TOKEN_EXPIRY_SECONDS, RECOVERY_CODE_COUNT,
+};
+use crate::utils::errors::AppError;
+use crate::utils::jwt::Claims;
+use crate::utils::roles::Role;
+use crate::utils::tokens::{generate_opaque_token, hash_token};
+
+/// Starts adding an authenticator to the caller's account. The secret stays pending, and
+/// logins unaffected, until a code from it is confirmed with `confirm_totp_enrollment`.
+pub async fn start_totp_enrollment(pool: &PgPool, claims: &Claims) -> Result<TotpEnrollment, AppError> {
+    enroll_totp(pool, claims.user_id, &claims.sub).await
+}
+
+/// Generates a new pending TOTP secret for a user, in place of any unconfirmed one.
+pub async fn enroll_totp(executor: impl PgExecutor<'_>, user_id: i32, username: &str) -> Result<TotpEnrollment, AppError> {
+    let secret = totp_rs::Secret::Raw(rand::random::<[u8; 20]>().to_vec()).to_encoded().to_string();
+    if !upsert_pending_totp(executor, user_id, &secret).await? {
+        return Err(AppError::Conflict("Two-factor authentication is already enabled".to_string()));
+    }
+    let totp = totp_for(&secret, username)
+        .ok_or_else(|| AppError::InternalServerError("Failed to generate TOTP secret".to_string()))?;
+
+    Ok(TotpEnrollment {
+        otpauth_uri: totp.get_url(),
+        secret,
+    })
+}
+
+/// Enables the caller's pending authenticator once they send a code from it, and returns
+/// their recovery codes. These are shown only this once.
+pub async fn confirm_totp_enrollment(pool: &PgPool, claims: &Claims, code: &str) -> Result<RecoveryCodes, AppError> {
+    let mut tx = pool.begin().await?;
+    let Some(recovery_codes) = confirm_totp(&mut tx, claims.user_id, code).await? else {
+        return Err(invalid_code());
+    };
+    tx.commit().await?;
+
+    info!("User {} enabled two-factor authentication", claims.user_id);
+    Ok(recovery_codes)
+}
+
+/// Enables a user's pending authenticator if `code` is from it, with a fresh set of
+/// recovery codes. Returns `None` if the code doesn't match.
+pub async fn confirm_totp(conn: &mut PgConnection, user_id: i32, code: &str) -> Result<Option<RecoveryCodes>, AppError> {
+    let totp = match find_user_totp_for_update(conn, user_id).await? {
+        None => {
+            return Err(AppError::Conflict(
+                "No authenticator is being set up; start the enrollment first".to_string(),
+            ))
+        }
+        Some(totp) if totp.is_enabled() => {
+            return Err(AppError::Conflict("Two-factor authentication is already enabled".to_string()))
+        }
+        Some(totp) => totp,
+    };
+    let Some(step) = totp.matching_step(code.trim(), now()) else {
+        return Ok(None);
+    };
+
+    enable_totp(&mut *conn, user_id, step).await?;
+    Ok(Some(issue_recovery_codes(conn, user_id).await?))
+}
+
+/// Checks a second factor for a user with an enabled authenticator: a current code from
+/// it, or one of their unused recovery codes, which is then used up.
+pub async fn verify_second_factor(conn: &mut PgConnection, user_id: i32, code: &str) -> Result<bool, AppError> {
+    let Some(totp) = find_user_totp_for_update(conn, user_id).await?.filter(UserTotp::is_enabled) else {
+        return Ok(false);
+    };
+    if let Some(step) = totp.matching_step(code.trim(), now()) {
+        update_totp_last_used_step(&mut *conn, user_id, step).await?;
+        return Ok(true);
+    }
+
+    let used = use_recovery_code(&mut *conn, user_id, &hash_token(&normalize_recovery_code(code))).await?;
+    if used {
+        info!("User {} used a recovery code", user_id);
+    }
+    Ok(used)
+}
+
+/// Turns off two-factor authentication for the caller, who must prove they still have a
+/// second factor. Admins can't while it is required for them.
+pub async fn disable_totp(pool: &PgPool, claims: &Claims, code: &str) -> Result<(), AppError> {
+    let mut tx = pool.begin().await?;
+    let user = find_user_by_id(&mut *tx, claims.user_id)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("User {} not found", claims.user_id)))?;
+    if user.role == Role::ADMIN && get_security_settings(&mut *tx).await?.require_admin_mfa {
+        return Err(AppError::Conflict("Two-factor authentication is required for admins".to_string()));
+    }
+    if !is_totp_enabled(&mut *tx, user.id).await? {
+        return Err(AppError::Conflict("Two-factor authentication is not enabled".to_string()));
+    }
+    if !verify_second_factor(&mut tx, user.id, code).await? {
+        return Err(invalid_code());
+    }
+
+    delete_user_totp(&mut *tx, user.id).await?;
+    delete_recovery_codes(&mut *tx, user.id).await?;
+    tx.commit().await?;
+
+    info!("User {} disabled two-factor authentication", user.id);
+    Ok(())
+}
+
+/// Replaces the caller's recovery codes, used or not, with a new set.
+pub async fn regenerate_recovery_codes(pool: &PgPool, claims: &Claims, code: &str) -> Result<RecoveryCodes, AppError> {
+    let mut tx = pool.begin().await?;
+    if !is_totp_enabled(&mut *tx, claims.user_id).await? {
+        return Err(AppError::Conflict("Two-factor authentication is not enabled".to_string()));
+    }
+    if !verify_second_factor(&mut tx, claims.user_id, code).await? {
+        return Err(invalid_code());
+    }
+    let recovery_codes = issue_recovery_codes(&mut tx, claims.user_id).await?;
+    tx.commit().await?;
+
+    info!("User {} regenerated their recovery codes", claims.user_id);
+    Ok(recovery_codes)
+}
+
+/// Records a login that still needs its second factor, and returns the "mfa pending"
+/// token to complete it with.
+pub async fn create_mfa_challenge(
+    executor: impl PgExecutor<'_>,
+    user_id: i32,
+    enrollment_required: bool,
+) -> Result<MfaChallenge, AppError> {
+    let token = generate_opaque_token();
+    let expires_at = Utc::now() + Duration::seconds(MFA_CHALLENGE_EXPIRY_SECONDS);
+    insert_mfa_challenge(executor, user_id, &hash_token(&token), expires_at).await?;
+
+    Ok(MfaChallenge {
+        mfa_token: token,
+        token_type: "MfaPending",
+        expires_in: MFA_CHALLENGE_EXPIRY_SECONDS,
+        enrollment_required,
+    })
+}
+
+/// Issues the token a user without an authenticator needs to add one while logging in,
+/// replacing any issued before. Hand it to them in person or over another trusted channel.
+pub async fn issue_mfa_enrollment_token(
+    pool: &PgPool,
+    claims: &Claims,
+    user_id: i32,
+) -> Result<MfaEnrollmentToken, AppError> {
+    let mut tx = pool.begin().await?;
+    if find_user_by_id(&mut *tx, user_id).await?.is_none() {
+        return Err(AppError::NotFound(format!("User {} not found", user_id)));
+    }
+    if is_totp_enabled(&mut *tx, user_id).await? {
+        return Err(AppError::Conflict("This user already has two-factor authentication".to_string()));
+    }
+    let token = generate_opaque_token();
+    let expires_at = Utc::now() + Duration::seconds(MFA_ENROLLMENT_TOKEN_EXPIRY_SECONDS);
+    replace_mfa_enrollment_token(&mut tx, user_id, &hash_token(&token), claims.user_id, expires_at).await?;
+    tx.commit().await?;
+
+    info!("User {} issued a two-factor enrollment token for user {}", claims.user_id, user_id);
+    Ok(MfaEnrollmentToken {
+        user_id,
+        enrollment_token: token,
+        expires_in: MFA_ENROLLMENT_TOKEN_EXPIRY_SECONDS,
+    })
+}
+
+pub async fn read_security_settings(pool: &PgPool) -> Result<SecuritySettings, AppError> {
+    Ok(get_security_settings(pool).await?)
+}
+
+/// Changes whether admins must use two-factor authentication. When it becomes required,
+/// admins without an authenticator are logged out everywhere; they can log in again once
+/// they add one with an enrollment token. The caller must have one already, so as not to
+/// lock themselves out.
+pub async fn change_security_settings(
+    pool: &PgPool,
+    claims: &Claims,
+    require_admin_mfa: bool,
+) -> Result<SecuritySettings, AppError> {
+    let mut tx = pool.begin().await?;
+    if require_admin_mfa && !is_totp_enabled(&mut *tx, claims.user_id).await? {
+        return Err(AppError::Conflict(
+            "Enable two-factor authentication on your own account before requiring it for admins".to_string(),
+        ));
+    }
+    let settings = update_security_settings(&mut *tx, require_admin_mfa).await?;
+    let revoked = if require_admin_mfa {
+        revoke_sessions_of_admins_without_totp(&mut *tx).await?
+    } else {
+        0
+    };
+    tx.commit().await?;
+
+    info!(
+        "User {} set two-factor authentication for admins to {}",
+        claims.user_id,
+        if require_admin_mfa { "required" } else { "optional" }
+    );
+    if revoked > 0 {
+        warn!("Ended {} sessions of admins without two-factor authentication", revoked);
+    }
+    Ok(settings)
+}
+
+/// Whether a user with `role` must have two-factor authentication and doesn't yet.
+pub async fn needs_totp_enrollment(conn: &mut PgConnection, user_id: i32, role: &Role) -> Result<bool, AppError> {
+    if *role != Role::ADMIN || !get_security_settings(&mut *conn).await?.require_admin_mfa {
+        return Ok(false);
+    }
+    Ok(!is_totp_enabled(conn, user_id).await?)
+}
+
+async fn issue_recovery_codes(conn: &mut PgConnection, user_id: i32) -> Result<RecoveryCodes, AppError> {
+    let recovery_codes: Vec<String> = (0..RECOVERY_CODE_COUNT).map(|_| generate_recovery_code()).collect();
+    let hashes: Vec<String> = recovery_codes.iter().map(|code| hash_token(&normalize_recovery_code(code))).collect();
+    replace_recovery_codes(conn, user_id, &hashes).await?;
+    Ok(RecoveryCodes { recovery_codes })
+}
+
+/// A recovery code of 16 base32 characters (80 bits), in groups of four for legibility.
+fn generate_recovery_code() -> String {
+    const ALPHABET: &[u8; 32] = b"abcdefghijklmnopqrstuvwxyz234567";
+    rand::random::<[u8; 16]>()
+        .chunks(4)
+        .map(|group| group.iter().map(|byte| ALPHABET[(byte % 32) as usize] as char).collect::<String>())
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+/// Recovery codes are compared without case, dashes or spaces, however they were typed.
+fn normalize_recovery_code(code: &str) -> String {
+    code.chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .map(|c| c.to_ascii_lowercase())
+        .collect()
+}
+
+fn invalid_code() -> AppError {
+    AppError::invalid_field("code", "invalid_code", "Invalid or expired code")
+}
+
+fn now() -> u64 {
+    Utc::now().timestamp() as u64
+}
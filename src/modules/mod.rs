@@ -1,2 +1,12 @@
 pub mod users;
-pub mod transactions;
\ No newline at end of file
+pub mod transactions;
+pub mod accounts;
+pub mod categories;
+pub mod budgets;
+pub mod recurring;
+pub mod imports;
+pub mod duplicates;
+pub mod rules;
+pub mod tags;
+pub mod attachments;
+pub mod mfa;
\ No newline at end of file
@@ -0,0 +1,69 @@
+use bigdecimal::BigDecimal;
+use chrono::NaiveDate;
+use serde::Deserialize;
+use validator::Validate;
+
+use crate::modules::recurring::models::{Frequency, RecurringDraft, Schedule};
+use crate::modules::transactions::dtos::validate_amount;
+use crate::modules::transactions::models::TransactionKind;
+use crate::utils::money::{deserialize_amount, deserialize_optional_amount};
+
+#[derive(Deserialize, Validate)]
+pub struct CreateRecurringRequest {
+    pub account_id: i32,
+    pub category_id: Option<i32>,
+    pub kind: TransactionKind,
+    #[serde(deserialize_with = "deserialize_amount")]
+    #[validate(custom(function = "validate_amount"))]
+    pub amount: BigDecimal,
+    #[serde(default)]
+    #[validate(length(max = 255, message = "Description cannot exceed 255 characters"))]
+    pub description: String,
+    pub frequency: Frequency,
+    #[serde(default = "default_interval")]
+    #[validate(range(min = 1, max = 366, message = "Interval must be between 1 and 366"))]
+    pub interval: i32,
+    pub start_date: NaiveDate,
+    pub until: Option<NaiveDate>,
+    #[validate(range(min = 1, max = 1000, message = "Count must be between 1 and 1000"))]
+    pub count: Option<i32>,
+}
+
+fn default_interval() -> i32 {
+    1
+}
+
+impl From<CreateRecurringRequest> for RecurringDraft {
+    fn from(request: CreateRecurringRequest) -> Self {
+        RecurringDraft {
+            account_id: request.account_id,
+            category_id: request.category_id,
+            kind: request.kind,
+            amount: request.amount,
+            description: request.description,
+            schedule: Schedule {
+                frequency: request.frequency,
+                interval: request.interval,
+                start_date: request.start_date,
+                until: request.until,
+                count: request.count,
+            },
+        }
+    }
+}
+
+/// Changes to a single upcoming occurrence. Fields left out keep the template's value.
+#[derive(Deserialize, Validate)]
+pub struct OccurrenceRequest {
+    #[serde(default, deserialize_with = "deserialize_optional_amount")]
+    #[validate(custom(function = "validate_amount"))]
+    pub amount: Option<BigDecimal>,
+    #[validate(length(max = 255, message = "Description cannot exceed 255 characters"))]
+    pub description: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct OccurrenceQuery {
+    pub from: Option<NaiveDate>,
+    pub to: Option<NaiveDate>,
+}
@@ -0,0 +1,273 @@
+use bigdecimal::BigDecimal;
+use chrono::{DateTime, Days, Months, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+use crate::modules::transactions::models::TransactionKind;
+use crate::utils::money::serialize_amount;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, sqlx::Type)]
+#[serde(rename_all = "UPPERCASE")]
+#[sqlx(type_name = "VARCHAR", rename_all = "UPPERCASE")]
+pub enum Frequency {
+    Daily,
+    Weekly,
+    Monthly,
+    Yearly,
+}
+
+impl Frequency {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Frequency::Daily => "DAILY",
+            Frequency::Weekly => "WEEKLY",
+            Frequency::Monthly => "MONTHLY",
+            Frequency::Yearly => "YEARLY",
+        }
+    }
+}
+
+impl fmt::Display for Frequency {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+/// When a recurring transaction happens: every `interval` days, weeks, months or years
+/// from `start_date`, ending after `until` or after `count` occurrences if either is set.
+/// Monthly and yearly dates that don't exist in a month fall on its last day instead
+/// (Jan 31 is followed by Feb 28), without shifting later occurrences.
+#[derive(Debug, Clone, Copy)]
+pub struct Schedule {
+    pub frequency: Frequency,
+    pub interval: i32,
+    pub start_date: NaiveDate,
+    pub until: Option<NaiveDate>,
+    pub count: Option<i32>,
+}
+
+impl Schedule {
+    /// The `index`-th occurrence (0-based), ignoring `until` and `count`.
+    fn nth(&self, index: u32) -> Option<NaiveDate> {
+        let steps = index.checked_mul(self.interval as u32)?;
+        match self.frequency {
+            Frequency::Daily => self.start_date.checked_add_days(Days::new(steps as u64)),
+            Frequency::Weekly => self.start_date.checked_add_days(Days::new(steps as u64 * 7)),
+            Frequency::Monthly => self.start_date.checked_add_months(Months::new(steps)),
+            Frequency::Yearly => self.start_date.checked_add_months(Months::new(steps.checked_mul(12)?)),
+        }
+    }
+
+    /// All occurrences falling within `from..=to`, in order.
+    pub fn occurrences_between(&self, from: NaiveDate, to: NaiveDate) -> Vec<NaiveDate> {
+        let last = match self.until {
+            Some(until) => until.min(to),
+            None => to,
+        };
+        let limit = self.count.map_or(u32::MAX, |count| count as u32);
+
+        let mut dates = Vec::new();
+        for index in 0..limit {
+            match self.nth(index) {
+                Some(date) if date <= last => {
+                    if date >= from {
+                        dates.push(date);
+                    }
+                }
+                _ => break,
+            }
+        }
+        dates
+    }
+
+    pub fn is_occurrence(&self, date: NaiveDate) -> bool {
+        self.occurrences_between(date, date).contains(&date)
+    }
+}
+
+/// The fields of a new recurring template. `amount` is positive, as for transactions.
+pub struct RecurringDraft {
+    pub account_id: i32,
+    pub category_id: Option<i32>,
+    pub kind: TransactionKind,
+    pub amount: BigDecimal,
+    pub description: String,
+    pub schedule: Schedule,
+}
+
+#[derive(Serialize)]
+pub struct RecurringTransaction {
+    pub id: i32,
+    pub user_id: i32,
+    pub account_id: i32,
+    pub category_id: Option<i32>,
+    pub kind: TransactionKind,
+    #[serde(serialize_with = "serialize_amount")]
+    pub amount: BigDecimal,
+    pub description: String,
+    pub frequency: Frequency,
+    pub interval: i32,
+    pub start_date: NaiveDate,
+    pub until: Option<NaiveDate>,
+    pub count: Option<i32>,
+    pub materialized_through: Option<NaiveDate>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl RecurringTransaction {
+    pub fn schedule(&self) -> Schedule {
+        Schedule {
+            frequency: self.frequency,
+            interval: self.interval,
+            start_date: self.start_date,
+            until: self.until,
+            count: self.count,
+        }
+    }
+}
+
+impl fmt::Display for RecurringTransaction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "RecurringTransaction {{ id: {}, user_id: {}, account_id: {}, kind: {}, amount: {:.2}, frequency: {}, interval: {}, start_date: {} }}",
+            self.id,
+            self.user_id,
+            self.account_id,
+            self.kind,
+            self.amount,
+            self.frequency,
+            self.interval,
+            self.start_date
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, sqlx::Type)]
+#[serde(rename_all = "UPPERCASE")]
+#[sqlx(type_name = "VARCHAR", rename_all = "UPPERCASE")]
+pub enum OccurrenceStatus {
+    /// Not posted yet and unchanged from the template.
+    Scheduled,
+    /// Not posted yet, with its own amount or description.
+    Modified,
+    Skipped,
+    Posted,
+}
+
+impl OccurrenceStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            OccurrenceStatus::Scheduled => "SCHEDULED",
+            OccurrenceStatus::Modified => "MODIFIED",
+            OccurrenceStatus::Skipped => "SKIPPED",
+            OccurrenceStatus::Posted => "POSTED",
+        }
+    }
+}
+
+/// A stored exception or posting record for one occurrence of a template.
+pub struct OccurrenceRecord {
+    pub occurrence_date: NaiveDate,
+    pub status: OccurrenceStatus,
+    pub amount: Option<BigDecimal>,
+    pub description: Option<String>,
+    pub transaction_id: Option<i32>,
+}
+
+/// One occurrence of a template as shown to users, with any changes applied.
+#[derive(Serialize)]
+pub struct Occurrence {
+    pub date: NaiveDate,
+    pub status: OccurrenceStatus,
+    #[serde(serialize_with = "serialize_amount")]
+    pub amount: BigDecimal,
+    pub description: String,
+    pub transaction_id: Option<i32>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    fn schedule(frequency: Frequency, interval: i32, start_date: NaiveDate) -> Schedule {
+        Schedule { frequency, interval, start_date, until: None, count: None }
+    }
+
+    #[test]
+    fn daily_and_weekly_dates_step_by_the_interval() {
+        let every_third_day = schedule(Frequency::Daily, 3, date(2025, 3, 1));
+        assert_eq!(
+            every_third_day.occurrences_between(date(2025, 3, 1), date(2025, 3, 10)),
+            [date(2025, 3, 1), date(2025, 3, 4), date(2025, 3, 7), date(2025, 3, 10)]
+        );
+
+        let fortnightly = schedule(Frequency::Weekly, 2, date(2025, 3, 1));
+        assert_eq!(
+            fortnightly.occurrences_between(date(2025, 3, 1), date(2025, 4, 1)),
+            [date(2025, 3, 1), date(2025, 3, 15), date(2025, 3, 29)]
+        );
+    }
+
+    #[test]
+    fn month_end_falls_on_the_last_day_without_drifting() {
+        let monthly = schedule(Frequency::Monthly, 1, date(2025, 1, 31));
+
+        assert_eq!(
+            monthly.occurrences_between(date(2025, 1, 1), date(2025, 4, 30)),
+            [date(2025, 1, 31), date(2025, 2, 28), date(2025, 3, 31), date(2025, 4, 30)]
+        );
+    }
+
+    #[test]
+    fn leap_day_falls_on_feb_28_in_other_years() {
+        let yearly = schedule(Frequency::Yearly, 1, date(2024, 2, 29));
+
+        assert_eq!(
+            yearly.occurrences_between(date(2024, 1, 1), date(2028, 12, 31)),
+            [date(2024, 2, 29), date(2025, 2, 28), date(2026, 2, 28), date(2027, 2, 28), date(2028, 2, 29)]
+        );
+    }
+
+    #[test]
+    fn window_start_skips_earlier_occurrences() {
+        let monthly = schedule(Frequency::Monthly, 2, date(2025, 1, 15));
+
+        assert_eq!(
+            monthly.occurrences_between(date(2025, 4, 1), date(2025, 9, 30)),
+            [date(2025, 5, 15), date(2025, 7, 15), date(2025, 9, 15)]
+        );
+    }
+
+    #[test]
+    fn until_and_count_end_the_schedule() {
+        let until = Schedule { until: Some(date(2025, 3, 20)), ..schedule(Frequency::Weekly, 1, date(2025, 3, 1)) };
+        assert_eq!(
+            until.occurrences_between(date(2025, 1, 1), date(2025, 12, 31)),
+            [date(2025, 3, 1), date(2025, 3, 8), date(2025, 3, 15)]
+        );
+
+        // `count` includes occurrences before the window
+        let count = Schedule { count: Some(3), ..schedule(Frequency::Monthly, 1, date(2025, 1, 10)) };
+        assert_eq!(
+            count.occurrences_between(date(2025, 2, 1), date(2025, 12, 31)),
+            [date(2025, 2, 10), date(2025, 3, 10)]
+        );
+        assert!(count.occurrences_between(date(2025, 4, 1), date(2025, 12, 31)).is_empty());
+    }
+
+    #[test]
+    fn only_scheduled_dates_are_occurrences() {
+        let weekly = Schedule { count: Some(2), ..schedule(Frequency::Weekly, 1, date(2025, 3, 3)) };
+
+        assert!(weekly.is_occurrence(date(2025, 3, 10)));
+        assert!(!weekly.is_occurrence(date(2025, 3, 11)));
+        assert!(!weekly.is_occurrence(date(2025, 3, 17)));
+        assert!(!weekly.is_occurrence(date(2025, 2, 24)));
+    }
+}
@@ -0,0 +1,228 @@
+use bigdecimal::BigDecimal;
+use chrono::NaiveDate;
+use sqlx::{PgConnection, PgPool};
+
+use crate::modules::recurring::models::{
+    Frequency, OccurrenceRecord, OccurrenceStatus, RecurringDraft, RecurringTransaction,
+};
+use crate::modules::transactions::models::TransactionKind;
+
+pub async fn insert_recurring_transaction(
+    pool: &PgPool,
+    user_id: i32,
+    draft: &RecurringDraft,
+) -> Result<RecurringTransaction, sqlx::Error> {
+    sqlx::query_as!(
+        RecurringTransaction,
+        r#"
+        INSERT INTO recurring_transactions (user_id, account_id, category_id, kind, amount, description, frequency, interval, start_date, until, count)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)
+        RETURNING id, user_id, account_id, category_id, kind as "kind: TransactionKind", amount, description,
+            frequency as "frequency: Frequency", interval, start_date, until, count, materialized_through, created_at, updated_at
+        "#,
+        user_id,
+        draft.account_id,
+        draft.category_id,
+        draft.kind.as_str(),
+        draft.amount,
+        draft.description,
+        draft.schedule.frequency.as_str(),
+        draft.schedule.interval,
+        draft.schedule.start_date,
+        draft.schedule.until,
+        draft.schedule.count
+    )
+        .fetch_one(pool)
+        .await
+}
+
+pub async fn find_recurring_transaction_by_id(pool: &PgPool, id: i32) -> Result<Option<RecurringTransaction>, sqlx::Error> {
+    sqlx::query_as!(
+        RecurringTransaction,
+        r#"
+        SELECT id, user_id, account_id, category_id, kind as "kind: TransactionKind", amount, description,
+            frequency as "frequency: Frequency", interval, start_date, until, count, materialized_through, created_at, updated_at
+        FROM recurring_transactions
+        WHERE id = $1
+        "#,
+        id
+    )
+        .fetch_optional(pool)
+        .await
+}
+
+pub async fn find_recurring_transactions_by_user_id(
+    pool: &PgPool,
+    user_id: i32,
+) -> Result<Vec<RecurringTransaction>, sqlx::Error> {
+    sqlx::query_as!(
+        RecurringTransaction,
+        r#"
+        SELECT id, user_id, account_id, category_id, kind as "kind: TransactionKind", amount, description,
+            frequency as "frequency: Frequency", interval, start_date, until, count, materialized_through, created_at, updated_at
+        FROM recurring_transactions
+        WHERE user_id = $1
+        ORDER BY start_date, id
+        "#,
+        user_id
+    )
+        .fetch_all(pool)
+        .await
+}
+
+/// Templates that may have occurrences on or before `today` not yet processed.
+pub async fn find_due_recurring_transactions(
+    pool: &PgPool,
+    today: NaiveDate,
+) -> Result<Vec<RecurringTransaction>, sqlx::Error> {
+    sqlx::query_as!(
+        RecurringTransaction,
+        r#"
+        SELECT id, user_id, account_id, category_id, kind as "kind: TransactionKind", amount, description,
+            frequency as "frequency: Frequency", interval, start_date, until, count, materialized_through, created_at, updated_at
+        FROM recurring_transactions
+        WHERE start_date <= $1
+            AND (materialized_through IS NULL OR materialized_through < $1)
+            AND (until IS NULL OR materialized_through IS NULL OR materialized_through < until)
+            -- Done once the last of `count` occurrences is processed, dated as `Schedule` dates it
+            AND (count IS NULL OR materialized_through IS NULL OR materialized_through < (start_date + make_interval(
+                months => (count - 1) * "interval" * CASE frequency WHEN 'MONTHLY' THEN 1 WHEN 'YEARLY' THEN 12 ELSE 0 END,
+                days => (count - 1) * "interval" * CASE frequency WHEN 'DAILY' THEN 1 WHEN 'WEEKLY' THEN 7 ELSE 0 END
+            ))::date)
+        ORDER BY id
+        "#,
+        today
+    )
+        .fetch_all(pool)
+        .await
+}
+
+pub async fn set_materialized_through(pool: &PgPool, id: i32, date: NaiveDate) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        "UPDATE recurring_transactions SET materialized_through = $2 WHERE id = $1 AND (materialized_through IS NULL OR materialized_through < $2)",
+        id,
+        date
+    )
+        .execute(pool)
+        .await?;
+
+    Ok(())
+}
+
+pub async fn delete_recurring_transaction(pool: &PgPool, id: i32) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query!("DELETE FROM recurring_transactions WHERE id = $1", id)
+        .execute(pool)
+        .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+pub async fn find_occurrence_records(
+    pool: &PgPool,
+    recurring_id: i32,
+    from: NaiveDate,
+    to: NaiveDate,
+) -> Result<Vec<OccurrenceRecord>, sqlx::Error> {
+    sqlx::query_as!(
+        OccurrenceRecord,
+        r#"
+        SELECT occurrence_date, status as "status: OccurrenceStatus", amount, description, transaction_id
+        FROM recurring_occurrences
+        WHERE recurring_id = $1 AND occurrence_date BETWEEN $2 AND $3
+        ORDER BY occurrence_date
+        "#,
+        recurring_id,
+        from,
+        to
+    )
+        .fetch_all(pool)
+        .await
+}
+
+/// Stores a modification or skip for an occurrence. Returns `None` when the occurrence
+/// has already been posted, which can no longer be changed here.
+pub async fn upsert_occurrence(
+    pool: &PgPool,
+    recurring_id: i32,
+    date: NaiveDate,
+    status: OccurrenceStatus,
+    amount: Option<&BigDecimal>,
+    description: Option<&str>,
+) -> Result<Option<OccurrenceRecord>, sqlx::Error> {
+    sqlx::query_as!(
+        OccurrenceRecord,
+        r#"
+        INSERT INTO recurring_occurrences (recurring_id, occurrence_date, status, amount, description)
+        VALUES ($1, $2, $3, $4, $5)
+        ON CONFLICT (recurring_id, occurrence_date) DO UPDATE
+            SET status = EXCLUDED.status, amount = EXCLUDED.amount, description = EXCLUDED.description
+            WHERE recurring_occurrences.status <> 'POSTED'
+        RETURNING occurrence_date, status as "status: OccurrenceStatus", amount, description, transaction_id
+        "#,
+        recurring_id,
+        date,
+        status.as_str(),
+        amount,
+        description
+    )
+        .fetch_optional(pool)
+        .await
+}
+
+/// Removes a modification or skip, returning the occurrence to the template's schedule.
+pub async fn delete_occurrence(pool: &PgPool, recurring_id: i32, date: NaiveDate) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query!(
+        "DELETE FROM recurring_occurrences WHERE recurring_id = $1 AND occurrence_date = $2 AND status <> 'POSTED'",
+        recurring_id,
+        date
+    )
+        .execute(pool)
+        .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// Claims an occurrence for posting by marking it POSTED. Succeeds at most once per
+/// occurrence, returning any amount or description it was modified with; returns
+/// `None` when it was already posted or skipped. The claim holds until the caller's
+/// database transaction ends, and is undone if it is rolled back.
+pub async fn claim_occurrence(
+    conn: &mut PgConnection,
+    recurring_id: i32,
+    date: NaiveDate,
+) -> Result<Option<(Option<BigDecimal>, Option<String>)>, sqlx::Error> {
+    let record = sqlx::query!(
+        r#"
+        INSERT INTO recurring_occurrences (recurring_id, occurrence_date, status)
+        VALUES ($1, $2, 'POSTED')
+        ON CONFLICT (recurring_id, occurrence_date) DO UPDATE
+            SET status = 'POSTED'
+            WHERE recurring_occurrences.status = 'MODIFIED'
+        RETURNING amount, description
+        "#,
+        recurring_id,
+        date
+    )
+        .fetch_optional(conn)
+        .await?;
+
+    Ok(record.map(|r| (r.amount, r.description)))
+}
+
+pub async fn link_occurrence_transaction(
+    conn: &mut PgConnection,
+    recurring_id: i32,
+    date: NaiveDate,
+    transaction_id: i32,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        "UPDATE recurring_occurrences SET transaction_id = $3 WHERE recurring_id = $1 AND occurrence_date = $2",
+        recurring_id,
+        date,
+        transaction_id
+    )
+        .execute(conn)
+        .await?;
+
+    Ok(())
+}
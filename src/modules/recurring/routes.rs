@@ -0,0 +1,183 @@
+use actix_web::{http::StatusCode, web, HttpResponse, HttpRequest};
+use chrono::NaiveDate;
+use validator::Validate;
+
+use crate::modules::recurring::dtos::{CreateRecurringRequest, OccurrenceQuery, OccurrenceRequest};
+use crate::modules::recurring::models::RecurringDraft;
+use crate::modules::recurring::services::{
+    create_user_recurring_transaction, list_user_recurring_transactions, get_accessible_recurring_transaction,
+    remove_user_recurring_transaction, list_occurrences, modify_occurrence, skip_occurrence, reset_occurrence,
+};
+use crate::utils::response::GenericResponse;
+use crate::utils::errors::AppError;
+use crate::middleware::jwt::{request_claims, JwtMiddleware};
+use crate::AppState;
+
+pub fn init(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/recurring")
+            .service(
+                web::resource("")
+                    .wrap(JwtMiddleware)
+                    .route(web::post().to(add_recurring))
+                    .route(web::get().to(list_recurring))
+            )
+            .service(
+                web::resource("/{id}")
+                    .wrap(JwtMiddleware)
+                    .route(web::get().to(get_recurring))
+                    .route(web::delete().to(delete_recurring))
+            )
+            .service(
+                web::resource("/{id}/occurrences")
+                    .wrap(JwtMiddleware)
+                    .route(web::get().to(get_occurrences))
+            )
+            .service(
+                web::resource("/{id}/occurrences/{date}")
+                    .wrap(JwtMiddleware)
+                    .route(web::put().to(put_occurrence))
+                    .route(web::delete().to(delete_occurrence))
+            )
+            .service(
+                web::resource("/{id}/occurrences/{date}/skip")
+                    .wrap(JwtMiddleware)
+                    .route(web::post().to(post_skip_occurrence))
+            ),
+    );
+}
+
+async fn add_recurring(
+    state: web::Data<AppState>,
+    recurring: web::Json<CreateRecurringRequest>,
+    req: HttpRequest,
+) -> Result<HttpResponse, AppError> {
+    recurring.validate()?;
+    let claims = request_claims(&req)?;
+
+    let draft = RecurringDraft::from(recurring.into_inner());
+    let recurring = create_user_recurring_transaction(&state.db, claims.user_id, &draft).await?;
+    log::info!("Recurring transaction created for user {}: {}", claims.user_id, recurring);
+
+    Ok(HttpResponse::Created().json(GenericResponse {
+        status: StatusCode::CREATED.as_u16(),
+        data: Some(recurring),
+        message: "Recurring transaction created successfully".to_string(),
+    }))
+}
+
+async fn list_recurring(state: web::Data<AppState>, req: HttpRequest) -> Result<HttpResponse, AppError> {
+    let claims = request_claims(&req)?;
+    let recurring = list_user_recurring_transactions(&state.db, claims.user_id).await?;
+
+    Ok(HttpResponse::Ok().json(GenericResponse {
+        status: StatusCode::OK.as_u16(),
+        data: Some(recurring),
+        message: "Recurring transactions retrieved successfully".to_string(),
+    }))
+}
+
+async fn get_recurring(
+    state: web::Data<AppState>,
+    path: web::Path<i32>,
+    req: HttpRequest,
+) -> Result<HttpResponse, AppError> {
+    let claims = request_claims(&req)?;
+    let recurring = get_accessible_recurring_transaction(&state.db, &claims, path.into_inner()).await?;
+
+    Ok(HttpResponse::Ok().json(GenericResponse {
+        status: StatusCode::OK.as_u16(),
+        data: Some(recurring),
+        message: "Recurring transaction retrieved successfully".to_string(),
+    }))
+}
+
+async fn delete_recurring(
+    state: web::Data<AppState>,
+    path: web::Path<i32>,
+    req: HttpRequest,
+) -> Result<HttpResponse, AppError> {
+    let claims = request_claims(&req)?;
+    let id = path.into_inner();
+
+    remove_user_recurring_transaction(&state.db, &claims, id).await?;
+    log::info!("Recurring transaction {} deleted by user {}", id, claims.user_id);
+
+    Ok(HttpResponse::Ok().json(GenericResponse {
+        status: StatusCode::OK.as_u16(),
+        data: None::<()>,
+        message: "Recurring transaction deleted successfully".to_string(),
+    }))
+}
+
+async fn get_occurrences(
+    state: web::Data<AppState>,
+    path: web::Path<i32>,
+    query: web::Query<OccurrenceQuery>,
+    req: HttpRequest,
+) -> Result<HttpResponse, AppError> {
+    let claims = request_claims(&req)?;
+    let occurrences = list_occurrences(&state.db, &claims, path.into_inner(), &query).await?;
+
+    Ok(HttpResponse::Ok().json(GenericResponse {
+        status: StatusCode::OK.as_u16(),
+        data: Some(occurrences),
+        message: "Occurrences retrieved successfully".to_string(),
+    }))
+}
+
+async fn put_occurrence(
+    state: web::Data<AppState>,
+    path: web::Path<(i32, NaiveDate)>,
+    occurrence: web::Json<OccurrenceRequest>,
+    req: HttpRequest,
+) -> Result<HttpResponse, AppError> {
+    occurrence.validate()?;
+    let claims = request_claims(&req)?;
+    let (id, date) = path.into_inner();
+
+    let occurrence = modify_occurrence(&state.db, &claims, id, date, &occurrence).await?;
+    log::info!("Occurrence {} of recurring transaction {} modified by user {}", date, id, claims.user_id);
+
+    Ok(HttpResponse::Ok().json(GenericResponse {
+        status: StatusCode::OK.as_u16(),
+        data: Some(occurrence),
+        message: "Occurrence updated successfully".to_string(),
+    }))
+}
+
+async fn delete_occurrence(
+    state: web::Data<AppState>,
+    path: web::Path<(i32, NaiveDate)>,
+    req: HttpRequest,
+) -> Result<HttpResponse, AppError> {
+    let claims = request_claims(&req)?;
+    let (id, date) = path.into_inner();
+
+    let occurrence = reset_occurrence(&state.db, &claims, id, date).await?;
+    log::info!("Occurrence {} of recurring transaction {} reset by user {}", date, id, claims.user_id);
+
+    Ok(HttpResponse::Ok().json(GenericResponse {
+        status: StatusCode::OK.as_u16(),
+        data: Some(occurrence),
+        message: "Occurrence reset successfully".to_string(),
+    }))
+}
+
+async fn post_skip_occurrence(
+    state: web::Data<AppState>,
+    path: web::Path<(i32, NaiveDate)>,
+    req: HttpRequest,
+) -> Result<HttpResponse, AppError> {
+    let claims = request_claims(&req)?;
+    let (id, date) = path.into_inner();
+
+    let occurrence = skip_occurrence(&state.db, &claims, id, date).await?;
+    log::info!("Occurrence {} of recurring transaction {} skipped by user {}", date, id, claims.user_id);
+
+    Ok(HttpResponse::Ok().json(GenericResponse {
+        status: StatusCode::OK.as_u16(),
+        data: Some(occurrence),
+        message: "Occurrence skipped successfully".to_string(),
+    }))
+}
@@ -0,0 +1,282 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use chrono::{Days, NaiveDate, Utc};
+use log::{error, info, warn};
+use sqlx::PgPool;
+
+use crate::modules::accounts::services::ensure_account_accepts_transactions;
+use crate::modules::categories::services::ensure_category_accepts_transactions;
+use crate::modules::recurring::dtos::{OccurrenceQuery, OccurrenceRequest};
+use crate::modules::recurring::models::{
+    Occurrence, OccurrenceRecord, OccurrenceStatus, RecurringDraft, RecurringTransaction,
+};
+use crate::modules::recurring::repositories::{
+    insert_recurring_transaction, find_recurring_transaction_by_id, find_recurring_transactions_by_user_id,
+    find_due_recurring_transactions, set_materialized_through, delete_recurring_transaction, find_occurrence_records,
+    upsert_occurrence, delete_occurrence, claim_occurrence, link_occurrence_transaction,
+};
+use crate::modules::transactions::models::{TransactionDraft, TransactionKind};
+use crate::modules::transactions::services::{flag_transaction_duplicates, record_user_transaction_unchecked};
+use crate::utils::errors::AppError;
+use crate::utils::jwt::Claims;
+
+/// Default window of `GET /recurring/{id}/occurrences`, and the widest one allowed.
+const DEFAULT_OCCURRENCE_WINDOW_DAYS: u64 = 90;
+const MAX_OCCURRENCE_WINDOW_DAYS: u64 = 366;
+
+pub async fn create_user_recurring_transaction(
+    pool: &PgPool,
+    user_id: i32,
+    draft: &RecurringDraft,
+) -> Result<RecurringTransaction, AppError> {
+    if draft.kind == TransactionKind::Transfer {
+        return Err(AppError::invalid_field("kind", "transfer", "Transfers cannot be recurring"));
+    }
+    if draft.schedule.until.is_some() && draft.schedule.count.is_some() {
+        return Err(AppError::invalid_field("until", "until", "Set either until or count, not both"));
+    }
+    if draft.schedule.until.is_some_and(|until| until < draft.schedule.start_date) {
+        return Err(AppError::invalid_field("until", "until", "Until cannot be before the start date"));
+    }
+    ensure_account_accepts_transactions(pool, user_id, draft.account_id, "account_id").await?;
+    if let Some(category_id) = draft.category_id {
+        ensure_category_accepts_transactions(pool, user_id, category_id, draft.kind).await?;
+    }
+
+    Ok(insert_recurring_transaction(pool, user_id, draft).await?)
+}
+
+pub async fn list_user_recurring_transactions(pool: &PgPool, user_id: i32) -> Result<Vec<RecurringTransaction>, AppError> {
+    Ok(find_recurring_transactions_by_user_id(pool, user_id).await?)
+}
+
+/// Loads template `id` for its owner or an admin, before it or its occurrences are changed.
+pub async fn get_accessible_recurring_transaction(
+    pool: &PgPool,
+    claims: &Claims,
+    id: i32,
+) -> Result<RecurringTransaction, AppError> {
+    let recurring = find_recurring_transaction_by_id(pool, id)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("Recurring transaction {} not found", id)))?;
+
+    if recurring.user_id != claims.user_id && !claims.is_admin() {
+        warn!("User {} attempted to access recurring transaction {} owned by user {}", claims.user_id, id, recurring.user_id);
+        return Err(AppError::Forbidden("You do not have access to this recurring transaction".to_string()));
+    }
+
+    Ok(recurring)
+}
+
+/// Stops a recurring transaction. Transactions it already posted are kept.
+pub async fn remove_user_recurring_transaction(pool: &PgPool, claims: &Claims, id: i32) -> Result<(), AppError> {
+    get_accessible_recurring_transaction(pool, claims, id).await?;
+
+    if !delete_recurring_transaction(pool, id).await? {
+        return Err(AppError::NotFound(format!("Recurring transaction {} not found", id)));
+    }
+    Ok(())
+}
+
+fn to_occurrence(recurring: &RecurringTransaction, date: NaiveDate, record: Option<OccurrenceRecord>) -> Occurrence {
+    match record {
+        Some(record) => Occurrence {
+            date,
+            status: record.status,
+            amount: record.amount.unwrap_or_else(|| recurring.amount.clone()),
+            description: record.description.unwrap_or_else(|| recurring.description.clone()),
+            transaction_id: record.transaction_id,
+        },
+        None => Occurrence {
+            date,
+            status: OccurrenceStatus::Scheduled,
+            amount: recurring.amount.clone(),
+            description: recurring.description.clone(),
+            transaction_id: None,
+        },
+    }
+}
+
+pub async fn list_occurrences(
+    pool: &PgPool,
+    claims: &Claims,
+    id: i32,
+    query: &OccurrenceQuery,
+) -> Result<Vec<Occurrence>, AppError> {
+    let recurring = get_accessible_recurring_transaction(pool, claims, id).await?;
+
+    let from = query.from.unwrap_or_else(|| Utc::now().date_naive());
+    let to = match query.to {
+        Some(to) => to,
+        None => from.checked_add_days(Days::new(DEFAULT_OCCURRENCE_WINDOW_DAYS)).unwrap_or(from),
+    };
+    if to < from || from.checked_add_days(Days::new(MAX_OCCURRENCE_WINDOW_DAYS)).is_some_and(|max| to > max) {
+        return Err(AppError::invalid_field(
+            "to",
+            "range",
+            "The range must end after it starts and span at most 366 days",
+        ));
+    }
+
+    let mut records: HashMap<NaiveDate, OccurrenceRecord> = find_occurrence_records(pool, id, from, to)
+        .await?
+        .into_iter()
+        .map(|record| (record.occurrence_date, record))
+        .collect();
+
+    Ok(recurring
+        .schedule()
+        .occurrences_between(from, to)
+        .into_iter()
+        .map(|date| {
+            let record = records.remove(&date);
+            to_occurrence(&recurring, date, record)
+        })
+        .collect())
+}
+
+/// Loads a template and checks that `date` is one of its occurrences that has not been
+/// processed yet, so it can still be changed.
+async fn get_changeable_occurrence(
+    pool: &PgPool,
+    claims: &Claims,
+    id: i32,
+    date: NaiveDate,
+) -> Result<RecurringTransaction, AppError> {
+    let recurring = get_accessible_recurring_transaction(pool, claims, id).await?;
+
+    if !recurring.schedule().is_occurrence(date) {
+        return Err(AppError::NotFound(format!("Recurring transaction {} has no occurrence on {}", id, date)));
+    }
+    if recurring.materialized_through.is_some_and(|through| date <= through) {
+        return Err(AppError::Conflict(format!("The occurrence on {} has already been processed", date)));
+    }
+
+    Ok(recurring)
+}
+
+pub async fn modify_occurrence(
+    pool: &PgPool,
+    claims: &Claims,
+    id: i32,
+    date: NaiveDate,
+    request: &OccurrenceRequest,
+) -> Result<Occurrence, AppError> {
+    if request.amount.is_none() && request.description.is_none() {
+        return Err(AppError::invalid_field("amount", "required", "Provide an amount or a description"));
+    }
+    let recurring = get_changeable_occurrence(pool, claims, id, date).await?;
+
+    let record = upsert_occurrence(
+        pool,
+        id,
+        date,
+        OccurrenceStatus::Modified,
+        request.amount.as_ref(),
+        request.description.as_deref(),
+    )
+        .await?
+        .ok_or_else(|| AppError::Conflict(format!("The occurrence on {} has already been posted", date)))?;
+
+    Ok(to_occurrence(&recurring, date, Some(record)))
+}
+
+pub async fn skip_occurrence(pool: &PgPool, claims: &Claims, id: i32, date: NaiveDate) -> Result<Occurrence, AppError> {
+    let recurring = get_changeable_occurrence(pool, claims, id, date).await?;
+
+    let record = upsert_occurrence(pool, id, date, OccurrenceStatus::Skipped, None, None)
+        .await?
+        .ok_or_else(|| AppError::Conflict(format!("The occurrence on {} has already been posted", date)))?;
+
+    Ok(to_occurrence(&recurring, date, Some(record)))
+}
+
+/// Drops any modification or skip of an occurrence so it follows the template again.
+pub async fn reset_occurrence(pool: &PgPool, claims: &Claims, id: i32, date: NaiveDate) -> Result<Occurrence, AppError> {
+    let recurring = get_changeable_occurrence(pool, claims, id, date).await?;
+    delete_occurrence(pool, id, date).await?;
+
+    Ok(to_occurrence(&recurring, date, None))
+}
+
+/// Posts one occurrence as a transaction, recorded the way `record_user_transaction` would,
+/// and checked for duplicates once it is linked to its occurrence. The claim, the
+/// transaction and the link are written together, so an occurrence that fails is left to
+/// be retried. Returns false when it had already been posted or skipped.
+async fn post_occurrence(pool: &PgPool, recurring: &RecurringTransaction, date: NaiveDate) -> Result<bool, AppError> {
+    let mut tx = pool.begin().await?;
+    let Some((amount, description)) = claim_occurrence(&mut tx, recurring.id, date).await? else {
+        return Ok(false);
+    };
+
+    let draft = TransactionDraft {
+        account_id: recurring.account_id,
+        kind: recurring.kind,
+        amount: amount.unwrap_or_else(|| recurring.amount.clone()),
+        category_id: recurring.category_id,
+        description: description.unwrap_or_else(|| recurring.description.clone()),
+        occurred_on: Some(date),
+        occurred_time: None,
+        external_id: None,
+        tags: Vec::new(),
+        splits: Vec::new(),
+    };
+    let recorded = record_user_transaction_unchecked(&mut tx, pool, recurring.user_id, &draft).await?;
+    // Linked first, so that earlier posts of schedules are not taken for duplicates
+    link_occurrence_transaction(&mut tx, recurring.id, date, recorded.transaction.id).await?;
+    tx.commit().await?;
+
+    flag_transaction_duplicates(pool, recurring.user_id, recorded.transaction.id).await;
+    Ok(true)
+}
+
+/// Posts every due occurrence of every template up to and including `today`, returning
+/// how many transactions were recorded. Safe to run repeatedly or concurrently: each
+/// occurrence is claimed before it is posted, so it is recorded at most once. When an
+/// occurrence fails (e.g. its account was archived), the template is retried from that
+/// occurrence on the next run.
+pub async fn materialize_due_occurrences(pool: &PgPool, today: NaiveDate) -> Result<usize, AppError> {
+    let mut posted = 0;
+
+    for recurring in find_due_recurring_transactions(pool, today).await? {
+        let from = recurring
+            .materialized_through
+            .and_then(|through| through.succ_opt())
+            .unwrap_or(recurring.start_date);
+
+        let mut through = Some(today);
+        for date in recurring.schedule().occurrences_between(from, today) {
+            match post_occurrence(pool, &recurring, date).await {
+                Ok(true) => posted += 1,
+                Ok(false) => {}
+                Err(e) => {
+                    warn!("Failed to post occurrence {} of recurring transaction {}: {:?}", date, recurring.id, e);
+                    through = date.pred_opt();
+                    break;
+                }
+            }
+        }
+
+        if let Some(through) = through {
+            set_materialized_through(pool, recurring.id, through).await?;
+        }
+    }
+
+    Ok(posted)
+}
+
+/// Runs `materialize_due_occurrences` now and then every `period` on the server's runtime.
+pub fn spawn_recurring_scheduler(pool: PgPool, period: Duration) {
+    actix_web::rt::spawn(async move {
+        let mut ticker = actix_web::rt::time::interval(period);
+        loop {
+            ticker.tick().await;
+            match materialize_due_occurrences(&pool, Utc::now().date_naive()).await {
+                Ok(0) => {}
+                Ok(posted) => info!("Posted {} recurring transactions", posted),
+                Err(e) => error!("Failed to post recurring transactions: {:?}", e),
+            }
+        }
+    });
+}
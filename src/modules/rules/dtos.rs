@@ -0,0 +1,83 @@
+use bigdecimal::BigDecimal;
+use chrono::NaiveDate;
+use serde::Deserialize;
+use validator::{Validate, ValidationError};
+
+use crate::modules::rules::models::{RuleCondition, RuleDraft};
+use crate::modules::tags::dtos::validate_tags;
+use crate::modules::tags::models::normalize_tags;
+use crate::utils::money::has_money_scale;
+
+#[derive(Deserialize, Validate)]
+pub struct RuleRequest {
+    #[validate(length(min = 1, max = 100, message = "Name must be between 1 and 100 characters"))]
+    pub name: String,
+    /// Rules are tried from the lowest priority number up.
+    #[serde(default)]
+    #[validate(range(min = 0, max = 10000, message = "Priority must be between 0 and 10000"))]
+    pub priority: i32,
+    #[validate(custom(function = "validate_conditions"))]
+    pub conditions: Vec<RuleCondition>,
+    pub category_id: Option<i32>,
+    #[serde(default)]
+    #[validate(custom(function = "validate_tags"))]
+    pub tags: Vec<String>,
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+impl From<RuleRequest> for RuleDraft {
+    fn from(request: RuleRequest) -> Self {
+        RuleDraft {
+            name: request.name,
+            priority: request.priority,
+            conditions: request.conditions,
+            category_id: request.category_id,
+            tags: normalize_tags(&request.tags),
+            enabled: request.enabled,
+        }
+    }
+}
+
+/// Re-applies rules to the transactions in the given account and date range (all of them
+/// by default). Rules only change the category of uncategorized transactions unless
+/// `overwrite` is set.
+#[derive(Deserialize)]
+pub struct ApplyRulesQuery {
+    #[serde(default)]
+    pub dry_run: bool,
+    #[serde(default)]
+    pub overwrite: bool,
+    pub account_id: Option<i32>,
+    pub from: Option<NaiveDate>,
+    pub to: Option<NaiveDate>,
+}
+
+fn validate_conditions(conditions: &[RuleCondition]) -> Result<(), ValidationError> {
+    if conditions.is_empty() || conditions.len() > 20 {
+        let mut error = ValidationError::new("length");
+        error.message = Some("A rule needs between 1 and 20 conditions".into());
+        return Err(error);
+    }
+
+    for condition in conditions {
+        match condition {
+            RuleCondition::Description { value, .. } if value.trim().is_empty() || value.chars().count() > 255 => {
+                let mut error = ValidationError::new("description");
+                error.message = Some("Description conditions need a value of 1 to 255 characters".into());
+                return Err(error);
+            }
+            RuleCondition::Amount { value, .. } if *value <= BigDecimal::from(0) || !has_money_scale(value) => {
+                let mut error = ValidationError::new("amount");
+                error.message = Some("Amount conditions need a positive amount with at most 2 decimal places".into());
+                return Err(error);
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,333 @@
+use bigdecimal::BigDecimal;
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+use crate::modules::categories::models::CategoryKind;
+use crate::modules::transactions::models::TransactionKind;
+use crate::utils::money::{deserialize_amount, serialize_amount};
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TextOperator {
+    Contains,
+    Equals,
+    StartsWith,
+    EndsWith,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AmountOperator {
+    Eq,
+    Lt,
+    Lte,
+    Gt,
+    Gte,
+}
+
+/// One test a transaction must pass for a rule to apply. Descriptions are compared
+/// case-insensitively, and amounts are the positive API amount.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "field", rename_all = "snake_case")]
+pub enum RuleCondition {
+    Description {
+        operator: TextOperator,
+        value: String,
+    },
+    Amount {
+        operator: AmountOperator,
+        #[serde(serialize_with = "serialize_amount", deserialize_with = "deserialize_amount")]
+        value: BigDecimal,
+    },
+    AccountId {
+        value: i32,
+    },
+    Kind {
+        value: TransactionKind,
+    },
+}
+
+impl RuleCondition {
+    pub fn matches(&self, subject: &RuleSubject) -> bool {
+        match self {
+            RuleCondition::Description { operator, value } => {
+                let description = subject.description.to_lowercase();
+                let value = value.to_lowercase();
+                match operator {
+                    TextOperator::Contains => description.contains(&value),
+                    TextOperator::Equals => description.trim() == value.trim(),
+                    TextOperator::StartsWith => description.trim_start().starts_with(value.trim_start()),
+                    TextOperator::EndsWith => description.trim_end().ends_with(value.trim_end()),
+                }
+            }
+            RuleCondition::Amount { operator, value } => {
+                let amount = subject.amount.abs();
+                match operator {
+                    AmountOperator::Eq => amount == *value,
+                    AmountOperator::Lt => amount < *value,
+                    AmountOperator::Lte => amount <= *value,
+                    AmountOperator::Gt => amount > *value,
+                    AmountOperator::Gte => amount >= *value,
+                }
+            }
+            RuleCondition::AccountId { value } => subject.account_id == *value,
+            RuleCondition::Kind { value } => subject.kind == *value,
+        }
+    }
+}
+
+/// The parts of a transaction, recorded or about to be, that rules look at. A rule only
+/// gives a category to a transaction without one, and never to a split transaction.
+pub struct RuleSubject<'a> {
+    pub account_id: i32,
+    pub kind: TransactionKind,
+    pub amount: &'a BigDecimal,
+    pub description: &'a str,
+    pub category_id: Option<i32>,
+    pub split: bool,
+}
+
+/// The user-supplied fields of a rule, as written by inserts and updates.
+pub struct RuleDraft {
+    pub name: String,
+    pub priority: i32,
+    pub conditions: Vec<RuleCondition>,
+    pub category_id: Option<i32>,
+    pub tags: Vec<String>,
+    pub enabled: bool,
+}
+
+#[derive(Serialize)]
+pub struct Rule {
+    pub id: i32,
+    pub user_id: i32,
+    pub name: String,
+    pub priority: i32,
+    pub conditions: Vec<RuleCondition>,
+    /// Category given to the transactions the rule matches.
+    pub category_id: Option<i32>,
+    #[serde(skip)]
+    pub category_kind: Option<CategoryKind>,
+    /// Tags added to the transactions the rule matches.
+    pub tags: Vec<String>,
+    pub enabled: bool,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl Rule {
+    pub fn matches(&self, subject: &RuleSubject) -> bool {
+        self.enabled && self.conditions.iter().all(|condition| condition.matches(subject))
+    }
+
+    /// The category this rule gives a transaction of `kind`: none for transfers, and none
+    /// when the rule's category is for the other direction of money.
+    pub fn category_for(&self, kind: TransactionKind) -> Option<i32> {
+        let fits = match (kind, self.category_kind?) {
+            (TransactionKind::Transfer, _) => false,
+            (TransactionKind::Adjustment, _) => true,
+            (TransactionKind::Income, category_kind) => category_kind == CategoryKind::Income,
+            (TransactionKind::Expense, category_kind) => category_kind == CategoryKind::Expense,
+        };
+        self.category_id.filter(|_| fits)
+    }
+}
+
+impl fmt::Display for Rule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Rule {{ id: {}, user_id: {}, name: {}, priority: {}, conditions: {}, category_id: {}, tags: {}, enabled: {} }}",
+            self.id,
+            self.user_id,
+            self.name,
+            self.priority,
+            self.conditions.len(),
+            self.category_id.map_or_else(|| "None".to_string(), |id| id.to_string()),
+            self.tags.join(","),
+            self.enabled
+        )
+    }
+}
+
+/// What a rule does to a transaction: the category it gives one without a category, and
+/// the tags it adds.
+pub struct RuleAction<'a> {
+    pub rule: &'a Rule,
+    pub category_id: Option<i32>,
+    pub tags: &'a [String],
+}
+
+impl RuleAction<'_> {
+    /// Applies the action to a transaction's category and sorted tag names.
+    pub fn apply(&self, category_id: &mut Option<i32>, tags: &mut Vec<String>) {
+        if self.category_id.is_some() {
+            *category_id = self.category_id;
+        }
+        for tag in self.tags {
+            if let Err(index) = tags.binary_search(tag) {
+                tags.insert(index, tag.clone());
+            }
+        }
+    }
+}
+
+/// The action of the first rule (in `rules`' order) that matches the transaction and has
+/// something to do to it: a category for it, or tags.
+pub fn first_action<'a>(rules: &'a [Rule], subject: &RuleSubject) -> Option<RuleAction<'a>> {
+    rules.iter().filter(|rule| rule.matches(subject)).find_map(|rule| {
+        let category_id = match subject.category_id {
+            None if !subject.split => rule.category_for(subject.kind),
+            _ => None,
+        };
+        (category_id.is_some() || !rule.tags.is_empty()).then_some(RuleAction {
+            rule,
+            category_id,
+            tags: &rule.tags,
+        })
+    })
+}
+
+/// What rules would change on an existing transaction: its category, its tags or both.
+#[derive(Serialize)]
+pub struct RuleChange {
+    pub transaction_id: i32,
+    pub occurred_on: NaiveDate,
+    pub description: Option<String>,
+    #[serde(serialize_with = "serialize_amount")]
+    pub amount: BigDecimal,
+    pub rule_id: i32,
+    pub rule_name: String,
+    pub from_category_id: Option<i32>,
+    pub to_category_id: Option<i32>,
+    pub added_tags: Vec<String>,
+}
+
+/// Outcome of re-applying rules to existing transactions. A dry run only lists the changes.
+#[derive(Serialize)]
+pub struct RuleApplication {
+    pub dry_run: bool,
+    pub examined: usize,
+    pub changed: usize,
+    pub changes: Vec<RuleChange>,
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+
+    fn amount(value: &str) -> BigDecimal {
+        BigDecimal::from_str(value).unwrap()
+    }
+
+    fn rule(id: i32, conditions: Vec<RuleCondition>, category: Option<(i32, CategoryKind)>, tags: &[&str]) -> Rule {
+        Rule {
+            id,
+            user_id: 1,
+            name: format!("Rule {}", id),
+            priority: id,
+            conditions,
+            category_id: category.map(|(id, _)| id),
+            category_kind: category.map(|(_, kind)| kind),
+            tags: tags.iter().map(|tag| tag.to_string()).collect(),
+            enabled: true,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    fn description(operator: TextOperator, value: &str) -> RuleCondition {
+        RuleCondition::Description { operator, value: value.to_string() }
+    }
+
+    fn subject<'a>(description: &'a str, amount: &'a BigDecimal) -> RuleSubject<'a> {
+        RuleSubject {
+            account_id: 1,
+            kind: TransactionKind::Expense,
+            amount,
+            description,
+            category_id: None,
+            split: false,
+        }
+    }
+
+    #[test]
+    fn descriptions_are_compared_without_case() {
+        let amount = amount("10.00");
+        let subject = subject("  Coffee at Joe's ", &amount);
+
+        assert!(description(TextOperator::Contains, "JOE").matches(&subject));
+        assert!(description(TextOperator::Equals, "coffee at joe's").matches(&subject));
+        assert!(description(TextOperator::StartsWith, "coffee").matches(&subject));
+        assert!(description(TextOperator::EndsWith, "JOE'S").matches(&subject));
+        assert!(!description(TextOperator::StartsWith, "joe").matches(&subject));
+    }
+
+    #[test]
+    fn amounts_are_compared_by_size() {
+        let rent = amount("-50.00");
+        let subject = subject("Rent", &rent);
+        let condition = |operator, value| RuleCondition::Amount { operator, value: amount(value) };
+
+        assert!(condition(AmountOperator::Eq, "50").matches(&subject));
+        assert!(condition(AmountOperator::Gte, "50").matches(&subject));
+        assert!(condition(AmountOperator::Gt, "49.99").matches(&subject));
+        assert!(!condition(AmountOperator::Lt, "50").matches(&subject));
+        assert!(condition(AmountOperator::Lte, "50").matches(&subject));
+    }
+
+    #[test]
+    fn first_matching_rule_wins() {
+        let amount = amount("4.50");
+        let rules = [
+            rule(1, vec![description(TextOperator::Contains, "tea")], Some((10, CategoryKind::Expense)), &[]),
+            rule(2, vec![description(TextOperator::Contains, "coffee")], Some((20, CategoryKind::Expense)), &["cafe"]),
+            rule(3, vec![], Some((30, CategoryKind::Expense)), &["everything"]),
+        ];
+
+        let action = first_action(&rules, &subject("Coffee", &amount)).unwrap();
+        assert_eq!(action.rule.id, 2);
+        assert_eq!(action.category_id, Some(20));
+        assert_eq!(action.tags, ["cafe"]);
+    }
+
+    #[test]
+    fn rules_with_nothing_to_do_are_passed_over() {
+        let amount = amount("4.50");
+        let mut disabled = rule(1, vec![], Some((10, CategoryKind::Expense)), &["off"]);
+        disabled.enabled = false;
+        let rules = [
+            disabled,
+            // Its category is for income, and it adds no tags
+            rule(2, vec![], Some((20, CategoryKind::Income)), &[]),
+            rule(3, vec![], Some((30, CategoryKind::Expense)), &[]),
+            rule(4, vec![], None, &["tagged"]),
+        ];
+
+        assert_eq!(first_action(&rules, &subject("Coffee", &amount)).unwrap().rule.id, 3);
+
+        // Categorized and split transactions only get tags
+        let mut categorized = subject("Coffee", &amount);
+        categorized.category_id = Some(99);
+        let action = first_action(&rules, &categorized).unwrap();
+        assert_eq!((action.rule.id, action.category_id), (4, None));
+        let mut split = subject("Coffee", &amount);
+        split.split = true;
+        assert_eq!(first_action(&rules, &split).unwrap().rule.id, 4);
+    }
+
+    #[test]
+    fn actions_add_tags_in_order() {
+        let rule = rule(1, vec![], Some((10, CategoryKind::Expense)), &["b", "d"]);
+        let action = RuleAction { rule: &rule, category_id: Some(10), tags: &rule.tags };
+        let mut category_id = None;
+        let mut tags = vec!["a".to_string(), "d".to_string()];
+
+        action.apply(&mut category_id, &mut tags);
+        assert_eq!(category_id, Some(10));
+        assert_eq!(tags, ["a", "b", "d"]);
+    }
+}
@@ -0,0 +1,142 @@
+use sqlx::types::Json;
+use sqlx::PgPool;
+
+use crate::modules::categories::models::CategoryKind;
+use crate::modules::rules::models::{Rule, RuleCondition, RuleDraft};
+use crate::modules::tags::repositories::add_transaction_tags;
+
+pub async fn insert_rule(pool: &PgPool, user_id: i32, draft: &RuleDraft) -> Result<Rule, sqlx::Error> {
+    let record = sqlx::query!(
+        "INSERT INTO rules (user_id, name, priority, conditions, category_id, tags, enabled) VALUES ($1, $2, $3, $4, $5, $6, $7) RETURNING id",
+        user_id,
+        draft.name,
+        draft.priority,
+        Json(&draft.conditions) as _,
+        draft.category_id,
+        &draft.tags,
+        draft.enabled
+    )
+        .fetch_one(pool)
+        .await?;
+
+    find_rule_by_id(pool, record.id).await?.ok_or(sqlx::Error::RowNotFound)
+}
+
+pub async fn find_rule_by_id(pool: &PgPool, id: i32) -> Result<Option<Rule>, sqlx::Error> {
+    let record = sqlx::query!(
+        r#"
+        SELECT r.id, r.user_id, r.name, r.priority, r.conditions as "conditions: Json<Vec<RuleCondition>>", r.category_id,
+            c.kind as "category_kind?: CategoryKind", r.tags, r.enabled, r.created_at, r.updated_at
+        FROM rules r
+        LEFT JOIN categories c ON c.id = r.category_id
+        WHERE r.id = $1
+        "#,
+        id
+    )
+        .fetch_optional(pool)
+        .await?;
+
+    Ok(record.map(|r| Rule {
+        id: r.id,
+        user_id: r.user_id,
+        name: r.name,
+        priority: r.priority,
+        conditions: r.conditions.0,
+        category_id: r.category_id,
+        category_kind: r.category_kind,
+        tags: r.tags,
+        enabled: r.enabled,
+        created_at: r.created_at,
+        updated_at: r.updated_at,
+    }))
+}
+
+/// A user's rules in the order they are tried: by priority, then by age.
+pub async fn find_rules_by_user_id(pool: &PgPool, user_id: i32) -> Result<Vec<Rule>, sqlx::Error> {
+    let records = sqlx::query!(
+        r#"
+        SELECT r.id, r.user_id, r.name, r.priority, r.conditions as "conditions: Json<Vec<RuleCondition>>", r.category_id,
+            c.kind as "category_kind?: CategoryKind", r.tags, r.enabled, r.created_at, r.updated_at
+        FROM rules r
+        LEFT JOIN categories c ON c.id = r.category_id
+        WHERE r.user_id = $1
+        ORDER BY r.priority, r.id
+        "#,
+        user_id
+    )
+        .fetch_all(pool)
+        .await?;
+
+    Ok(records
+        .into_iter()
+        .map(|r| Rule {
+            id: r.id,
+            user_id: r.user_id,
+            name: r.name,
+            priority: r.priority,
+            conditions: r.conditions.0,
+            category_id: r.category_id,
+            category_kind: r.category_kind,
+            tags: r.tags,
+            enabled: r.enabled,
+            created_at: r.created_at,
+            updated_at: r.updated_at,
+        })
+        .collect())
+}
+
+pub async fn update_rule(pool: &PgPool, id: i32, draft: &RuleDraft) -> Result<Option<Rule>, sqlx::Error> {
+    // updated_at is maintained by the update_rules_updated_at trigger
+    let result = sqlx::query!(
+        "UPDATE rules SET name = $2, priority = $3, conditions = $4, category_id = $5, tags = $6, enabled = $7 WHERE id = $1",
+        id,
+        draft.name,
+        draft.priority,
+        Json(&draft.conditions) as _,
+        draft.category_id,
+        &draft.tags,
+        draft.enabled
+    )
+        .execute(pool)
+        .await?;
+
+    if result.rows_affected() == 0 {
+        return Ok(None);
+    }
+    find_rule_by_id(pool, id).await
+}
+
+pub async fn delete_rule(pool: &PgPool, id: i32) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query!("DELETE FROM rules WHERE id = $1", id)
+        .execute(pool)
+        .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// Saves what re-applied rules changed, all together: new categories as
+/// `(transaction_id, category_id)` and added tags as `(transaction_id, name)`.
+pub async fn save_rule_changes(
+    pool: &PgPool,
+    user_id: i32,
+    categories: &[(i32, i32)],
+    tags: &[(i32, String)],
+) -> Result<(), sqlx::Error> {
+    let mut tx = pool.begin().await?;
+
+    let (transaction_ids, category_ids): (Vec<i32>, Vec<i32>) = categories.iter().copied().unzip();
+    sqlx::query!(
+        r#"
+        UPDATE transactions t SET category_id = c.category_id
+        FROM UNNEST($1::int[], $2::int[]) AS c(transaction_id, category_id)
+        WHERE t.id = c.transaction_id
+        "#,
+        &transaction_ids,
+        &category_ids
+    )
+        .execute(&mut *tx)
+        .await?;
+    add_transaction_tags(&mut tx, user_id, tags).await?;
+
+    tx.commit().await
+}
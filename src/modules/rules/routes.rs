@@ -0,0 +1,141 @@
+use actix_web::{http::StatusCode, web, HttpResponse, HttpRequest};
+use validator::Validate;
+
+use crate::modules::rules::dtos::{ApplyRulesQuery, RuleRequest};
+use crate::modules::rules::models::RuleDraft;
+use crate::modules::rules::services::{
+    create_user_rule, list_user_rules, get_accessible_rule, update_user_rule, remove_user_rule, apply_user_rules,
+};
+use crate::utils::response::GenericResponse;
+use crate::utils::errors::AppError;
+use crate::middleware::jwt::{request_claims, JwtMiddleware};
+use crate::AppState;
+
+pub fn init(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/rules")
+            .service(
+                web::resource("")
+                    .wrap(JwtMiddleware)
+                    .route(web::post().to(add_rule))
+                    .route(web::get().to(list_rules))
+            )
+            .service(
+                web::resource("/apply")
+                    .wrap(JwtMiddleware)
+                    .route(web::post().to(post_apply_rules))
+            )
+            .service(
+                web::resource("/{id}")
+                    .wrap(JwtMiddleware)
+                    .route(web::get().to(get_rule))
+                    .route(web::put().to(put_rule))
+                    .route(web::delete().to(delete_rule))
+            ),
+    );
+}
+
+async fn add_rule(
+    state: web::Data<AppState>,
+    rule: web::Json<RuleRequest>,
+    req: HttpRequest,
+) -> Result<HttpResponse, AppError> {
+    rule.validate()?;
+    let claims = request_claims(&req)?;
+
+    let draft = RuleDraft::from(rule.into_inner());
+    let rule = create_user_rule(&state.db, claims.user_id, &draft).await?;
+    log::info!("Rule created for user {}: {}", claims.user_id, rule);
+
+    Ok(HttpResponse::Created().json(GenericResponse {
+        status: StatusCode::CREATED.as_u16(),
+        data: Some(rule),
+        message: "Rule created successfully".to_string(),
+    }))
+}
+
+async fn list_rules(state: web::Data<AppState>, req: HttpRequest) -> Result<HttpResponse, AppError> {
+    let claims = request_claims(&req)?;
+    let rules = list_user_rules(&state.db, claims.user_id).await?;
+
+    Ok(HttpResponse::Ok().json(GenericResponse {
+        status: StatusCode::OK.as_u16(),
+        data: Some(rules),
+        message: "Rules retrieved successfully".to_string(),
+    }))
+}
+
+async fn get_rule(
+    state: web::Data<AppState>,
+    path: web::Path<i32>,
+    req: HttpRequest,
+) -> Result<HttpResponse, AppError> {
+    let claims = request_claims(&req)?;
+    let rule = get_accessible_rule(&state.db, &claims, path.into_inner()).await?;
+
+    Ok(HttpResponse::Ok().json(GenericResponse {
+        status: StatusCode::OK.as_u16(),
+        data: Some(rule),
+        message: "Rule retrieved successfully".to_string(),
+    }))
+}
+
+async fn put_rule(
+    state: web::Data<AppState>,
+    path: web::Path<i32>,
+    rule: web::Json<RuleRequest>,
+    req: HttpRequest,
+) -> Result<HttpResponse, AppError> {
+    rule.validate()?;
+    let claims = request_claims(&req)?;
+
+    let draft = RuleDraft::from(rule.into_inner());
+    let rule = update_user_rule(&state.db, &claims, path.into_inner(), &draft).await?;
+    log::info!("Rule updated by user {}: {}", claims.user_id, rule);
+
+    Ok(HttpResponse::Ok().json(GenericResponse {
+        status: StatusCode::OK.as_u16(),
+        data: Some(rule),
+        message: "Rule updated successfully".to_string(),
+    }))
+}
+
+async fn delete_rule(
+    state: web::Data<AppState>,
+    path: web::Path<i32>,
+    req: HttpRequest,
+) -> Result<HttpResponse, AppError> {
+    let claims = request_claims(&req)?;
+    let id = path.into_inner();
+
+    remove_user_rule(&state.db, &claims, id).await?;
+    log::info!("Rule {} deleted by user {}", id, claims.user_id);
+
+    Ok(HttpResponse::Ok().json(GenericResponse {
+        status: StatusCode::OK.as_u16(),
+        data: None::<()>,
+        message: "Rule deleted successfully".to_string(),
+    }))
+}
+
+async fn post_apply_rules(
+    state: web::Data<AppState>,
+    query: web::Query<ApplyRulesQuery>,
+    req: HttpRequest,
+) -> Result<HttpResponse, AppError> {
+    let claims = request_claims(&req)?;
+    let application = apply_user_rules(&state.db, claims.user_id, &query).await?;
+
+    let message = if application.dry_run {
+        format!("Rules would change {} of {} transactions", application.changed, application.examined)
+    } else {
+        log::info!("Rules re-applied for user {}: {} transactions changed", claims.user_id, application.changed);
+        format!("Rules changed {} of {} transactions", application.changed, application.examined)
+    };
+
+    Ok(HttpResponse::Ok().json(GenericResponse {
+        status: StatusCode::OK.as_u16(),
+        data: Some(application),
+        message,
+    }))
+}
@@ -0,0 +1,195 @@
+use futures::StreamExt;
+use log::warn;
+use sqlx::PgPool;
+
+use crate::modules::accounts::repositories::find_account_by_id;
+use crate::modules::categories::repositories::find_category_by_id;
+use crate::modules::rules::dtos::ApplyRulesQuery;
+use crate::modules::rules::models::{first_action, Rule, RuleApplication, RuleChange, RuleCondition, RuleDraft, RuleSubject};
+use crate::modules::rules::repositories::{
+    insert_rule, find_rule_by_id, find_rules_by_user_id, update_rule, delete_rule, save_rule_changes,
+};
+use crate::modules::transactions::models::{TransactionDraft, TransactionFilter, TransactionKind, TransactionSortField};
+use crate::modules::transactions::repositories::stream_transactions;
+use crate::utils::errors::AppError;
+use crate::utils::jwt::Claims;
+use crate::utils::pagination::SortDirection;
+
+/// Checks that a rule only refers to the user's own accounts and categories, and that it
+/// does something when it matches: sets a category, adds tags or both.
+async fn ensure_valid_rule(pool: &PgPool, user_id: i32, draft: &RuleDraft) -> Result<(), AppError> {
+    if draft.category_id.is_none() && draft.tags.is_empty() {
+        return Err(AppError::invalid_field("category_id", "required", "A rule needs a category or tags to add"));
+    }
+    if let Some(category_id) = draft.category_id {
+        match find_category_by_id(pool, category_id).await? {
+            Some(category) if category.user_id == user_id => {}
+            _ => return Err(AppError::invalid_field("category_id", "category", "Category not found")),
+        }
+    }
+
+    for condition in &draft.conditions {
+        match condition {
+            RuleCondition::AccountId { value } => match find_account_by_id(pool, *value).await? {
+                Some(account) if account.user_id == user_id => {}
+                _ => return Err(AppError::invalid_field("conditions", "account", "Account not found")),
+            },
+            RuleCondition::Kind { value: TransactionKind::Transfer } => {
+                return Err(AppError::invalid_field("conditions", "transfer", "Rules do not apply to transfers"));
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+pub async fn create_user_rule(pool: &PgPool, user_id: i32, draft: &RuleDraft) -> Result<Rule, AppError> {
+    ensure_valid_rule(pool, user_id, draft).await?;
+
+    Ok(insert_rule(pool, user_id, draft).await?)
+}
+
+pub async fn list_user_rules(pool: &PgPool, user_id: i32) -> Result<Vec<Rule>, AppError> {
+    Ok(find_rules_by_user_id(pool, user_id).await?)
+}
+
+/// Loads rule `id`; admins may load any user's rules, other users only their own.
+pub async fn get_accessible_rule(pool: &PgPool, claims: &Claims, id: i32) -> Result<Rule, AppError> {
+    let rule = find_rule_by_id(pool, id)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("Rule {} not found", id)))?;
+
+    if rule.user_id != claims.user_id && !claims.is_admin() {
+        warn!("User {} attempted to access rule {} owned by user {}", claims.user_id, id, rule.user_id);
+        return Err(AppError::Forbidden("You do not have access to this rule".to_string()));
+    }
+
+    Ok(rule)
+}
+
+pub async fn update_user_rule(pool: &PgPool, claims: &Claims, id: i32, draft: &RuleDraft) -> Result<Rule, AppError> {
+    let rule = get_accessible_rule(pool, claims, id).await?;
+    ensure_valid_rule(pool, rule.user_id, draft).await?;
+
+    update_rule(pool, id, draft)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("Rule {} not found", id)))
+}
+
+/// Deletes a rule. Transactions it already categorized keep their category.
+pub async fn remove_user_rule(pool: &PgPool, claims: &Claims, id: i32) -> Result<(), AppError> {
+    get_accessible_rule(pool, claims, id).await?;
+
+    if !delete_rule(pool, id).await? {
+        return Err(AppError::NotFound(format!("Rule {} not found", id)));
+    }
+    Ok(())
+}
+
+/// Runs the user's rules over a transaction about to be recorded, giving it the category
+/// and tags of the first rule that applies. Returns the id of that rule. Transfers are
+/// left alone.
+pub async fn apply_rules_to_draft(pool: &PgPool, user_id: i32, draft: &mut TransactionDraft) -> Result<Option<i32>, AppError> {
+    if draft.kind == TransactionKind::Transfer {
+        return Ok(None);
+    }
+    let rules = find_rules_by_user_id(pool, user_id).await?;
+
+    let subject = RuleSubject {
+        account_id: draft.account_id,
+        kind: draft.kind,
+        amount: &draft.amount,
+        description: &draft.description,
+        category_id: draft.category_id,
+        split: !draft.splits.is_empty(),
+    };
+    let Some(action) = first_action(&rules, &subject) else {
+        return Ok(None);
+    };
+    action.apply(&mut draft.category_id, &mut draft.tags);
+
+    Ok(Some(action.rule.id))
+}
+
+/// Runs the user's rules over their recorded transactions, oldest first, and lists the
+/// changes they lead to. The changes are saved together unless it is a dry run. Rules
+/// only give a category to uncategorized transactions unless `overwrite` is set; tags are
+/// added either way.
+pub async fn apply_user_rules(pool: &PgPool, user_id: i32, query: &ApplyRulesQuery) -> Result<RuleApplication, AppError> {
+    if let (Some(from), Some(to)) = (query.from, query.to)
+        && to < from
+    {
+        return Err(AppError::invalid_field("to", "range", "The range must end after it starts"));
+    }
+    let rules = find_rules_by_user_id(pool, user_id).await?;
+
+    let filter = TransactionFilter {
+        account_id: query.account_id,
+        from: query.from,
+        to: query.to,
+        ..TransactionFilter::default()
+    };
+    let mut rows = stream_transactions(pool.clone(), user_id, filter, TransactionSortField::OccurredOn, SortDirection::Asc);
+
+    let mut examined = 0;
+    let mut changes = Vec::new();
+    while let Some(transaction) = rows.next().await {
+        let transaction = transaction?;
+        if transaction.kind == TransactionKind::Transfer {
+            continue;
+        }
+        examined += 1;
+
+        let subject = RuleSubject {
+            account_id: transaction.account_id,
+            kind: transaction.kind,
+            amount: &transaction.amount,
+            description: transaction.description.as_deref().unwrap_or_default(),
+            category_id: transaction.category_id.filter(|_| !query.overwrite),
+            split: !transaction.splits.is_empty(),
+        };
+        let Some(action) = first_action(&rules, &subject) else {
+            continue;
+        };
+        let mut category_id = subject.category_id;
+        let mut tags = transaction.tags.clone();
+        action.apply(&mut category_id, &mut tags);
+
+        let category_id = category_id.or(transaction.category_id);
+        let added_tags: Vec<String> = tags.into_iter().filter(|tag| !transaction.tags.contains(tag)).collect();
+        if category_id == transaction.category_id && added_tags.is_empty() {
+            continue;
+        }
+        changes.push(RuleChange {
+            transaction_id: transaction.id,
+            occurred_on: transaction.occurred_on,
+            description: transaction.description,
+            amount: transaction.amount,
+            rule_id: action.rule.id,
+            rule_name: action.rule.name.clone(),
+            from_category_id: transaction.category_id,
+            to_category_id: category_id,
+            added_tags,
+        });
+    }
+
+    if !query.dry_run && !changes.is_empty() {
+        let categories: Vec<(i32, i32)> = changes
+            .iter()
+            .filter(|change| change.to_category_id != change.from_category_id)
+            .filter_map(|change| Some((change.transaction_id, change.to_category_id?)))
+            .collect();
+        let tags: Vec<(i32, String)> = changes
+            .iter()
+            .flat_map(|change| change.added_tags.iter().map(|tag| (change.transaction_id, tag.clone())))
+            .collect();
+        save_rule_changes(pool, user_id, &categories, &tags).await?;
+    }
+
+    Ok(RuleApplication {
+        dry_run: query.dry_run,
+        examined,
+        changed: changes.len(),
+        changes,
+    })
+}
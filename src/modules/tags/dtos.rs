@@ -0,0 +1,31 @@
+use serde::Deserialize;
+use validator::{Validate, ValidationError};
+
+use crate::utils::constants::labels::{MAX_TAG_LENGTH, MAX_TAGS_PER_TRANSACTION};
+
+#[derive(Deserialize, Validate)]
+pub struct TagRequest {
+    #[validate(custom(function = "validate_tag"))]
+    pub name: String,
+}
+
+/// A tag name must have 1 to 50 characters once trimmed, and no commas since lists of
+/// tags are written comma-separated in query strings.
+pub(crate) fn validate_tag(name: &str) -> Result<(), ValidationError> {
+    let name = name.trim();
+    if name.is_empty() || name.chars().count() > MAX_TAG_LENGTH || name.contains(',') {
+        let mut error = ValidationError::new("tag");
+        error.message = Some("Tags must be between 1 and 50 characters and cannot contain commas".into());
+        return Err(error);
+    }
+    Ok(())
+}
+
+pub(crate) fn validate_tags(tags: &[String]) -> Result<(), ValidationError> {
+    if tags.len() > MAX_TAGS_PER_TRANSACTION {
+        let mut error = ValidationError::new("length");
+        error.message = Some("At most 20 tags can be given".into());
+        return Err(error);
+    }
+    tags.iter().try_for_each(|tag| validate_tag(tag))
+}
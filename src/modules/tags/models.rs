@@ -0,0 +1,36 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::fmt;
+
+#[derive(Serialize)]
+pub struct Tag {
+    pub id: i32,
+    pub user_id: i32,
+    pub name: String,
+    /// Transactions the tag is on.
+    pub transaction_count: i64,
+    pub created_at: DateTime<Utc>,
+}
+
+impl fmt::Display for Tag {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Tag {{ id: {}, user_id: {}, name: {}, transaction_count: {} }}",
+            self.id, self.user_id, self.name, self.transaction_count
+        )
+    }
+}
+
+/// A tag name as it is stored: trimmed and lowercased, so `Vacation ` and `vacation` are one tag.
+pub fn normalize_tag(name: &str) -> String {
+    name.trim().to_lowercase()
+}
+
+/// Tag names as stored, sorted and without repeats.
+pub fn normalize_tags(names: &[String]) -> Vec<String> {
+    let mut tags: Vec<String> = names.iter().map(|name| normalize_tag(name)).collect();
+    tags.sort();
+    tags.dedup();
+    tags
+}
@@ -0,0 +1,122 @@
+use sqlx::{PgConnection, PgPool};
+
+use crate::modules::tags::models::Tag;
+
+pub async fn find_tags_by_user_id(pool: &PgPool, user_id: i32) -> Result<Vec<Tag>, sqlx::Error> {
+    sqlx::query_as!(
+        Tag,
+        r#"
+        SELECT g.id, g.user_id, g.name, COUNT(tt.transaction_id) as "transaction_count!", g.created_at
+        FROM tags g
+        LEFT JOIN transaction_tags tt ON tt.tag_id = g.id
+        WHERE g.user_id = $1
+        GROUP BY g.id
+        ORDER BY g.name
+        "#,
+        user_id
+    )
+        .fetch_all(pool)
+        .await
+}
+
+pub async fn find_tag_by_id(pool: &PgPool, id: i32) -> Result<Option<Tag>, sqlx::Error> {
+    sqlx::query_as!(
+        Tag,
+        r#"
+        SELECT g.id, g.user_id, g.name, COUNT(tt.transaction_id) as "transaction_count!", g.created_at
+        FROM tags g
+        LEFT JOIN transaction_tags tt ON tt.tag_id = g.id
+        WHERE g.id = $1
+        GROUP BY g.id
+        "#,
+        id
+    )
+        .fetch_optional(pool)
+        .await
+}
+
+pub async fn rename_tag(pool: &PgPool, id: i32, name: &str) -> Result<Option<Tag>, sqlx::Error> {
+    let result = sqlx::query!("UPDATE tags SET name = $2 WHERE id = $1", id, name)
+        .execute(pool)
+        .await?;
+
+    if result.rows_affected() == 0 {
+        return Ok(None);
+    }
+    find_tag_by_id(pool, id).await
+}
+
+/// Deletes a tag, taking it off every transaction through ON DELETE CASCADE.
+pub async fn delete_tag(pool: &PgPool, id: i32) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query!("DELETE FROM tags WHERE id = $1", id)
+        .execute(pool)
+        .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// Replaces a transaction's tags with `names`, creating the user's tags that don't exist
+/// yet, and returns them sorted. `names` must already be normalized and without repeats.
+pub async fn set_transaction_tags(
+    conn: &mut PgConnection,
+    user_id: i32,
+    transaction_id: i32,
+    names: &[String],
+) -> Result<Vec<String>, sqlx::Error> {
+    // The no-op update makes existing tags show up in RETURNING next to the new ones
+    let records = sqlx::query!(
+        r#"
+        WITH named AS (
+            INSERT INTO tags (user_id, name) SELECT $1, UNNEST($3::varchar[])
+            ON CONFLICT (user_id, name) DO UPDATE SET name = EXCLUDED.name
+            RETURNING id, name
+        ),
+        cleared AS (
+            DELETE FROM transaction_tags WHERE transaction_id = $2 AND tag_id NOT IN (SELECT id FROM named)
+        ),
+        linked AS (
+            INSERT INTO transaction_tags (transaction_id, tag_id) SELECT $2, id FROM named
+            ON CONFLICT DO NOTHING
+        )
+        SELECT name FROM named ORDER BY name
+        "#,
+        user_id,
+        transaction_id,
+        names
+    )
+        .fetch_all(&mut *conn)
+        .await?;
+
+    Ok(records.into_iter().map(|r| r.name).collect())
+}
+
+/// Puts tags on transactions, given as `(transaction_id, name)` pairs of one user's, and
+/// creates the tags that don't exist yet. Tags a transaction already has are left as they are.
+pub async fn add_transaction_tags(
+    conn: &mut PgConnection,
+    user_id: i32,
+    tags: &[(i32, String)],
+) -> Result<(), sqlx::Error> {
+    let (transaction_ids, names): (Vec<i32>, Vec<String>) = tags.iter().cloned().unzip();
+    sqlx::query!(
+        r#"
+        WITH named AS (
+            INSERT INTO tags (user_id, name) SELECT DISTINCT $1::int, n.name FROM UNNEST($3::varchar[]) AS n(name)
+            ON CONFLICT (user_id, name) DO UPDATE SET name = EXCLUDED.name
+            RETURNING id, name
+        )
+        INSERT INTO transaction_tags (transaction_id, tag_id)
+        SELECT t.transaction_id, named.id
+        FROM UNNEST($2::int[], $3::varchar[]) AS t(transaction_id, name)
+        JOIN named ON named.name = t.name
+        ON CONFLICT DO NOTHING
+        "#,
+        user_id,
+        &transaction_ids,
+        &names
+    )
+        .execute(&mut *conn)
+        .await?;
+
+    Ok(())
+}
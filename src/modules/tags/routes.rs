@@ -0,0 +1,90 @@
+use actix_web::{http::StatusCode, web, HttpResponse, HttpRequest};
+use validator::Validate;
+
+use crate::modules::tags::dtos::TagRequest;
+use crate::modules::tags::services::{list_user_tags, get_accessible_tag, rename_user_tag, remove_user_tag};
+use crate::utils::response::GenericResponse;
+use crate::utils::errors::AppError;
+use crate::middleware::jwt::{request_claims, JwtMiddleware};
+use crate::AppState;
+
+pub fn init(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/tags")
+            .service(
+                web::resource("")
+                    .wrap(JwtMiddleware)
+                    .route(web::get().to(list_tags))
+            )
+            .service(
+                web::resource("/{id}")
+                    .wrap(JwtMiddleware)
+                    .route(web::get().to(get_tag))
+                    .route(web::put().to(put_tag))
+                    .route(web::delete().to(delete_tag))
+            ),
+    );
+}
+
+async fn list_tags(state: web::Data<AppState>, req: HttpRequest) -> Result<HttpResponse, AppError> {
+    let claims = request_claims(&req)?;
+    let tags = list_user_tags(&state.db, claims.user_id).await?;
+
+    Ok(HttpResponse::Ok().json(GenericResponse {
+        status: StatusCode::OK.as_u16(),
+        data: Some(tags),
+        message: "Tags retrieved successfully".to_string(),
+    }))
+}
+
+async fn get_tag(
+    state: web::Data<AppState>,
+    path: web::Path<i32>,
+    req: HttpRequest,
+) -> Result<HttpResponse, AppError> {
+    let claims = request_claims(&req)?;
+    let tag = get_accessible_tag(&state.db, &claims, path.into_inner()).await?;
+
+    Ok(HttpResponse::Ok().json(GenericResponse {
+        status: StatusCode::OK.as_u16(),
+        data: Some(tag),
+        message: "Tag retrieved successfully".to_string(),
+    }))
+}
+
+async fn put_tag(
+    state: web::Data<AppState>,
+    path: web::Path<i32>,
+    tag: web::Json<TagRequest>,
+    req: HttpRequest,
+) -> Result<HttpResponse, AppError> {
+    tag.validate()?;
+    let claims = request_claims(&req)?;
+
+    let tag = rename_user_tag(&state.db, &claims, path.into_inner(), &tag.name).await?;
+    log::info!("Tag renamed by user {}: {}", claims.user_id, tag);
+
+    Ok(HttpResponse::Ok().json(GenericResponse {
+        status: StatusCode::OK.as_u16(),
+        data: Some(tag),
+        message: "Tag updated successfully".to_string(),
+    }))
+}
+
+async fn delete_tag(
+    state: web::Data<AppState>,
+    path: web::Path<i32>,
+    req: HttpRequest,
+) -> Result<HttpResponse, AppError> {
+    let claims = request_claims(&req)?;
+    let id = path.into_inner();
+
+    remove_user_tag(&state.db, &claims, id).await?;
+    log::info!("Tag {} deleted by user {}", id, claims.user_id);
+
+    Ok(HttpResponse::Ok().json(GenericResponse {
+        status: StatusCode::OK.as_u16(),
+        data: None::<()>,
+        message: "Tag deleted successfully".to_string(),
+    }))
+}
@@ -0,0 +1,44 @@
+use log::warn;
+use sqlx::PgPool;
+
+use crate::modules::tags::models::{normalize_tag, Tag};
+use crate::modules::tags::repositories::{find_tags_by_user_id, find_tag_by_id, rename_tag, delete_tag};
+use crate::utils::errors::AppError;
+use crate::utils::jwt::Claims;
+
+pub async fn list_user_tags(pool: &PgPool, user_id: i32) -> Result<Vec<Tag>, AppError> {
+    Ok(find_tags_by_user_id(pool, user_id).await?)
+}
+
+/// Loads tag `id` when the caller owns it or is an admin.
+pub async fn get_accessible_tag(pool: &PgPool, claims: &Claims, id: i32) -> Result<Tag, AppError> {
+    let tag = find_tag_by_id(pool, id)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("Tag {} not found", id)))?;
+
+    if tag.user_id != claims.user_id && !claims.is_admin() {
+        warn!("User {} attempted to access tag {} owned by user {}", claims.user_id, id, tag.user_id);
+        return Err(AppError::Forbidden("You do not have access to this tag".to_string()));
+    }
+
+    Ok(tag)
+}
+
+/// Renames a tag on every transaction it is on.
+pub async fn rename_user_tag(pool: &PgPool, claims: &Claims, id: i32, name: &str) -> Result<Tag, AppError> {
+    get_accessible_tag(pool, claims, id).await?;
+
+    rename_tag(pool, id, &normalize_tag(name))
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("Tag {} not found", id)))
+}
+
+/// Deletes a tag and takes it off every transaction; the transactions are kept.
+pub async fn remove_user_tag(pool: &PgPool, claims: &Claims, id: i32) -> Result<(), AppError> {
+    get_accessible_tag(pool, claims, id).await?;
+
+    if !delete_tag(pool, id).await? {
+        return Err(AppError::NotFound(format!("Tag {} not found", id)));
+    }
+    Ok(())
+}
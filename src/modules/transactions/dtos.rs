@@ -1,25 +1,268 @@
-use serde::Deserialize;
-use validator::{Validate};
+use bigdecimal::BigDecimal;
+use chrono::{NaiveDate, NaiveTime};
+use serde::{Deserialize, Serialize};
+use validator::{Validate, ValidationError};
+
+use crate::modules::tags::dtos::validate_tags;
+use crate::modules::tags::models::normalize_tags;
+use crate::modules::transactions::models::{
+    ExportFormat, SplitDraft, TagMatch, TransactionDraft, TransactionFilter, TransactionKind, TransactionSortField, TransferDraft,
+};
+use crate::utils::money::{deserialize_amount, deserialize_optional_amount, has_money_scale, serialize_amount};
+use crate::utils::pagination::SortDirection;
 
 #[derive(Deserialize, Validate)]
 pub struct TransactionRequest {
-    #[validate(range(min = 100.0, max = 1000000.0, message = "Amount must be between 100 and 1,000,000"))]
-    pub amount: f64,
-    #[validate(length(min = 1, max = 50, message = "Category must be between 1 and 50 characters"))]
-    pub category: String,
+    pub account_id: i32,
+    pub kind: TransactionKind,
+    #[serde(deserialize_with = "deserialize_amount")]
+    #[validate(custom(function = "validate_signed_amount"))]
+    pub amount: BigDecimal,
+    pub category_id: Option<i32>,
     #[validate(length(max = 255, message = "Description cannot exceed 255 characters"))]
     pub description: String,
+    #[serde(default, deserialize_with = "date_format::deserialize")]
+    pub occurred_on: Option<NaiveDate>,
+    #[serde(default)]
+    pub occurred_time: Option<NaiveTime>,
+    #[serde(default)]
+    #[validate(custom(function = "validate_tags"))]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    #[validate(nested, custom(function = "validate_splits"))]
+    pub splits: Vec<SplitRequest>,
+}
+
+#[derive(Serialize, Deserialize, Validate)]
+pub struct SplitRequest {
+    pub category_id: i32,
+    #[serde(serialize_with = "serialize_amount", deserialize_with = "deserialize_amount")]
+    #[validate(custom(function = "validate_split_amount"))]
+    pub amount: BigDecimal,
+    #[validate(length(max = 255, message = "Memo cannot exceed 255 characters"))]
+    pub memo: Option<String>,
+}
+
+impl From<&SplitRequest> for SplitDraft {
+    fn from(request: &SplitRequest) -> Self {
+        SplitDraft {
+            category_id: request.category_id,
+            amount: request.amount.clone(),
+            memo: request.memo.clone(),
+        }
+    }
+}
+
+impl From<TransactionRequest> for TransactionDraft {
+    fn from(request: TransactionRequest) -> Self {
+        TransactionDraft {
+            account_id: request.account_id,
+            kind: request.kind,
+            amount: request.amount,
+            category_id: request.category_id,
+            description: request.description,
+            occurred_on: request.occurred_on,
+            occurred_time: request.occurred_time,
+            external_id: None,
+            tags: normalize_tags(&request.tags),
+            splits: request.splits.iter().map(SplitDraft::from).collect(),
+        }
+    }
+}
+
+#[derive(Deserialize, Validate)]
+pub struct TransactionPatchRequest {
+    pub account_id: Option<i32>,
+    pub kind: Option<TransactionKind>,
+    #[serde(default, deserialize_with = "deserialize_optional_amount")]
+    #[validate(custom(function = "validate_signed_amount"))]
+    pub amount: Option<BigDecimal>,
+    pub category_id: Option<i32>,
+    #[validate(length(max = 255, message = "Description cannot exceed 255 characters"))]
+    pub description: Option<String>,
+    #[serde(default, deserialize_with = "date_format::deserialize")]
+    pub occurred_on: Option<NaiveDate>,
+    /// Left as it is when missing; `null` removes the time.
+    #[serde(default, deserialize_with = "deserialize_nullable")]
+    pub occurred_time: Option<Option<NaiveTime>>,
+    /// Replaces the transaction's tags when given.
+    #[validate(custom(function = "validate_tags"))]
+    pub tags: Option<Vec<String>>,
+    /// Replaces the transaction's splits when given; an empty list removes them.
+    #[validate(nested, custom(function = "validate_splits"))]
+    pub splits: Option<Vec<SplitRequest>>,
+}
+
+#[derive(Deserialize, Validate)]
+pub struct TransferRequest {
+    pub from_account_id: i32,
+    pub to_account_id: i32,
+    #[serde(deserialize_with = "deserialize_amount")]
+    #[validate(custom(function = "validate_amount"))]
+    pub amount: BigDecimal,
+    #[serde(default)]
+    #[validate(length(max = 255, message = "Description cannot exceed 255 characters"))]
+    pub description: String,
+    #[serde(default, deserialize_with = "date_format::deserialize")]
+    pub occurred_on: Option<NaiveDate>,
+    #[serde(default)]
+    pub occurred_time: Option<NaiveTime>,
+}
+
+impl From<TransferRequest> for TransferDraft {
+    fn from(request: TransferRequest) -> Self {
+        TransferDraft {
+            from_account_id: request.from_account_id,
+            to_account_id: request.to_account_id,
+            amount: request.amount,
+            description: request.description,
+            occurred_on: request.occurred_on,
+            occurred_time: request.occurred_time,
+        }
+    }
+}
+
+#[derive(Deserialize, Validate)]
+pub struct TransactionListQuery {
+    pub account_id: Option<i32>,
+    #[serde(default, deserialize_with = "date_format::deserialize")]
+    pub from: Option<NaiveDate>,
+    #[serde(default, deserialize_with = "date_format::deserialize")]
+    pub to: Option<NaiveDate>,
+    pub category_id: Option<i32>,
+    pub kind: Option<TransactionKind>,
+    #[serde(default, deserialize_with = "deserialize_optional_amount")]
+    pub min_amount: Option<BigDecimal>,
+    #[serde(default, deserialize_with = "deserialize_optional_amount")]
+    pub max_amount: Option<BigDecimal>,
+    #[validate(length(min = 1, max = 255, message = "Search text must be between 1 and 255 characters"))]
+    pub q: Option<String>,
+    /// Comma-separated tag names.
+    #[validate(custom(function = "validate_tag_list"))]
+    pub tags: Option<String>,
+    #[serde(default)]
+    pub tag_match: TagMatch,
+    #[serde(default)]
+    pub sort: TransactionSortField,
+    #[serde(default)]
+    pub order: SortDirection,
+    pub cursor: Option<String>,
+    #[validate(range(min = 1, max = 200, message = "Limit must be between 1 and 200"))]
+    pub limit: Option<i64>,
+}
+
+impl TransactionListQuery {
+    pub fn filter(&self) -> TransactionFilter {
+        TransactionFilter {
+            account_id: self.account_id,
+            from: self.from,
+            to: self.to,
+            category_id: self.category_id,
+            kind: self.kind,
+            min_amount: self.min_amount.clone(),
+            max_amount: self.max_amount.clone(),
+            search: self.q.clone(),
+            tags: self.tags.as_deref().map(split_tags).unwrap_or_default(),
+            tag_match: self.tag_match,
+        }
+    }
+}
+
+/// Format of `GET /transactions/export`; the filters and sort order are read from the
+/// same query string as a `TransactionListQuery`.
+#[derive(Deserialize)]
+pub struct ExportQuery {
+    pub format: ExportFormat,
+}
+
+#[derive(Deserialize)]
+pub struct SummaryQuery {
+    #[serde(default, deserialize_with = "date_format::deserialize")]
+    pub from: Option<NaiveDate>,
+    #[serde(default, deserialize_with = "date_format::deserialize")]
+    pub to: Option<NaiveDate>,
+}
+
+fn split_tags(list: &str) -> Vec<String> {
+    normalize_tags(&list.split(',').map(str::to_string).collect::<Vec<_>>())
+}
+
+fn validate_tag_list(list: &str) -> Result<(), ValidationError> {
+    validate_tags(&list.split(',').map(str::to_string).collect::<Vec<_>>())
+}
+
+/// A transaction is either not split or split into 2 to 50 lines.
+fn validate_splits(splits: &[SplitRequest]) -> Result<(), ValidationError> {
+    if splits.len() == 1 || splits.len() > 50 {
+        let mut error = ValidationError::new("length");
+        error.message = Some("A transaction is split into between 2 and 50 lines".into());
+        return Err(error);
+    }
+    Ok(())
+}
+
+fn validate_split_amount(amount: &BigDecimal) -> Result<(), ValidationError> {
+    if *amount <= BigDecimal::from(0) || *amount > BigDecimal::from(1_000_000) {
+        let mut error = ValidationError::new("range");
+        error.message = Some("Split amounts must be positive and at most 1,000,000".into());
+        return Err(error);
+    }
+    if !has_money_scale(amount) {
+        let mut error = ValidationError::new("scale");
+        error.message = Some("Amount cannot have more than 2 decimal places".into());
+        return Err(error);
+    }
+    Ok(())
+}
+
+pub(crate) fn validate_amount(amount: &BigDecimal) -> Result<(), ValidationError> {
+    if *amount < BigDecimal::from(100) || *amount > BigDecimal::from(1_000_000) {
+        let mut error = ValidationError::new("range");
+        error.message = Some("Amount must be between 100 and 1,000,000".into());
+        return Err(error);
+    }
+    if !has_money_scale(amount) {
+        let mut error = ValidationError::new("scale");
+        error.message = Some("Amount cannot have more than 2 decimal places".into());
+        return Err(error);
+    }
+    Ok(())
+}
+
+/// Like `validate_amount`, but also takes the negative amounts an adjustment can have.
+/// That only adjustments do is checked once the kind is known.
+fn validate_signed_amount(amount: &BigDecimal) -> Result<(), ValidationError> {
+    validate_amount(&amount.abs()).map_err(|mut error| {
+        if error.code == "range" {
+            error.message = Some("Amount must be between 100 and 1,000,000, or -100 and -1,000,000 for an adjustment".into());
+        }
+        error
+    })
+}
+
+/// Tells a field given as `null` (`Some(None)`) apart from a missing one (`None`, with
+/// `#[serde(default)]`).
+fn deserialize_nullable<'de, D, T>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    Option::<T>::deserialize(deserializer).map(Some)
 }
 
 mod date_format {
     use chrono::NaiveDate;
     use serde::{self, Deserialize, Deserializer};
 
-    pub fn deserialize<'de, D>(deserializer: D) -> Result<NaiveDate, D::Error>
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<Option<NaiveDate>, D::Error>
     where
         D: Deserializer<'de>,
     {
-        let s = String::deserialize(deserializer)?;
-        NaiveDate::parse_from_str(&s, "%Y-%m-%d").map_err(serde::de::Error::custom)
+        match Option::<String>::deserialize(deserializer)? {
+            Some(s) => NaiveDate::parse_from_str(&s, "%Y-%m-%d")
+                .map(Some)
+                .map_err(serde::de::Error::custom),
+            None => Ok(None),
+        }
     }
 }
\ No newline at end of file
@@ -0,0 +1,290 @@
+use std::collections::HashMap;
+
+use actix_web::web::{self, Bytes};
+use bigdecimal::{BigDecimal, ToPrimitive};
+use chrono::{NaiveDate, NaiveTime};
+use futures::channel::mpsc;
+use futures::executor::block_on_stream;
+use futures::stream::{self, BoxStream, Stream, StreamExt};
+use futures::SinkExt;
+use rust_xlsxwriter::{Format, Workbook, XlsxError};
+use serde::Serialize;
+
+use crate::modules::transactions::models::{Transaction, TransactionKind};
+use crate::utils::errors::AppError;
+use crate::utils::money::serialize_amount;
+
+/// Columns of every export format, in order.
+const EXPORT_COLUMNS: [&str; 13] = [
+    "id",
+    "occurred_on",
+    "occurred_time",
+    "account_id",
+    "account",
+    "currency",
+    "kind",
+    "amount",
+    "category_id",
+    "category",
+    "description",
+    "counterpart_id",
+    "external_id",
+];
+
+/// Last row index a worksheet has room for; row 0 holds the header.
+const MAX_XLSX_ROW: u32 = 1_048_575;
+
+/// Rows fetched ahead of the thread writing a workbook.
+const XLSX_ROW_BUFFER: usize = 256;
+
+/// A transaction as it is exported: `amount` is signed as stored, and the account and
+/// category are given by name next to their ids.
+#[derive(Serialize)]
+pub struct ExportRow {
+    pub id: i32,
+    pub occurred_on: NaiveDate,
+    pub occurred_time: Option<NaiveTime>,
+    pub account_id: i32,
+    pub account: String,
+    pub currency: String,
+    pub kind: TransactionKind,
+    #[serde(serialize_with = "serialize_amount")]
+    pub amount: BigDecimal,
+    pub category_id: Option<i32>,
+    pub category: Option<String>,
+    pub description: Option<String>,
+    pub counterpart_id: Option<i32>,
+    pub external_id: Option<String>,
+}
+
+/// Names of a user's accounts and categories, looked up once per export.
+pub struct ExportNames {
+    /// Account name and currency by id.
+    pub accounts: HashMap<i32, (String, String)>,
+    /// Category name by id, prefixed with its parent's (`Food > Groceries`).
+    pub categories: HashMap<i32, String>,
+}
+
+impl ExportNames {
+    fn row(&self, transaction: Transaction) -> ExportRow {
+        let (account, currency) = self.accounts.get(&transaction.account_id).cloned().unwrap_or_default();
+        ExportRow {
+            id: transaction.id,
+            occurred_on: transaction.occurred_on,
+            occurred_time: transaction.occurred_time,
+            account_id: transaction.account_id,
+            account,
+            currency,
+            kind: transaction.kind,
+            amount: transaction.amount,
+            category_id: transaction.category_id,
+            category: transaction.category_id.and_then(|id| self.categories.get(&id).cloned()),
+            description: transaction.description,
+            counterpart_id: transaction.counterpart_id,
+            external_id: transaction.external_id,
+        }
+    }
+}
+
+/// An encoded export: streamed chunk by chunk, or built in full before it is sent.
+pub enum TransactionExport {
+    Stream(BoxStream<'static, Result<Bytes, AppError>>),
+    File(Vec<u8>),
+}
+
+/// Encodes rows as CSV with a header line, one chunk per row.
+pub fn csv_stream<S>(rows: S, names: ExportNames) -> BoxStream<'static, Result<Bytes, AppError>>
+where
+    S: Stream<Item = Result<Transaction, sqlx::Error>> + Send + 'static,
+{
+    let rows = rows.map(move |row| csv_line(csv_row(names.row(row?))));
+    stream::once(async { csv_line(EXPORT_COLUMNS) }).chain(rows).boxed()
+}
+
+/// Keeps the free text of a row from being run as a formula when the CSV is opened in a
+/// spreadsheet.
+fn csv_row(row: ExportRow) -> ExportRow {
+    ExportRow {
+        account: csv_text(row.account),
+        category: row.category.map(csv_text),
+        description: row.description.map(csv_text),
+        external_id: row.external_id.map(csv_text),
+        ..row
+    }
+}
+
+/// Prefixes text that a spreadsheet would read as a formula with `'`, which makes it
+/// plain text. Amounts are numbers and keep their `-`.
+fn csv_text(text: String) -> String {
+    if text.starts_with(['=', '+', '-', '@', '\t', '\r']) {
+        format!("'{}", text)
+    } else {
+        text
+    }
+}
+
+fn csv_line(record: impl Serialize) -> Result<Bytes, AppError> {
+    let mut writer = csv::WriterBuilder::new().has_headers(false).from_writer(Vec::new());
+    writer.serialize(record).map_err(csv_error)?;
+    writer
+        .into_inner()
+        .map(Bytes::from)
+        .map_err(|e| csv_error(e.into_error().into()))
+}
+
+fn csv_error(error: csv::Error) -> AppError {
+    AppError::InternalServerError(format!("Failed to write CSV export: {}", error))
+}
+
+/// Encodes rows as JSON Lines: one JSON object per line.
+pub fn jsonl_stream<S>(rows: S, names: ExportNames) -> BoxStream<'static, Result<Bytes, AppError>>
+where
+    S: Stream<Item = Result<Transaction, sqlx::Error>> + Send + 'static,
+{
+    rows.map(move |row| {
+        let mut line = serde_json::to_vec(&names.row(row?))
+            .map_err(|e| AppError::InternalServerError(format!("Failed to write JSON export: {}", e)))?;
+        line.push(b'\n');
+        Ok(Bytes::from(line))
+    })
+    .boxed()
+}
+
+/// Builds an XLSX workbook with one sheet of transactions. A workbook is a zip archive
+/// that can only be written once complete, so it is returned whole. Writing it is blocking
+/// work, done on a thread of its own while the rows are still being fetched.
+pub async fn xlsx_file<S>(mut rows: S, names: ExportNames) -> Result<Vec<u8>, AppError>
+where
+    S: Stream<Item = Result<Transaction, sqlx::Error>> + Unpin,
+{
+    let (mut sender, receiver) = mpsc::channel(XLSX_ROW_BUFFER);
+    let workbook = web::block(move || write_xlsx(block_on_stream(receiver)));
+
+    let mut line: u32 = 0;
+    while let Some(row) = rows.next().await {
+        if line == MAX_XLSX_ROW {
+            return Err(AppError::invalid_field(
+                "format",
+                "rows",
+                "Too many transactions for a spreadsheet; export them as csv or jsonl instead",
+            ));
+        }
+        line += 1;
+        // The writer only hangs up when it failed, which it reports below
+        if sender.send(names.row(row?)).await.is_err() {
+            break;
+        }
+    }
+    drop(sender);
+
+    workbook
+        .await
+        .map_err(|e| AppError::InternalServerError(format!("Failed to write XLSX export: {}", e)))?
+}
+
+/// Writes rows to a workbook until they run out. The worksheet runs in constant memory
+/// mode, spilling rows to a temporary file as they are written.
+fn write_xlsx(rows: impl Iterator<Item = ExportRow>) -> Result<Vec<u8>, AppError> {
+    let header_format = Format::new().set_bold();
+    let date_format = Format::new().set_num_format("yyyy-mm-dd");
+    let time_format = Format::new().set_num_format("hh:mm:ss");
+    let amount_format = Format::new().set_num_format("#,##0.00");
+
+    let mut workbook = Workbook::new();
+    let worksheet = workbook.add_worksheet_with_constant_memory();
+    worksheet.set_name("Transactions").map_err(xlsx_error)?;
+    worksheet.set_column_width(1, 12).map_err(xlsx_error)?;
+    worksheet.set_column_width(4, 20).map_err(xlsx_error)?;
+    worksheet.set_column_width(9, 24).map_err(xlsx_error)?;
+    worksheet.set_column_width(10, 40).map_err(xlsx_error)?;
+    worksheet.set_freeze_panes(1, 0).map_err(xlsx_error)?;
+    worksheet
+        .write_row_with_format(0, 0, EXPORT_COLUMNS, &header_format)
+        .map_err(xlsx_error)?;
+
+    for (row, line) in rows.zip(1..) {
+        worksheet.write_number(line, 0, row.id).map_err(xlsx_error)?;
+        worksheet
+            .write_datetime_with_format(line, 1, row.occurred_on, &date_format)
+            .map_err(xlsx_error)?;
+        if let Some(time) = row.occurred_time {
+            worksheet.write_datetime_with_format(line, 2, time, &time_format).map_err(xlsx_error)?;
+        }
+        worksheet.write_number(line, 3, row.account_id).map_err(xlsx_error)?;
+        worksheet.write_string(line, 4, row.account).map_err(xlsx_error)?;
+        worksheet.write_string(line, 5, row.currency).map_err(xlsx_error)?;
+        worksheet.write_string(line, 6, row.kind.as_str()).map_err(xlsx_error)?;
+        worksheet
+            .write_number_with_format(line, 7, row.amount.to_f64().unwrap_or_default(), &amount_format)
+            .map_err(xlsx_error)?;
+        if let Some(category_id) = row.category_id {
+            worksheet.write_number(line, 8, category_id).map_err(xlsx_error)?;
+        }
+        if let Some(category) = row.category {
+            worksheet.write_string(line, 9, category).map_err(xlsx_error)?;
+        }
+        if let Some(description) = row.description {
+            worksheet.write_string(line, 10, description).map_err(xlsx_error)?;
+        }
+        if let Some(counterpart_id) = row.counterpart_id {
+            worksheet.write_number(line, 11, counterpart_id).map_err(xlsx_error)?;
+        }
+        if let Some(external_id) = row.external_id {
+            worksheet.write_string(line, 12, external_id).map_err(xlsx_error)?;
+        }
+    }
+
+    workbook.save_to_buffer().map_err(xlsx_error)
+}
+
+fn xlsx_error(error: XlsxError) -> AppError {
+    AppError::InternalServerError(format!("Failed to write XLSX export: {}", error))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names() -> ExportNames {
+        ExportNames { accounts: HashMap::new(), categories: HashMap::new() }
+    }
+
+    #[test]
+    fn formulas_are_exported_as_text() {
+        for text in ["=HYPERLINK(\"http://x\")", "+1", "-2+3", "@SUM(A1)", "\tTab"] {
+            assert_eq!(csv_text(text.to_string()), format!("'{}", text));
+        }
+        assert_eq!(csv_text("Coffee - large".to_string()), "Coffee - large");
+        assert_eq!(csv_text(String::new()), "");
+    }
+
+    #[test]
+    fn csv_row_keeps_negative_amounts_as_numbers() {
+        let row = ExportRow {
+            id: 1,
+            occurred_on: NaiveDate::from_ymd_opt(2025, 3, 1).unwrap(),
+            occurred_time: None,
+            account_id: 2,
+            account: "@Checking".to_string(),
+            currency: "USD".to_string(),
+            kind: TransactionKind::Expense,
+            amount: BigDecimal::from(-12),
+            category_id: None,
+            category: None,
+            description: Some("=1+1".to_string()),
+            counterpart_id: None,
+            external_id: Some("-42".to_string()),
+        };
+
+        let line = csv_line(csv_row(row)).unwrap();
+        assert_eq!(&line[..], b"1,2025-03-01,,2,'@Checking,USD,EXPENSE,-12.00,,,'=1+1,,'-42\n");
+    }
+
+    #[actix_web::test]
+    async fn empty_export_is_a_workbook_with_a_header() {
+        let rows = stream::iter(Vec::<Result<Transaction, sqlx::Error>>::new());
+        let workbook = xlsx_file(rows, names()).await.unwrap();
+
+        assert!(workbook.starts_with(b"PK"));
+    }
+}
@@ -0,0 +1,739 @@
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::fmt::Write;
+
+use actix_web::web::Bytes;
+use bigdecimal::{BigDecimal, Zero};
+use chrono::NaiveDate;
+use futures::future;
+use futures::stream::{self, BoxStream, Stream, StreamExt};
+
+use crate::modules::accounts::models::{Account, AccountType};
+use crate::modules::categories::models::{Category, CategoryKind};
+use crate::modules::transactions::models::{JournalTransaction, TransactionKind};
+use crate::utils::errors::AppError;
+
+const OPENING_BALANCES: &str = "Equity:Opening-Balances";
+const ADJUSTMENTS: &str = "Equity:Adjustments";
+/// Books a transfer leg whose other leg no longer exists.
+const TRANSFERS: &str = "Equity:Transfers";
+const UNCATEGORIZED_INCOME: &str = "Income:Uncategorized";
+const UNCATEGORIZED_EXPENSES: &str = "Expenses:Uncategorized";
+
+/// Plain-text accounting syntax a journal is written in. `Ledger` is also read by hledger.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum JournalSyntax {
+    Beancount,
+    Ledger,
+}
+
+/// A user's accounts and categories as journal accounts, with the opening balances that
+/// start the journal.
+pub struct JournalBook {
+    /// Day every journal account is opened and the opening balances are booked on.
+    start: NaiveDate,
+    /// Journal account name and currency by account id.
+    accounts: HashMap<i32, (String, String)>,
+    /// Journal account name by category id.
+    categories: HashMap<i32, String>,
+    /// Every journal account, with its currency if it holds a single one.
+    chart: Vec<(String, Option<String>)>,
+    openings: Vec<(i32, BigDecimal)>,
+}
+
+impl JournalBook {
+    /// Accounts become `Assets:<Type>:<Name>` (credit cards `Liabilities:CreditCard:<Name>`)
+    /// and categories `Income:<Parent>:<Name>` or `Expenses:<Parent>:<Name>`. Names that
+    /// clash once made valid account names get the account or category id appended.
+    pub fn new(
+        start: NaiveDate,
+        accounts: &[Account],
+        categories: &[Category],
+        openings: Vec<(i32, BigDecimal)>,
+    ) -> Self {
+        let mut chart: Vec<(String, Option<String>)> = [
+            OPENING_BALANCES,
+            ADJUSTMENTS,
+            TRANSFERS,
+            UNCATEGORIZED_INCOME,
+            UNCATEGORIZED_EXPENSES,
+        ]
+        .iter()
+        .map(|name| (name.to_string(), None))
+        .collect();
+        let mut used: HashSet<String> = chart.iter().map(|(name, _)| name.clone()).collect();
+
+        let mut account_names = HashMap::new();
+        for account in accounts {
+            let root = match account.account_type {
+                AccountType::Checking => "Assets:Checking",
+                AccountType::Savings => "Assets:Savings",
+                AccountType::Cash => "Assets:Cash",
+                AccountType::CreditCard => "Liabilities:CreditCard",
+            };
+            let name = unique_name(&mut used, format!("{}:{}", root, component(&account.name)), account.id);
+            chart.push((name.clone(), Some(account.currency.clone())));
+            account_names.insert(account.id, (name, account.currency.clone()));
+        }
+
+        let by_id: HashMap<i32, &Category> = categories.iter().map(|category| (category.id, category)).collect();
+        let mut category_names = HashMap::new();
+        for category in categories {
+            let mut path = vec![component(&category.name)];
+            let mut parent_id = category.parent_id;
+            while let Some(parent) = parent_id.and_then(|id| by_id.get(&id)) {
+                if path.len() > by_id.len() {
+                    break;
+                }
+                path.push(component(&parent.name));
+                parent_id = parent.parent_id;
+            }
+            path.push(match category.kind {
+                CategoryKind::Income => "Income".to_string(),
+                CategoryKind::Expense => "Expenses".to_string(),
+            });
+            path.reverse();
+
+            let name = unique_name(&mut used, path.join(":"), category.id);
+            chart.push((name.clone(), None));
+            category_names.insert(category.id, name);
+        }
+        chart.sort();
+
+        JournalBook {
+            start,
+            accounts: account_names,
+            categories: category_names,
+            chart,
+            openings,
+        }
+    }
+
+    /// Account directives, then an opening balance entry per account.
+    fn preamble(&self, syntax: JournalSyntax) -> String {
+        let mut out = String::new();
+        let currencies: BTreeSet<&str> = self.accounts.values().map(|(_, currency)| currency.as_str()).collect();
+
+        match syntax {
+            JournalSyntax::Beancount => {
+                let _ = writeln!(out, "option \"title\" \"Finance Tracker\"");
+                for currency in &currencies {
+                    let _ = writeln!(out, "option \"operating_currency\" \"{}\"", currency);
+                }
+                out.push('\n');
+                for (name, currency) in &self.chart {
+                    match currency {
+                        Some(currency) => {
+                            let _ = writeln!(out, "{} open {} {}", self.start, name, currency);
+                        }
+                        None => {
+                            let _ = writeln!(out, "{} open {}", self.start, name);
+                        }
+                    }
+                }
+            }
+            JournalSyntax::Ledger => {
+                for currency in &currencies {
+                    let _ = writeln!(out, "commodity {}", currency);
+                }
+                out.push('\n');
+                for (name, _) in &self.chart {
+                    let _ = writeln!(out, "account {}", name);
+                }
+            }
+        }
+        out.push('\n');
+
+        for (account_id, balance) in &self.openings {
+            let Some((account, currency)) = self.accounts.get(account_id) else {
+                continue;
+            };
+            out.push_str(&entry(
+                syntax,
+                self.start,
+                "Opening balance",
+                &[],
+                &[
+                    (account, balance.clone(), currency),
+                    (OPENING_BALANCES, -balance, currency),
+                ],
+            ));
+        }
+        out
+    }
+
+    /// The entry a transaction is booked as, or `None` for the second leg of a transfer
+    /// whose entry was already written with the first. `pending_legs` holds those.
+    fn transaction_entry(
+        &self,
+        syntax: JournalSyntax,
+        row: JournalTransaction,
+        pending_legs: &mut HashSet<i32>,
+    ) -> Option<String> {
+        let transaction = row.transaction;
+        if pending_legs.remove(&transaction.id) {
+            return None;
+        }
+        let (account, currency) = self.accounts.get(&transaction.account_id)?;
+
+        let category = transaction.category_id.and_then(|id| self.categories.get(&id));
+        let uncategorized = match transaction.kind {
+            TransactionKind::Income => UNCATEGORIZED_INCOME,
+            TransactionKind::Expense => UNCATEGORIZED_EXPENSES,
+            _ => ADJUSTMENTS,
+        };
+        let other = match (transaction.kind, category) {
+            (TransactionKind::Transfer, _) => {
+                match (transaction.counterpart_id, row.counterpart_account_id.and_then(|id| self.accounts.get(&id))) {
+                    (Some(counterpart_id), Some((counterpart, _))) => {
+                        pending_legs.insert(counterpart_id);
+                        counterpart.as_str()
+                    }
+                    _ => TRANSFERS,
+                }
+            }
+            (_, Some(category)) => category.as_str(),
+            (_, None) => uncategorized,
+        };
+        // A split transaction is booked to each of its lines' categories instead
+        let others: Vec<(&str, BigDecimal)> = if transaction.splits.is_empty() {
+            vec![(other, transaction.amount.clone())]
+        } else {
+            transaction
+                .splits
+                .iter()
+                .map(|split| {
+                    let category = self.categories.get(&split.category_id).map_or(uncategorized, String::as_str);
+                    (category, split.amount.clone())
+                })
+                .collect()
+        };
+
+        let mut metadata = vec![("id", transaction.id.to_string())];
+        if let Some(external_id) = transaction.external_id {
+            metadata.push(("external_id", external_id));
+        }
+        let description = transaction.description.filter(|description| !description.trim().is_empty());
+        let narration = description.as_deref().unwrap_or(match transaction.kind {
+            TransactionKind::Income => "Income",
+            TransactionKind::Expense => "Expense",
+            TransactionKind::Transfer => "Transfer",
+            TransactionKind::Adjustment => "Balance adjustment",
+        });
+
+        // Money in is booked to the account first, money out to where it went
+        let own = (account.as_str(), transaction.amount.clone(), currency);
+        let others = others.into_iter().map(|(other, amount)| (other, -amount, currency));
+        let postings: Vec<_> = if transaction.amount < BigDecimal::zero() {
+            others.chain(std::iter::once(own)).collect()
+        } else {
+            std::iter::once(own).chain(others).collect()
+        };
+        Some(entry(syntax, transaction.occurred_on, narration, &metadata, &postings))
+    }
+}
+
+/// Writes the journal: its preamble, then one balanced entry per transaction and one per
+/// transfer, streamed as rows are read.
+pub fn journal_stream<S>(rows: S, book: JournalBook, syntax: JournalSyntax) -> BoxStream<'static, Result<Bytes, AppError>>
+where
+    S: Stream<Item = Result<JournalTransaction, sqlx::Error>> + Send + 'static,
+{
+    let preamble = Bytes::from(book.preamble(syntax));
+    let mut pending_legs = HashSet::new();
+
+    let entries = rows.filter_map(move |row| {
+        future::ready(match row {
+            Ok(row) => book.transaction_entry(syntax, row, &mut pending_legs).map(|entry| Ok(Bytes::from(entry))),
+            Err(e) => Some(Err(AppError::from(e))),
+        })
+    });
+    stream::once(future::ready(Ok(preamble))).chain(entries).boxed()
+}
+
+fn entry(
+    syntax: JournalSyntax,
+    date: NaiveDate,
+    narration: &str,
+    metadata: &[(&str, String)],
+    postings: &[(&str, BigDecimal, &String)],
+) -> String {
+    let mut out = String::new();
+    match syntax {
+        JournalSyntax::Beancount => {
+            let _ = writeln!(out, "{} * \"{}\"", date, beancount_string(narration));
+            for (key, value) in metadata {
+                let _ = writeln!(out, "  {}: \"{}\"", key, beancount_string(value));
+            }
+            for (account, amount, currency) in postings {
+                let _ = writeln!(out, "  {}  {:.2} {}", account, amount, currency);
+            }
+        }
+        JournalSyntax::Ledger => {
+            let _ = writeln!(out, "{} * {}", date, ledger_text(narration));
+            for (key, value) in metadata {
+                let _ = writeln!(out, "    ; {}: {}", key, ledger_text(value));
+            }
+            for (account, amount, currency) in postings {
+                let _ = writeln!(out, "    {}  {:.2} {}", account, amount, currency);
+            }
+        }
+    }
+    out.push('\n');
+    out
+}
+
+/// A name as one component of an account name: words capitalized and joined with dashes,
+/// starting with a capital letter or digit as beancount requires.
+fn component(name: &str) -> String {
+    let words: Vec<String> = name
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(|word| {
+            let mut chars = word.chars();
+            chars.next().map_or_else(String::new, |first| first.to_uppercase().chain(chars).collect())
+        })
+        .collect();
+    let component = words.join("-");
+    match component.chars().next() {
+        Some(first) if first.is_uppercase() || first.is_ascii_digit() => component,
+        Some(_) => format!("X-{}", component),
+        None => "Unnamed".to_string(),
+    }
+}
+
+fn unique_name(used: &mut HashSet<String>, name: String, id: i32) -> String {
+    let name = if used.contains(&name) { format!("{}-{}", name, id) } else { name };
+    used.insert(name.clone());
+    name
+}
+
+fn beancount_string(text: &str) -> String {
+    single_line(text).replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+/// Ledger and hledger read a `;` as the start of a comment.
+fn ledger_text(text: &str) -> String {
+    single_line(text).replace(';', ",")
+}
+
+fn single_line(text: &str) -> String {
+    text.split(['\r', '\n']).filter(|line| !line.is_empty()).collect::<Vec<_>>().join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use actix_web::web;
+    use chrono::Utc;
+    use futures::TryStreamExt;
+    use sqlx::PgPool;
+    use uuid::Uuid;
+
+    use super::*;
+    use crate::modules::accounts::repositories::insert_account;
+    use crate::modules::categories::repositories::insert_category;
+    use crate::modules::transactions::dtos::TransactionListQuery;
+    use crate::modules::transactions::export::TransactionExport;
+    use crate::modules::transactions::models::{
+        ExportFormat, SplitDraft, Transaction, TransactionDraft, TransactionSplit, TransferDraft,
+    };
+    use crate::modules::transactions::repositories::{calculate_user_transaction_summary, insert_transaction, insert_transfer};
+    use crate::modules::transactions::services::export_user_transactions;
+    use crate::modules::users::repositories::insert_user;
+    use crate::utils::test_db::rolled_back_pool;
+
+    const USD: &str = "USD";
+
+    fn amount(value: &str) -> BigDecimal {
+        BigDecimal::from_str(value).unwrap()
+    }
+
+    fn date(day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2025, 3, day).unwrap()
+    }
+
+    fn account(id: i32, name: &str, account_type: AccountType) -> Account {
+        Account {
+            id,
+            user_id: 1,
+            name: name.to_string(),
+            account_type,
+            currency: USD.to_string(),
+            opening_balance: BigDecimal::zero(),
+            archived: false,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    fn category(id: i32, parent_id: Option<i32>, name: &str, kind: CategoryKind) -> Category {
+        Category {
+            id,
+            user_id: 1,
+            parent_id,
+            name: name.to_string(),
+            kind,
+            icon: None,
+            color: None,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    fn row(id: i32, account_id: i32, kind: TransactionKind, value: &str, category_id: Option<i32>) -> JournalTransaction {
+        JournalTransaction {
+            transaction: Transaction {
+                id,
+                user_id: 1,
+                account_id,
+                kind,
+                amount: amount(value),
+                description: None,
+                category_id,
+                occurred_on: date(5),
+                occurred_time: None,
+                counterpart_id: None,
+                external_id: None,
+                tags: Vec::new(),
+                splits: Vec::new(),
+                created_at: Utc::now(),
+                updated_at: Utc::now(),
+            },
+            counterpart_account_id: None,
+        }
+    }
+
+    /// Checking (1) and savings (2) accounts, and Salary (10), Food (20) and Groceries (21)
+    /// nested under Food.
+    fn book() -> JournalBook {
+        JournalBook::new(
+            date(1),
+            &[
+                account(1, "Everyday checking", AccountType::Checking),
+                account(2, "rainy day", AccountType::Savings),
+            ],
+            &[
+                category(10, None, "Salary", CategoryKind::Income),
+                category(20, None, "Food", CategoryKind::Expense),
+                category(21, Some(20), "Groceries", CategoryKind::Expense),
+            ],
+            vec![(1, amount("1000"))],
+        )
+    }
+
+    #[test]
+    fn component_makes_a_valid_account_name_component() {
+        assert_eq!(component("rainy day fund"), "Rainy-Day-Fund");
+        assert_eq!(component("  bills & utilities "), "Bills-Utilities");
+        assert_eq!(component("401k"), "401k");
+        assert_eq!(component("épargne"), "Épargne");
+        assert_eq!(component("日本"), "X-日本");
+        assert_eq!(component("--"), "Unnamed");
+    }
+
+    #[test]
+    fn clashing_names_get_the_id_appended() {
+        let book = JournalBook::new(
+            date(1),
+            &[
+                account(1, "Rainy day", AccountType::Savings),
+                account(2, "rainy-day", AccountType::Savings),
+            ],
+            &[category(3, None, "Opening balances", CategoryKind::Expense)],
+            Vec::new(),
+        );
+
+        assert_eq!(book.accounts[&1].0, "Assets:Savings:Rainy-Day");
+        assert_eq!(book.accounts[&2].0, "Assets:Savings:Rainy-Day-2");
+        assert_eq!(book.categories[&3], "Expenses:Opening-Balances");
+    }
+
+    #[test]
+    fn categories_are_nested_under_their_parents() {
+        let book = book();
+
+        assert_eq!(book.categories[&10], "Income:Salary");
+        assert_eq!(book.categories[&21], "Expenses:Food:Groceries");
+    }
+
+    #[test]
+    fn beancount_strings_are_escaped_onto_one_line() {
+        assert_eq!(beancount_string("Lunch at \"Joe's\""), "Lunch at \\\"Joe's\\\"");
+        assert_eq!(beancount_string("C:\\receipts"), "C:\\\\receipts");
+        assert_eq!(beancount_string("first\r\nsecond\n"), "first second");
+    }
+
+    #[test]
+    fn ledger_text_has_no_comment_markers() {
+        assert_eq!(ledger_text("rent; March\nparking"), "rent, March parking");
+    }
+
+    #[test]
+    fn entry_is_written_in_each_syntax() {
+        let usd = USD.to_string();
+        let metadata = [("id", "7".to_string())];
+        let postings = [
+            ("Expenses:Food", amount("12.5"), &usd),
+            ("Assets:Checking:Everyday", amount("-12.5"), &usd),
+        ];
+
+        assert_eq!(
+            entry(JournalSyntax::Beancount, date(5), "Lunch \"out\"", &metadata, &postings),
+            "2025-03-05 * \"Lunch \\\"out\\\"\"\n  id: \"7\"\n  Expenses:Food  12.50 USD\n  Assets:Checking:Everyday  -12.50 USD\n\n"
+        );
+        assert_eq!(
+            entry(JournalSyntax::Ledger, date(5), "Lunch; out", &metadata, &postings),
+            "2025-03-05 * Lunch, out\n    ; id: 7\n    Expenses:Food  12.50 USD\n    Assets:Checking:Everyday  -12.50 USD\n\n"
+        );
+    }
+
+    #[test]
+    fn expense_is_booked_from_the_account_to_its_category() {
+        let entry = book()
+            .transaction_entry(JournalSyntax::Ledger, row(5, 1, TransactionKind::Expense, "-42.10", Some(21)), &mut HashSet::new())
+            .unwrap();
+
+        assert_eq!(
+            entry,
+            "2025-03-05 * Expense\n    ; id: 5\n    Expenses:Food:Groceries  42.10 USD\n    Assets:Checking:Everyday-Checking  -42.10 USD\n\n"
+        );
+    }
+
+    #[test]
+    fn split_is_booked_to_each_line_category() {
+        let mut split = row(5, 1, TransactionKind::Expense, "-30", None);
+        split.transaction.splits = vec![
+            TransactionSplit { category_id: 21, amount: amount("-20"), memo: None },
+            TransactionSplit { category_id: 99, amount: amount("-10"), memo: None },
+        ];
+        let entry = book().transaction_entry(JournalSyntax::Beancount, split, &mut HashSet::new()).unwrap();
+
+        assert!(entry.contains("\n  Expenses:Food:Groceries  20.00 USD\n"));
+        assert!(entry.contains("\n  Expenses:Uncategorized  10.00 USD\n"));
+        assert!(entry.contains("\n  Assets:Checking:Everyday-Checking  -30.00 USD\n"));
+    }
+
+    #[test]
+    fn transfer_is_booked_with_its_first_leg_only() {
+        let mut outgoing = row(5, 1, TransactionKind::Transfer, "-500", None);
+        outgoing.transaction.counterpart_id = Some(6);
+        outgoing.counterpart_account_id = Some(2);
+        let mut incoming = row(6, 2, TransactionKind::Transfer, "500", None);
+        incoming.transaction.counterpart_id = Some(5);
+        incoming.counterpart_account_id = Some(1);
+
+        let book = book();
+        let mut pending_legs = HashSet::new();
+        let entry = book.transaction_entry(JournalSyntax::Ledger, outgoing, &mut pending_legs).unwrap();
+
+        assert!(entry.contains("\n    Assets:Savings:Rainy-Day  500.00 USD\n"));
+        assert!(entry.contains("\n    Assets:Checking:Everyday-Checking  -500.00 USD\n"));
+        assert!(book.transaction_entry(JournalSyntax::Ledger, incoming, &mut pending_legs).is_none());
+        assert!(pending_legs.is_empty());
+    }
+
+    #[test]
+    fn transfer_leg_without_its_counterpart_is_booked_to_equity() {
+        let entry = book()
+            .transaction_entry(JournalSyntax::Ledger, row(5, 1, TransactionKind::Transfer, "-500", None), &mut HashSet::new())
+            .unwrap();
+
+        assert!(entry.contains("\n    Equity:Transfers  500.00 USD\n"));
+    }
+
+    #[test]
+    fn opening_balances_are_booked_against_equity() {
+        let preamble = book().preamble(JournalSyntax::Beancount);
+
+        assert!(preamble.contains("2025-03-01 open Assets:Checking:Everyday-Checking USD\n"));
+        assert!(preamble.contains("2025-03-01 open Expenses:Food:Groceries\n"));
+        assert!(preamble.ends_with(
+            "2025-03-01 * \"Opening balance\"\n  Assets:Checking:Everyday-Checking  1000.00 USD\n  Equity:Opening-Balances  -1000.00 USD\n\n"
+        ));
+    }
+
+    /// One entry of an exported journal: the transaction ids in its metadata, and its postings.
+    struct ParsedEntry {
+        ids: Vec<i32>,
+        postings: Vec<(String, BigDecimal)>,
+    }
+
+    /// The user created by `seed`, and the ids of the two legs of their transfer.
+    struct Seeded {
+        user_id: i32,
+        transfer_legs: (i32, i32),
+    }
+
+    fn draft(account_id: i32, kind: TransactionKind, value: &str, category_id: Option<i32>, day: u32) -> TransactionDraft {
+        TransactionDraft {
+            account_id,
+            kind,
+            amount: amount(value),
+            category_id,
+            description: format!("{} on day {}", kind, day),
+            occurred_on: Some(date(day)),
+            occurred_time: None,
+            external_id: None,
+            tags: Vec::new(),
+            splits: Vec::new(),
+        }
+    }
+
+    /// A user with a checking account opened at 1000.00 and an empty savings account, a
+    /// Salary category and Groceries nested under Food, and a month of transactions: a
+    /// salary, groceries with a quoted description and a `;`, a shop split between Food and
+    /// Groceries, an uncategorized expense, a negative adjustment and a transfer to savings.
+    async fn seed(pool: &PgPool) -> Seeded {
+        let username = format!("journal_{}", Uuid::new_v4().simple());
+        let email = format!("{}@example.com", username);
+        let user = insert_user(pool, &username, &email, "hash", "USER").await.unwrap();
+        let checking = insert_account(pool, user.id, "Everyday checking", AccountType::Checking, USD, &amount("1000.00"))
+            .await
+            .unwrap();
+        let savings = insert_account(pool, user.id, "Rainy day", AccountType::Savings, USD, &amount("0"))
+            .await
+            .unwrap();
+        let salary = insert_category(pool, user.id, None, "Salary", CategoryKind::Income, None, None).await.unwrap();
+        let food = insert_category(pool, user.id, None, "Food", CategoryKind::Expense, None, None).await.unwrap();
+        let groceries = insert_category(pool, user.id, Some(food.id), "Groceries", CategoryKind::Expense, None, None)
+            .await
+            .unwrap();
+
+        let mut quoted = draft(checking.id, TransactionKind::Expense, "150.25", Some(groceries.id), 3);
+        quoted.description = "Groceries at \"Joe's\"; weekly".to_string();
+        let mut split = draft(checking.id, TransactionKind::Expense, "300.00", None, 10);
+        split.splits = vec![
+            SplitDraft { category_id: groceries.id, amount: amount("200.00"), memo: None },
+            SplitDraft { category_id: food.id, amount: amount("100.00"), memo: Some("Takeaway".to_string()) },
+        ];
+        let drafts = [
+            draft(checking.id, TransactionKind::Income, "2500.00", Some(salary.id), 1),
+            quoted,
+            split,
+            draft(checking.id, TransactionKind::Expense, "120.00", None, 12),
+            draft(checking.id, TransactionKind::Adjustment, "-100.00", None, 20),
+        ];
+        let mut conn = pool.acquire().await.unwrap();
+        for draft in &drafts {
+            insert_transaction(&mut conn, user.id, draft).await.unwrap();
+        }
+        drop(conn);
+
+        let transfer = insert_transfer(
+            pool,
+            user.id,
+            &TransferDraft {
+                from_account_id: checking.id,
+                to_account_id: savings.id,
+                amount: amount("500.00"),
+                description: "Saving up".to_string(),
+                occurred_on: Some(date(15)),
+                occurred_time: None,
+            },
+        )
+        .await
+        .unwrap();
+
+        Seeded {
+            user_id: user.id,
+            transfer_legs: (transfer.outgoing.id, transfer.incoming.id),
+        }
+    }
+
+    /// The user's whole journal, exported as the export endpoint does with no filter.
+    async fn export(pool: &PgPool, user_id: i32, format: ExportFormat) -> String {
+        let query = web::Query::<TransactionListQuery>::from_query("").unwrap();
+        let TransactionExport::Stream(chunks) = export_user_transactions(pool, user_id, &query, format).await.unwrap() else {
+            panic!("journals are streamed");
+        };
+        let chunks: Vec<Bytes> = chunks.try_collect().await.unwrap();
+        chunks.iter().map(|chunk| String::from_utf8(chunk.to_vec()).unwrap()).collect()
+    }
+
+    /// The dated `*` entries of a journal in either syntax, skipping its directives.
+    fn parse_entries(journal: &str) -> Vec<ParsedEntry> {
+        journal
+            .split("\n\n")
+            .filter(|block| block.lines().next().is_some_and(|header| header.contains(" * ")))
+            .map(|block| {
+                let mut entry = ParsedEntry { ids: Vec::new(), postings: Vec::new() };
+                for line in block.lines().skip(1).map(|line| line.trim().trim_start_matches("; ")) {
+                    if let Some(id) = line.strip_prefix("id: ") {
+                        entry.ids.push(id.trim_matches('"').parse().unwrap());
+                    } else if line.starts_with(char::is_uppercase) {
+                        let mut parts = line.rsplitn(3, ' ');
+                        let (_currency, value, account) = (parts.next(), parts.next().unwrap(), parts.next().unwrap());
+                        entry.postings.push((account.trim().to_string(), amount(value)));
+                    }
+                }
+                entry
+            })
+            .collect()
+    }
+
+    fn total<'a>(postings: impl Iterator<Item = &'a (String, BigDecimal)>) -> BigDecimal {
+        postings.map(|(_, value)| value).sum()
+    }
+
+    /// Every entry balances, the transfer is booked once and the Income and Expenses
+    /// postings add up to the transaction summary's totals.
+    async fn check_export(format: ExportFormat) -> Option<String> {
+        let pool = rolled_back_pool().await?;
+        let seeded = seed(&pool).await;
+        let journal = export(&pool, seeded.user_id, format).await;
+        let entries = parse_entries(&journal);
+
+        // The opening balance, five transactions and one transfer
+        assert_eq!(entries.len(), 7);
+        for entry in &entries {
+            assert!(entry.postings.len() >= 2, "entry {:?} has a single posting", entry.ids);
+            assert!(total(entry.postings.iter()).is_zero(), "entry {:?} does not balance", entry.ids);
+        }
+
+        let (outgoing, incoming) = seeded.transfer_legs;
+        let transfers: Vec<&ParsedEntry> = entries
+            .iter()
+            .filter(|entry| entry.ids.contains(&outgoing) || entry.ids.contains(&incoming))
+            .collect();
+        assert_eq!(transfers.len(), 1, "both legs of the transfer were booked");
+        assert_eq!(transfers[0].postings.len(), 2);
+        assert!(transfers[0].postings.contains(&("Assets:Checking:Everyday-Checking".to_string(), amount("-500.00"))));
+        assert!(transfers[0].postings.contains(&("Assets:Savings:Rainy-Day".to_string(), amount("500.00"))));
+
+        let postings: Vec<&(String, BigDecimal)> = entries.iter().flat_map(|entry| &entry.postings).collect();
+        let income = -total(postings.iter().copied().filter(|(account, _)| account.starts_with("Income:")));
+        let expense = total(postings.iter().copied().filter(|(account, _)| account.starts_with("Expenses:")));
+        let (total_income, total_expense, _) = calculate_user_transaction_summary(&pool, seeded.user_id, None, None)
+            .await
+            .unwrap();
+        assert_eq!(income, total_income);
+        assert_eq!(expense, total_expense);
+        assert_eq!(expense, amount("570.25"));
+        assert!(postings.contains(&&("Expenses:Food:Groceries".to_string(), amount("200.00"))));
+        assert!(postings.contains(&&("Expenses:Food".to_string(), amount("100.00"))));
+
+        Some(journal)
+    }
+
+    #[actix_web::test]
+    async fn ledger_export_balances_and_matches_the_summary() {
+        let Some(journal) = check_export(ExportFormat::Ledger).await else {
+            return;
+        };
+
+        assert!(journal.starts_with("commodity USD\n\naccount Assets:Checking:Everyday-Checking\n"));
+        assert!(journal.contains(" * Groceries at \"Joe's\", weekly\n"));
+    }
+
+    #[actix_web::test]
+    async fn beancount_export_balances_and_matches_the_summary() {
+        let Some(journal) = check_export(ExportFormat::Beancount).await else {
+            return;
+        };
+
+        assert!(journal.starts_with("option \"title\" \"Finance Tracker\"\noption \"operating_currency\" \"USD\"\n\n"));
+        assert!(journal.contains(" open Assets:Savings:Rainy-Day USD\n"));
+        assert!(journal.contains(" * \"Groceries at \\\"Joe's\\\"; weekly\"\n"));
+    }
+}
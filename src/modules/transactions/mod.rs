@@ -1,4 +1,6 @@
 pub mod dtos;
+pub mod export;
+pub mod journal;
 pub mod models;
 pub mod repositories;
 pub mod routes;
@@ -1,30 +1,288 @@
+use bigdecimal::BigDecimal;
 use serde::{Serialize, Deserialize};
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, NaiveDate, NaiveDateTime, NaiveTime, Utc};
 use std::fmt;
+use std::str::FromStr;
 
-#[derive(Serialize, Deserialize, sqlx::FromRow)]
+use crate::utils::money::{deserialize_amount, serialize_amount};
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, sqlx::Type)]
+#[serde(rename_all = "UPPERCASE")]
+#[sqlx(type_name = "VARCHAR", rename_all = "UPPERCASE")]
+pub enum TransactionKind {
+    Income,
+    Expense,
+    Transfer,
+    Adjustment,
+}
+
+impl TransactionKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TransactionKind::Income => "INCOME",
+            TransactionKind::Expense => "EXPENSE",
+            TransactionKind::Transfer => "TRANSFER",
+            TransactionKind::Adjustment => "ADJUSTMENT",
+        }
+    }
+
+    /// Amounts come in through the API as positive values; this turns one into the
+    /// signed value stored in the database. Expenses and transfers move money out.
+    /// Adjustments keep the sign they were given, so they can lower a balance as well as
+    /// raise it.
+    pub fn signed_amount(&self, amount: &BigDecimal) -> BigDecimal {
+        match self {
+            TransactionKind::Income => amount.abs(),
+            TransactionKind::Expense | TransactionKind::Transfer => -amount.abs(),
+            TransactionKind::Adjustment => amount.clone(),
+        }
+    }
+}
+
+impl fmt::Display for TransactionKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TransactionSortField {
+    #[default]
+    OccurredOn,
+    Amount,
+    CreatedAt,
+}
+
+impl TransactionSortField {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TransactionSortField::OccurredOn => "occurred_on",
+            TransactionSortField::Amount => "amount",
+            TransactionSortField::CreatedAt => "created_at",
+        }
+    }
+
+    /// SQL expression rows are ordered by. Transactions without a time sort as
+    /// midnight so they follow timed ones on the same day when newest come first.
+    pub fn sql_expr(&self) -> &'static str {
+        match self {
+            TransactionSortField::OccurredOn => "(occurred_on + COALESCE(occurred_time, TIME '00:00'))",
+            TransactionSortField::Amount => "amount",
+            TransactionSortField::CreatedAt => "created_at",
+        }
+    }
+
+    /// Postgres type a cursor value is cast to before comparing against `sql_expr`.
+    pub fn sql_type(&self) -> &'static str {
+        match self {
+            TransactionSortField::OccurredOn => "timestamp",
+            TransactionSortField::Amount => "numeric",
+            TransactionSortField::CreatedAt => "timestamptz",
+        }
+    }
+
+    pub fn cursor_value(&self, transaction: &Transaction) -> String {
+        match self {
+            TransactionSortField::OccurredOn => transaction
+                .occurred_on
+                .and_time(transaction.occurred_time.unwrap_or(NaiveTime::MIN))
+                .format("%Y-%m-%dT%H:%M:%S%.f")
+                .to_string(),
+            TransactionSortField::Amount => transaction.amount.to_string(),
+            TransactionSortField::CreatedAt => transaction.created_at.to_rfc3339(),
+        }
+    }
+
+    pub fn is_valid_cursor_value(&self, value: &str) -> bool {
+        match self {
+            TransactionSortField::OccurredOn => NaiveDateTime::parse_from_str(value, "%Y-%m-%dT%H:%M:%S%.f").is_ok(),
+            TransactionSortField::Amount => BigDecimal::from_str(value).is_ok(),
+            TransactionSortField::CreatedAt => DateTime::parse_from_rfc3339(value).is_ok(),
+        }
+    }
+}
+
+/// File formats transactions can be exported in.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    Csv,
+    Jsonl,
+    Xlsx,
+    Beancount,
+    Ledger,
+}
+
+impl ExportFormat {
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "text/csv; charset=utf-8",
+            ExportFormat::Jsonl => "application/x-ndjson",
+            ExportFormat::Xlsx => "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet",
+            ExportFormat::Beancount | ExportFormat::Ledger => "text/plain; charset=utf-8",
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "csv",
+            ExportFormat::Jsonl => "jsonl",
+            ExportFormat::Xlsx => "xlsx",
+            ExportFormat::Beancount => "beancount",
+            ExportFormat::Ledger => "ledger",
+        }
+    }
+}
+
+/// Whether a listing filtered by several tags wants transactions with any of them or all.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TagMatch {
+    #[default]
+    Any,
+    All,
+}
+
+/// Narrows a transaction listing. Amount bounds apply to the positive API amount.
+#[derive(Default)]
+pub struct TransactionFilter {
+    pub account_id: Option<i32>,
+    pub from: Option<NaiveDate>,
+    pub to: Option<NaiveDate>,
+    pub category_id: Option<i32>,
+    pub kind: Option<TransactionKind>,
+    pub min_amount: Option<BigDecimal>,
+    pub max_amount: Option<BigDecimal>,
+    pub search: Option<String>,
+    /// Normalized tag names; no tag filter when empty.
+    pub tags: Vec<String>,
+    pub tag_match: TagMatch,
+}
+
+/// The user-supplied fields of a transaction, as written by inserts and updates.
+/// `amount` is the positive API amount; the repository signs it by `kind`.
+#[derive(Clone)]
+pub struct TransactionDraft {
+    pub account_id: i32,
+    pub kind: TransactionKind,
+    pub amount: BigDecimal,
+    pub category_id: Option<i32>,
+    pub description: String,
+    pub occurred_on: Option<NaiveDate>,
+    pub occurred_time: Option<NaiveTime>,
+    /// Only written on insert; updates keep the stored value.
+    pub external_id: Option<String>,
+    /// Normalized tag names; they replace the transaction's tags.
+    pub tags: Vec<String>,
+    /// Lines the transaction is split into; they replace its splits.
+    pub splits: Vec<SplitDraft>,
+}
+
+/// One line of a split transaction as written. `amount` is the positive API amount.
+#[derive(Clone)]
+pub struct SplitDraft {
+    pub category_id: i32,
+    pub amount: BigDecimal,
+    pub memo: Option<String>,
+}
+
+/// One line of a split transaction, with its own category. `amount` is signed like the
+/// transaction's.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TransactionSplit {
+    pub category_id: i32,
+    #[serde(serialize_with = "serialize_amount", deserialize_with = "deserialize_amount")]
+    pub amount: BigDecimal,
+    pub memo: Option<String>,
+}
+
+impl From<&TransactionSplit> for SplitDraft {
+    fn from(split: &TransactionSplit) -> Self {
+        SplitDraft {
+            category_id: split.category_id,
+            amount: split.amount.abs(),
+            memo: split.memo.clone(),
+        }
+    }
+}
+
+/// A movement of money between two of a user's accounts. `amount` is positive; it is
+/// stored as a negative TRANSFER leg on the source and a positive one on the destination.
+pub struct TransferDraft {
+    pub from_account_id: i32,
+    pub to_account_id: i32,
+    pub amount: BigDecimal,
+    pub description: String,
+    pub occurred_on: Option<NaiveDate>,
+    pub occurred_time: Option<NaiveTime>,
+}
+
+#[derive(Serialize)]
+pub struct Transfer {
+    pub outgoing: Transaction,
+    pub incoming: Transaction,
+}
+
+#[derive(Clone, Serialize, Deserialize, sqlx::FromRow)]
 pub struct Transaction {
     pub id: i32,
     pub user_id: i32,
-    pub amount: f64,
+    pub account_id: i32,
+    pub kind: TransactionKind,
+    #[serde(serialize_with = "serialize_amount")]
+    pub amount: BigDecimal,
     pub description: Option<String>, // Now optional
-    pub category: Option<String>, // Now optional
+    pub category_id: Option<i32>,
+    pub occurred_on: NaiveDate,
+    pub occurred_time: Option<NaiveTime>,
+    /// The other leg when this transaction is one side of a transfer.
+    pub counterpart_id: Option<i32>,
+    /// The bank's identifier for a transaction imported from a statement.
+    pub external_id: Option<String>,
+    /// Names of the transaction's tags, sorted.
+    pub tags: Vec<String>,
+    /// Lines the transaction is split into; a split transaction has no `category_id`.
+    #[sqlx(json)]
+    pub splits: Vec<TransactionSplit>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
 
+/// A transaction just recorded, with the transactions it may duplicate and the rule that
+/// categorized it, if any.
+#[derive(Serialize)]
+pub struct RecordedTransaction {
+    #[serde(flatten)]
+    pub transaction: Transaction,
+    pub suspected_duplicate_of: Vec<i32>,
+    pub applied_rule_id: Option<i32>,
+}
+
+/// A transaction as written to a plain-text accounting journal, which books both legs of
+/// a transfer in one entry.
+#[derive(sqlx::FromRow)]
+pub struct JournalTransaction {
+    #[sqlx(flatten)]
+    pub transaction: Transaction,
+    pub counterpart_account_id: Option<i32>,
+}
+
 impl fmt::Display for Transaction {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(
             f,
-            "Transaction {{ id: {}, user_id: {}, amount: {:.2}, category: {}, description: {}, created_at: {}, updated_at: {} }}",
+            "Transaction {{ id: {}, user_id: {}, account_id: {}, kind: {}, amount: {:.2}, category_id: {}, description: {}, occurred_on: {}, created_at: {}, updated_at: {} }}",
             self.id,
             self.user_id,
+            self.account_id,
+            self.kind,
             self.amount,
-            self.category.as_deref().unwrap_or("None"),
+            self.category_id.map_or_else(|| "None".to_string(), |id| id.to_string()),
             self.description.as_deref().unwrap_or("None"),
+            self.occurred_on,
             self.created_at.format("%Y-%m-%d %H:%M:%S"),
             self.updated_at.format("%Y-%m-%d %H:%M:%S")
         )
     }
-}
\ No newline at end of file
+}
@@ -1,99 +1,592 @@
-use sqlx::PgPool;
+use sqlx::postgres::PgRow;
+use sqlx::types::Json;
+use sqlx::{PgConnection, PgExecutor, PgPool, Postgres, QueryBuilder};
 use bigdecimal::BigDecimal;
-use std::str::FromStr;
+use chrono::NaiveDate;
+use futures::channel::mpsc;
+use futures::{SinkExt, StreamExt};
 
-use crate::modules::transactions::models::Transaction;
+use crate::modules::transactions::models::{
+    JournalTransaction, SplitDraft, TagMatch, Transaction, TransactionDraft, TransactionKind, TransactionFilter, TransactionSortField,
+    TransactionSplit, Transfer, TransferDraft,
+};
+use crate::modules::tags::repositories::set_transaction_tags;
+use crate::utils::pagination::{Cursor, SortDirection};
 
+/// Every column of a `Transaction`, with its tag names gathered into `tags` and its
+/// split lines into a JSON array in `splits`.
+const TRANSACTION_COLUMNS: &str =
+    "id, user_id, account_id, kind, amount, description, category_id, occurred_on, occurred_time, counterpart_id, external_id, \
+     ARRAY(SELECT g.name FROM transaction_tags tt JOIN tags g ON g.id = tt.tag_id WHERE tt.transaction_id = transactions.id ORDER BY g.name) AS tags, \
+     COALESCE((SELECT json_agg(json_build_object('category_id', s.category_id, 'amount', s.amount::text, 'memo', s.memo) ORDER BY s.id) FROM transaction_splits s WHERE s.transaction_id = transactions.id), '[]') AS splits, \
+     created_at, updated_at";
+
+/// Inserts a transaction with its tags and splits. Run it in a database transaction so
+/// that they are written together.
 pub async fn insert_transaction(
-    pool: &PgPool,
+    conn: &mut PgConnection,
     user_id: i32,
-    amount: f64,
-    category: &str,
-    description: &str,
+    draft: &TransactionDraft,
 ) -> Result<Transaction, sqlx::Error> {
-    if amount.is_nan() || amount.is_infinite() {
-        return Err(sqlx::Error::Decode("Invalid f64: NaN or infinite".into()));
-    }
-    let amount_str = format!("{:.2}", amount); // Format to 2 decimal places
-    let amount_bd = BigDecimal::from_str(&amount_str).map_err(|e| {
-        sqlx::Error::Decode(format!("Failed to convert f64 to BigDecimal: {}", e).into())
-    })?;
-
     let record = sqlx::query!(
-        "INSERT INTO transactions (user_id, amount, description, category, updated_at) VALUES ($1, $2, $3, $4, NOW()) RETURNING id, user_id, amount::float8 as amount, description, category, created_at, updated_at",
+        "INSERT INTO transactions (user_id, account_id, kind, amount, description, category_id, occurred_on, occurred_time, external_id, updated_at) VALUES ($1, $2, $3, $4, $5, $6, COALESCE($7, CURRENT_DATE), $8, $9, NOW()) RETURNING id, user_id, account_id, kind as \"kind: TransactionKind\", amount, description, category_id, occurred_on, occurred_time, counterpart_id, external_id, created_at, updated_at",
         user_id,
-        amount_bd,
-        description,
-        category
+        draft.account_id,
+        draft.kind.as_str(),
+        draft.kind.signed_amount(&draft.amount),
+        draft.description,
+        draft.category_id,
+        draft.occurred_on,
+        draft.occurred_time,
+        draft.external_id
     )
-        .fetch_one(pool)
+        .fetch_one(&mut *conn)
         .await?;
+    let tags = set_transaction_tags(&mut *conn, user_id, record.id, &draft.tags).await?;
+    let splits = set_transaction_splits(&mut *conn, record.id, &record.amount, &draft.splits).await?;
 
     Ok(Transaction {
         id: record.id,
         user_id: record.user_id,
-        amount: record.amount.unwrap_or_default(),
+        account_id: record.account_id,
+        kind: record.kind,
+        amount: record.amount,
         description: record.description,
-        category: Some(record.category),
+        category_id: record.category_id,
+        occurred_on: record.occurred_on,
+        occurred_time: record.occurred_time,
+        counterpart_id: record.counterpart_id,
+        external_id: record.external_id,
+        tags,
+        splits,
         created_at: record.created_at.expect("created_at is NOT NULL"),
-        updated_at: record.updated_at.expect("updated_at is NOT NULL"),
+        updated_at: record.updated_at,
     })
 }
 
-pub async fn find_transactions_by_user_id(pool: &PgPool, user_id: i32) -> Result<Vec<Transaction>, sqlx::Error> {
-    let records = sqlx::query!(
-        "SELECT id, user_id, amount::float8 as amount, description, category, created_at, updated_at FROM transactions WHERE user_id = $1",
+fn push_transaction_filters(builder: &mut QueryBuilder<'_, Postgres>, user_id: i32, filter: &TransactionFilter) {
+    builder.push(" WHERE user_id = ").push_bind(user_id);
+
+    if let Some(account_id) = filter.account_id {
+        builder.push(" AND account_id = ").push_bind(account_id);
+    }
+    if let Some(from) = filter.from {
+        builder.push(" AND occurred_on >= ").push_bind(from);
+    }
+    if let Some(to) = filter.to {
+        builder.push(" AND occurred_on <= ").push_bind(to);
+    }
+    if let Some(category_id) = filter.category_id {
+        // A parent category also matches the transactions filed under its subcategories,
+        // and a split transaction matches the categories of its lines
+        builder
+            .push(" AND transactions.id IN (SELECT transaction_id FROM transaction_lines WHERE category_id IN (SELECT id FROM categories WHERE id = ")
+            .push_bind(category_id)
+            .push(" OR parent_id = ")
+            .push_bind(category_id)
+            .push("))");
+    }
+    if let Some(kind) = filter.kind {
+        builder.push(" AND kind = ").push_bind(kind.as_str());
+    }
+    if let Some(min_amount) = &filter.min_amount {
+        builder.push(" AND ABS(amount) >= ").push_bind(min_amount.clone());
+    }
+    if let Some(max_amount) = &filter.max_amount {
+        builder.push(" AND ABS(amount) <= ").push_bind(max_amount.clone());
+    }
+    if let Some(search) = &filter.search {
+        builder
+            .push(" AND description ILIKE ")
+            .push_bind(format!("%{}%", escape_like(search)));
+    }
+    if !filter.tags.is_empty() {
+        let tagged = "SELECT tt.transaction_id FROM transaction_tags tt JOIN tags g ON g.id = tt.tag_id WHERE g.name = ANY(";
+        match filter.tag_match {
+            TagMatch::Any => {
+                builder.push(format!(" AND transactions.id IN ({}", tagged)).push_bind(filter.tags.clone()).push("))");
+            }
+            TagMatch::All => {
+                builder
+                    .push(format!(" AND transactions.id IN ({}", tagged))
+                    .push_bind(filter.tags.clone())
+                    .push(") GROUP BY tt.transaction_id HAVING COUNT(*) = ")
+                    .push_bind(filter.tags.len() as i64)
+                    .push(")");
+            }
+        }
+    }
+}
+
+fn escape_like(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_")
+}
+
+pub async fn find_transactions_page(
+    pool: &PgPool,
+    user_id: i32,
+    filter: &TransactionFilter,
+    sort: TransactionSortField,
+    direction: SortDirection,
+    after: Option<&Cursor>,
+    limit: i64,
+) -> Result<Vec<Transaction>, sqlx::Error> {
+    let mut builder = QueryBuilder::<Postgres>::new(format!("SELECT {} FROM transactions", TRANSACTION_COLUMNS));
+    push_transaction_filters(&mut builder, user_id, filter);
+
+    if let Some(cursor) = after {
+        builder
+            .push(format!(" AND ({}, id) {} (", sort.sql_expr(), direction.after_operator()))
+            .push_bind(cursor.value.clone())
+            .push(format!("::{}, ", sort.sql_type()))
+            .push_bind(cursor.id)
+            .push(")");
+    }
+
+    builder
+        .push(format!(" ORDER BY {} {}, id {} LIMIT ", sort.sql_expr(), direction.as_sql(), direction.as_sql()))
+        .push_bind(limit);
+
+    builder.build_query_as::<Transaction>().fetch_all(pool).await
+}
+
+/// Rows handed over from a streaming query before it waits for the consumer to catch up.
+const STREAM_BUFFER_ROWS: usize = 256;
+
+/// Streams every transaction matching `filter`, in the same order as the paged listing.
+/// The query runs on a task of its own and rows are read from the connection as the
+/// receiver takes them, so only a few are held in memory however many match. The query
+/// stops once the receiver is dropped.
+pub fn stream_transactions(
+    pool: PgPool,
+    user_id: i32,
+    filter: TransactionFilter,
+    sort: TransactionSortField,
+    direction: SortDirection,
+) -> mpsc::Receiver<Result<Transaction, sqlx::Error>> {
+    let select = format!("SELECT {} FROM transactions", TRANSACTION_COLUMNS);
+    stream_rows(pool, select, user_id, filter, sort, direction)
+}
+
+/// Like `stream_transactions`, with each transfer leg's counterpart account.
+pub fn stream_journal_transactions(
+    pool: PgPool,
+    user_id: i32,
+    filter: TransactionFilter,
+    sort: TransactionSortField,
+    direction: SortDirection,
+) -> mpsc::Receiver<Result<JournalTransaction, sqlx::Error>> {
+    let select = format!(
+        "SELECT {}, (SELECT c.account_id FROM transactions c WHERE c.id = transactions.counterpart_id) AS counterpart_account_id FROM transactions",
+        TRANSACTION_COLUMNS
+    );
+    stream_rows(pool, select, user_id, filter, sort, direction)
+}
+
+fn stream_rows<T>(
+    pool: PgPool,
+    select: String,
+    user_id: i32,
+    filter: TransactionFilter,
+    sort: TransactionSortField,
+    direction: SortDirection,
+) -> mpsc::Receiver<Result<T, sqlx::Error>>
+where
+    T: for<'r> sqlx::FromRow<'r, PgRow> + Send + Unpin + 'static,
+{
+    let (mut sender, receiver) = mpsc::channel(STREAM_BUFFER_ROWS);
+
+    actix_web::rt::spawn(async move {
+        let mut builder = QueryBuilder::<Postgres>::new(select);
+        push_transaction_filters(&mut builder, user_id, &filter);
+        builder.push(format!(" ORDER BY {} {}, id {}", sort.sql_expr(), direction.as_sql(), direction.as_sql()));
+
+        let mut rows = builder.build_query_as::<T>().fetch(&pool);
+        while let Some(row) = rows.next().await {
+            let failed = row.is_err();
+            if sender.send(row).await.is_err() || failed {
+                break;
+            }
+        }
+    });
+
+    receiver
+}
+
+pub async fn find_first_transaction_date(pool: &PgPool, user_id: i32) -> Result<Option<NaiveDate>, sqlx::Error> {
+    let result = sqlx::query!(
+        "SELECT MIN(occurred_on) as first_date FROM transactions WHERE user_id = $1",
         user_id
     )
-        .fetch_all(pool)
+        .fetch_one(pool)
         .await?;
 
-    Ok(records.into_iter().map(|r| Transaction {
-        id: r.id,
-        user_id: r.user_id,
-        amount: r.amount.unwrap_or_default(),
-        description: r.description,
-        category: Some(r.category),
-        created_at: r.created_at.expect("created_at is NOT NULL"),
-        updated_at: r.updated_at.expect("updated_at is NOT NULL"),
-    }).collect())
+    Ok(result.first_date)
 }
 
-pub async fn calculate_user_transaction_summary(pool: &PgPool, user_id: i32) -> Result<(f64, f64), sqlx::Error> {
+pub async fn count_transactions(pool: &PgPool, user_id: i32, filter: &TransactionFilter) -> Result<i64, sqlx::Error> {
+    let mut builder = QueryBuilder::<Postgres>::new("SELECT COUNT(*) FROM transactions");
+    push_transaction_filters(&mut builder, user_id, filter);
+
+    builder.build_query_scalar::<i64>().fetch_one(pool).await
+}
+
+pub async fn calculate_user_transaction_summary(
+    pool: &PgPool,
+    user_id: i32,
+    from: Option<NaiveDate>,
+    to: Option<NaiveDate>,
+) -> Result<(BigDecimal, BigDecimal, BigDecimal), sqlx::Error> {
     let result = sqlx::query!(
         r#"
         SELECT
-            COALESCE(SUM(CASE WHEN amount > 0 THEN (amount::float8) ELSE 0 END), 0) as total_income,
-            COALESCE(SUM(CASE WHEN amount < 0 THEN (amount::float8) ELSE 0 END), 0) as total_expense
+            COALESCE(SUM(CASE WHEN kind = 'INCOME' THEN amount ELSE 0 END), 0) as total_income,
+            COALESCE(SUM(CASE WHEN kind = 'EXPENSE' THEN -amount ELSE 0 END), 0) as total_expense,
+            COALESCE(SUM(amount), 0) as balance
         FROM transactions
         WHERE user_id = $1
+            AND ($2::date IS NULL OR occurred_on >= $2)
+            AND ($3::date IS NULL OR occurred_on <= $3)
         "#,
-        user_id
+        user_id,
+        from,
+        to
     )
         .fetch_one(pool)
         .await?;
     
     Ok((
-        result.total_income.unwrap_or(0.0),
-        result.total_expense.unwrap_or(0.0)
+        result.total_income.unwrap_or_default(),
+        result.total_expense.unwrap_or_default(),
+        result.balance.unwrap_or_default()
     ))
 }
 
-pub async fn find_transaction_by_id(pool: &PgPool, id: i32) -> Result<Option<Transaction>, sqlx::Error> {
+pub async fn calculate_user_monthly_summary(
+    pool: &PgPool,
+    user_id: i32,
+    from: Option<NaiveDate>,
+    to: Option<NaiveDate>,
+) -> Result<Vec<(NaiveDate, BigDecimal, BigDecimal, BigDecimal)>, sqlx::Error> {
+    let records = sqlx::query!(
+        r#"
+        SELECT
+            date_trunc('month', occurred_on)::date as "month!",
+            COALESCE(SUM(CASE WHEN kind = 'INCOME' THEN amount ELSE 0 END), 0) as "total_income!",
+            COALESCE(SUM(CASE WHEN kind = 'EXPENSE' THEN -amount ELSE 0 END), 0) as "total_expense!",
+            COALESCE(SUM(amount), 0) as "balance!"
+        FROM transactions
+        WHERE user_id = $1
+            AND ($2::date IS NULL OR occurred_on >= $2)
+            AND ($3::date IS NULL OR occurred_on <= $3)
+        GROUP BY 1
+        ORDER BY 1
+        "#,
+        user_id,
+        from,
+        to
+    )
+        .fetch_all(pool)
+        .await?;
+
+    Ok(records.into_iter().map(|r| (r.month, r.total_income, r.total_expense, r.balance)).collect())
+}
+
+/// Totals per tag as `(tag_id, name, transactions, income, expense, balance)`, for every
+/// tag of the user's including those on no transaction in the range.
+pub async fn calculate_user_tag_summary(
+    pool: &PgPool,
+    user_id: i32,
+    from: Option<NaiveDate>,
+    to: Option<NaiveDate>,
+) -> Result<Vec<(i32, String, i64, BigDecimal, BigDecimal, BigDecimal)>, sqlx::Error> {
+    let records = sqlx::query!(
+        r#"
+        SELECT
+            g.id,
+            g.name,
+            COUNT(t.id) as "transactions!",
+            COALESCE(SUM(CASE WHEN t.kind = 'INCOME' THEN t.amount ELSE 0 END), 0) as "total_income!",
+            COALESCE(SUM(CASE WHEN t.kind = 'EXPENSE' THEN -t.amount ELSE 0 END), 0) as "total_expense!",
+            COALESCE(SUM(t.amount), 0) as "balance!"
+        FROM tags g
+        LEFT JOIN transaction_tags tt ON tt.tag_id = g.id
+        LEFT JOIN transactions t ON t.id = tt.transaction_id
+            AND ($2::date IS NULL OR t.occurred_on >= $2)
+            AND ($3::date IS NULL OR t.occurred_on <= $3)
+        WHERE g.user_id = $1
+        GROUP BY g.id, g.name
+        ORDER BY g.name
+        "#,
+        user_id,
+        from,
+        to
+    )
+        .fetch_all(pool)
+        .await?;
+
+    Ok(records
+        .into_iter()
+        .map(|r| (r.id, r.name, r.transactions, r.total_income, r.total_expense, r.balance))
+        .collect())
+}
+
+/// Totals per category as `(category_id, name, transactions, income, expense, balance)`
+/// over the range, counting each split line in its own category; uncategorized
+/// transactions have no id or name. Transfers are left out.
+pub async fn calculate_user_category_summary(
+    pool: &PgPool,
+    user_id: i32,
+    from: Option<NaiveDate>,
+    to: Option<NaiveDate>,
+) -> Result<Vec<(Option<i32>, Option<String>, i64, BigDecimal, BigDecimal, BigDecimal)>, sqlx::Error> {
+    let records = sqlx::query!(
+        r#"
+        SELECT
+            l.category_id,
+            c.name as "name?",
+            COUNT(DISTINCT l.transaction_id) as "transactions!",
+            COALESCE(SUM(CASE WHEN l.kind = 'INCOME' THEN l.amount ELSE 0 END), 0) as "total_income!",
+            COALESCE(SUM(CASE WHEN l.kind = 'EXPENSE' THEN -l.amount ELSE 0 END), 0) as "total_expense!",
+            COALESCE(SUM(l.amount), 0) as "balance!"
+        FROM transaction_lines l
+        LEFT JOIN categories c ON c.id = l.category_id
+        WHERE l.user_id = $1
+            AND l.kind <> 'TRANSFER'
+            AND ($2::date IS NULL OR l.occurred_on >= $2)
+            AND ($3::date IS NULL OR l.occurred_on <= $3)
+        GROUP BY l.category_id, c.name
+        ORDER BY c.name NULLS LAST
+        "#,
+        user_id,
+        from,
+        to
+    )
+        .fetch_all(pool)
+        .await?;
+
+    Ok(records
+        .into_iter()
+        .map(|r| (r.category_id, r.name, r.transactions, r.total_income, r.total_expense, r.balance))
+        .collect())
+}
+
+pub async fn find_transaction_by_id(executor: impl PgExecutor<'_>, id: i32) -> Result<Option<Transaction>, sqlx::Error> {
     let record = sqlx::query!(
-        "SELECT id, user_id, amount::float8 as amount, description, category, created_at, updated_at FROM transactions WHERE id = $1",
+        "SELECT id, user_id, account_id, kind as \"kind: TransactionKind\", amount, description, category_id, occurred_on, occurred_time, counterpart_id, external_id, ARRAY(SELECT g.name FROM transaction_tags tt JOIN tags g ON g.id = tt.tag_id WHERE tt.transaction_id = transactions.id ORDER BY g.name) as \"tags!\", COALESCE((SELECT json_agg(json_build_object('category_id', s.category_id, 'amount', s.amount::text, 'memo', s.memo) ORDER BY s.id) FROM transaction_splits s WHERE s.transaction_id = transactions.id), '[]') as \"splits!: Json<Vec<TransactionSplit>>\", created_at, updated_at FROM transactions WHERE id = $1",
         id
     )
-        .fetch_optional(pool)
+        .fetch_optional(executor)
         .await?;
 
     Ok(record.map(|r| Transaction {
         id: r.id,
         user_id: r.user_id,
-        amount: r.amount.unwrap_or_default(),
+        account_id: r.account_id,
+        kind: r.kind,
+        amount: r.amount,
         description: r.description,
-        category: Some(r.category),
+        category_id: r.category_id,
+        occurred_on: r.occurred_on,
+        occurred_time: r.occurred_time,
+        counterpart_id: r.counterpart_id,
+        external_id: r.external_id,
+        tags: r.tags,
+        splits: r.splits.0,
         created_at: r.created_at.expect("created_at is NOT NULL"),
-        updated_at: r.updated_at.expect("updated_at is NOT NULL"),
+        updated_at: r.updated_at,
     }))
-}
\ No newline at end of file
+}
+
+pub async fn find_transactions_by_ids(pool: &PgPool, ids: &[i32]) -> Result<Vec<Transaction>, sqlx::Error> {
+    sqlx::query_as::<_, Transaction>(&format!("SELECT {} FROM transactions WHERE id = ANY($1) ORDER BY id", TRANSACTION_COLUMNS))
+        .bind(ids)
+        .fetch_all(pool)
+        .await
+}
+
+pub async fn update_transaction(
+    pool: &PgPool,
+    id: i32,
+    draft: &TransactionDraft,
+) -> Result<Option<Transaction>, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+
+    // updated_at is maintained by the update_transactions_updated_at trigger
+    let record = sqlx::query!(
+        "UPDATE transactions SET account_id = $2, kind = $3, amount = $4, category_id = $5, description = $6, occurred_on = COALESCE($7, occurred_on), occurred_time = $8 WHERE id = $1 RETURNING id, user_id, account_id, kind as \"kind: TransactionKind\", amount, description, category_id, occurred_on, occurred_time, counterpart_id, external_id, created_at, updated_at",
+        id,
+        draft.account_id,
+        draft.kind.as_str(),
+        draft.kind.signed_amount(&draft.amount),
+        draft.category_id,
+        draft.description,
+        draft.occurred_on,
+        draft.occurred_time
+    )
+        .fetch_optional(&mut *tx)
+        .await?;
+    let Some(r) = record else {
+        return Ok(None);
+    };
+    let tags = set_transaction_tags(&mut tx, r.user_id, r.id, &draft.tags).await?;
+    let splits = set_transaction_splits(&mut tx, r.id, &r.amount, &draft.splits).await?;
+    tx.commit().await?;
+
+    Ok(Some(Transaction {
+        id: r.id,
+        user_id: r.user_id,
+        account_id: r.account_id,
+        kind: r.kind,
+        amount: r.amount,
+        description: r.description,
+        category_id: r.category_id,
+        occurred_on: r.occurred_on,
+        occurred_time: r.occurred_time,
+        counterpart_id: r.counterpart_id,
+        external_id: r.external_id,
+        tags,
+        splits,
+        created_at: r.created_at.expect("created_at is NOT NULL"),
+        updated_at: r.updated_at,
+    }))
+}
+
+/// Replaces a transaction's split lines, signing their amounts like the transaction's
+/// stored `amount`, and returns them in order. No lines leaves the transaction unsplit.
+pub async fn set_transaction_splits(
+    conn: &mut PgConnection,
+    transaction_id: i32,
+    amount: &BigDecimal,
+    splits: &[SplitDraft],
+) -> Result<Vec<TransactionSplit>, sqlx::Error> {
+    sqlx::query!("DELETE FROM transaction_splits WHERE transaction_id = $1", transaction_id)
+        .execute(&mut *conn)
+        .await?;
+    if splits.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let category_ids: Vec<i32> = splits.iter().map(|split| split.category_id).collect();
+    let negative = *amount < BigDecimal::from(0);
+    let amounts: Vec<BigDecimal> = splits
+        .iter()
+        .map(|split| if negative { -split.amount.abs() } else { split.amount.abs() })
+        .collect();
+    let memos: Vec<Option<String>> = splits.iter().map(|split| split.memo.clone()).collect();
+    sqlx::query_as!(
+        TransactionSplit,
+        r#"
+        INSERT INTO transaction_splits (transaction_id, category_id, amount, memo)
+        SELECT $1, * FROM UNNEST($2::int[], $3::numeric[], $4::varchar[])
+        RETURNING category_id, amount, memo
+        "#,
+        transaction_id,
+        &category_ids,
+        &amounts,
+        &memos as &[Option<String>]
+    )
+        .fetch_all(&mut *conn)
+        .await
+}
+
+pub async fn delete_transaction(pool: &PgPool, id: i32) -> Result<bool, sqlx::Error> {
+    // The counterpart leg of a transfer goes with it through ON DELETE CASCADE
+    let result = sqlx::query!("DELETE FROM transactions WHERE id = $1", id)
+        .execute(pool)
+        .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// Writes both legs of a transfer atomically and links them to each other.
+pub async fn insert_transfer(
+    pool: &PgPool,
+    user_id: i32,
+    draft: &TransferDraft,
+) -> Result<Transfer, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+
+    let outgoing_id = sqlx::query_scalar!(
+        "INSERT INTO transactions (user_id, account_id, kind, amount, description, occurred_on, occurred_time, updated_at) VALUES ($1, $2, $3, $4, $5, COALESCE($6, CURRENT_DATE), $7, NOW()) RETURNING id",
+        user_id,
+        draft.from_account_id,
+        TransactionKind::Transfer.as_str(),
+        -draft.amount.abs(),
+        draft.description,
+        draft.occurred_on,
+        draft.occurred_time
+    )
+        .fetch_one(&mut *tx)
+        .await?;
+
+    let incoming_id = sqlx::query_scalar!(
+        "INSERT INTO transactions (user_id, account_id, kind, amount, description, occurred_on, occurred_time, counterpart_id, updated_at) VALUES ($1, $2, $3, $4, $5, COALESCE($6, CURRENT_DATE), $7, $8, NOW()) RETURNING id",
+        user_id,
+        draft.to_account_id,
+        TransactionKind::Transfer.as_str(),
+        draft.amount.abs(),
+        draft.description,
+        draft.occurred_on,
+        draft.occurred_time,
+        outgoing_id
+    )
+        .fetch_one(&mut *tx)
+        .await?;
+
+    sqlx::query!("UPDATE transactions SET counterpart_id = $2 WHERE id = $1", outgoing_id, incoming_id)
+        .execute(&mut *tx)
+        .await?;
+
+    let outgoing = find_transaction_by_id(&mut *tx, outgoing_id).await?.ok_or(sqlx::Error::RowNotFound)?;
+    let incoming = find_transaction_by_id(&mut *tx, incoming_id).await?.ok_or(sqlx::Error::RowNotFound)?;
+    tx.commit().await?;
+
+    Ok(Transfer { outgoing, incoming })
+}
+
+/// Updates one leg of a transfer and mirrors the change onto its counterpart, which
+/// gets the opposite `amount` and the same description and date. Only the
+/// leg itself moves to `draft.account_id` and gets `draft.tags`. `amount` is the signed
+/// value of this leg.
+pub async fn update_transfer_leg(
+    pool: &PgPool,
+    id: i32,
+    counterpart_id: i32,
+    draft: &TransactionDraft,
+    amount: &BigDecimal,
+) -> Result<Option<Transaction>, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+
+    let user_id = sqlx::query_scalar!(
+        "UPDATE transactions SET account_id = $2, amount = $3, description = $4, occurred_on = COALESCE($5, occurred_on), occurred_time = $6 WHERE id = $1 RETURNING user_id",
+        id,
+        draft.account_id,
+        amount,
+        draft.description,
+        draft.occurred_on,
+        draft.occurred_time
+    )
+        .fetch_optional(&mut *tx)
+        .await?;
+    let Some(user_id) = user_id else {
+        return Ok(None);
+    };
+    set_transaction_tags(&mut tx, user_id, id, &draft.tags).await?;
+
+    sqlx::query!(
+        "UPDATE transactions SET amount = $2, description = $3, occurred_on = COALESCE($4, occurred_on), occurred_time = $5 WHERE id = $1",
+        counterpart_id,
+        -amount,
+        draft.description,
+        draft.occurred_on,
+        draft.occurred_time
+    )
+        .execute(&mut *tx)
+        .await?;
+
+    let transaction = find_transaction_by_id(&mut *tx, id).await?;
+    tx.commit().await?;
+
+    Ok(transaction)
+}
@@ -1,23 +1,75 @@
-use actix_web::{http::StatusCode, web, HttpResponse, Responder, HttpRequest, HttpMessage};
+use bigdecimal::BigDecimal;
+use actix_web::{http::header::ContentDisposition, http::StatusCode, web, HttpResponse, Responder, HttpRequest, HttpMessage};
 use serde::Serialize;
 use validator::Validate;
 use log::{error, warn};
 
-use crate::modules::transactions::dtos::TransactionRequest;
-use crate::modules::transactions::services::{record_user_transaction, list_user_transactions, get_user_financial_summary};
+use crate::modules::attachments::routes::{upload_attachment, list_attachments};
+use crate::modules::transactions::dtos::{
+    TransactionRequest, TransactionPatchRequest, TransactionListQuery, TransferRequest, SummaryQuery, ExportQuery,
+};
+use crate::modules::transactions::export::TransactionExport;
+use crate::modules::transactions::models::{TransactionDraft, TransferDraft};
+use crate::modules::transactions::services::{
+    record_user_transaction, record_user_transfer, list_user_transactions, get_user_financial_summary, get_user_monthly_summary,
+    get_user_tag_summary, get_user_category_summary,
+    get_accessible_transaction, replace_user_transaction, patch_user_transaction, remove_user_transaction,
+    export_user_transactions,
+};
 use crate::utils::jwt::Claims;
+use crate::utils::money::serialize_amount;
 use crate::utils::response::GenericResponse;
 use crate::utils::errors::AppError;
-use crate::middleware::jwt::JwtMiddleware;
+use crate::middleware::jwt::{request_claims, JwtMiddleware};
 use crate::middleware::rbac::RbacMiddleware;
 use crate::utils::roles::Role;
 use crate::AppState;
 
 #[derive(Serialize)]
 pub struct SummaryResponse {
-    pub(crate) total_income: f64,
-    pub(crate) total_expense: f64,
-    pub(crate) balance: f64,
+    #[serde(serialize_with = "serialize_amount")]
+    pub(crate) total_income: BigDecimal,
+    #[serde(serialize_with = "serialize_amount")]
+    pub(crate) total_expense: BigDecimal,
+    #[serde(serialize_with = "serialize_amount")]
+    pub(crate) balance: BigDecimal,
+}
+
+#[derive(Serialize)]
+pub struct MonthlySummaryResponse {
+    pub(crate) month: String,
+    #[serde(serialize_with = "serialize_amount")]
+    pub(crate) total_income: BigDecimal,
+    #[serde(serialize_with = "serialize_amount")]
+    pub(crate) total_expense: BigDecimal,
+    #[serde(serialize_with = "serialize_amount")]
+    pub(crate) balance: BigDecimal,
+}
+
+#[derive(Serialize)]
+pub struct TagSummaryResponse {
+    pub(crate) tag_id: i32,
+    pub(crate) tag: String,
+    pub(crate) transactions: i64,
+    #[serde(serialize_with = "serialize_amount")]
+    pub(crate) total_income: BigDecimal,
+    #[serde(serialize_with = "serialize_amount")]
+    pub(crate) total_expense: BigDecimal,
+    #[serde(serialize_with = "serialize_amount")]
+    pub(crate) balance: BigDecimal,
+}
+
+#[derive(Serialize)]
+pub struct CategorySummaryResponse {
+    pub(crate) category_id: Option<i32>,
+    pub(crate) category: Option<String>,
+    pub(crate) transactions: i64,
+    #[serde(serialize_with = "serialize_amount")]
+    pub(crate) total_income: BigDecimal,
+    #[serde(serialize_with = "serialize_amount")]
+    pub(crate) total_expense: BigDecimal,
+    #[serde(serialize_with = "serialize_amount")]
+    pub(crate) balance: BigDecimal,
 }
 
 pub fn init(cfg: &mut web::ServiceConfig) {
@@ -29,6 +81,16 @@ pub fn init(cfg: &mut web::ServiceConfig) {
                     .route(web::post().to(add_transaction))
                     .route(web::get().to(list_transactions))
             )
+            .service(
+                web::resource("/export")
+                    .wrap(JwtMiddleware)
+                    .route(web::get().to(export_transactions))
+            )
+            .service(
+                web::resource("/transfers")
+                    .wrap(JwtMiddleware)
+                    .route(web::post().to(add_transfer))
+            )
             .service(
                 web::resource("/summary")
                     .wrap(RbacMiddleware {
@@ -36,6 +98,44 @@ pub fn init(cfg: &mut web::ServiceConfig) {
                     })
                     .wrap(JwtMiddleware)
                     .route(web::get().to(summary))
+            )
+            .service(
+                web::resource("/summary/monthly")
+                    .wrap(RbacMiddleware {
+                        allowed_roles: vec![Role::USER, Role::ADMIN]
+                    })
+                    .wrap(JwtMiddleware)
+                    .route(web::get().to(monthly_summary))
+            )
+            .service(
+                web::resource("/summary/tags")
+                    .wrap(RbacMiddleware {
+                        allowed_roles: vec![Role::USER, Role::ADMIN]
+                    })
+                    .wrap(JwtMiddleware)
+                    .route(web::get().to(tag_summary))
+            )
+            .service(
+                web::resource("/summary/categories")
+                    .wrap(RbacMiddleware {
+                        allowed_roles: vec![Role::USER, Role::ADMIN]
+                    })
+                    .wrap(JwtMiddleware)
+                    .route(web::get().to(category_summary))
+            )
+            .service(
+                web::resource("/{id}")
+                    .wrap(JwtMiddleware)
+                    .route(web::get().to(get_transaction))
+                    .route(web::put().to(update_transaction))
+                    .route(web::patch().to(patch_transaction))
+                    .route(web::delete().to(delete_transaction))
+            )
+            .service(
+                web::resource("/{id}/attachments")
+                    .wrap(JwtMiddleware)
+                    .route(web::post().to(upload_attachment))
+                    .route(web::get().to(list_attachments))
             ),
     );
 }
@@ -58,54 +158,95 @@ async fn add_transaction(
         }
     };
 
-    match record_user_transaction(
-        &state.db,
-        claims.user_id,
-        transaction.amount,
-        &transaction.category,
-        &transaction.description,
-    ).await {
-        Ok(transaction) => {
-            log::info!("Transaction recorded successfully for user {}: {}", claims.user_id, transaction);
+    let draft = TransactionDraft::from(transaction.into_inner());
+    match record_user_transaction(&state.db, claims.user_id, &draft).await {
+        Ok(recorded) => {
+            log::info!("Transaction recorded successfully for user {}: {}", claims.user_id, recorded.transaction);
+            let message = if recorded.suspected_duplicate_of.is_empty() {
+                "Transaction recorded successfully".to_string()
+            } else {
+                "Transaction recorded successfully; it may duplicate an existing transaction".to_string()
+            };
             Ok(HttpResponse::Created().json(GenericResponse {
                 status: StatusCode::CREATED.as_u16(),
-                data: Some(transaction),
-                message: "Transaction recorded successfully".to_string(),
+                data: Some(recorded),
+                message,
             }))
         },
         Err(e) => {
             error!("Failed to record transaction for user {}: {:?}", claims.user_id, e);
-            Err(AppError::Database(e))
+            Err(e)
         }
     }
 }
 
-async fn list_transactions(state: web::Data<AppState>, req: HttpRequest) -> impl Responder {
-    let claims = match req.extensions_mut().get::<Claims>() {
-        Some(claims) => claims.clone(),
-        None => {
-            error!("Claims not found in request extensions");
-            return Err(AppError::InternalServerError("Authentication state error".to_string()));
-        }
-    };
+async fn add_transfer(
+    state: web::Data<AppState>,
+    transfer: web::Json<TransferRequest>,
+    req: HttpRequest,
+) -> Result<HttpResponse, AppError> {
+    transfer.validate()?;
+    let claims = request_claims(&req)?;
 
-    match list_user_transactions(&state.db, claims.user_id).await {
-        Ok(transactions) => {
-            log::info!("Retrieved {} transactions for user {}", transactions.len(), claims.user_id);
-            Ok(HttpResponse::Ok().json(GenericResponse {
-                status: StatusCode::OK.as_u16(),
-                data: Some(transactions),
-                message: "Transactions retrieved successfully".to_string(),
-            }))
-        },
-        Err(e) => {
-            error!("Failed to list transactions for user {}: {:?}", claims.user_id, e);
-            Err(AppError::Database(e))
-        }
-    }
+    let draft = TransferDraft::from(transfer.into_inner());
+    let transfer = record_user_transfer(&state.db, claims.user_id, &draft).await?;
+    log::info!(
+        "Transfer recorded for user {}: {} -> {}",
+        claims.user_id, transfer.outgoing, transfer.incoming
+    );
+
+    Ok(HttpResponse::Created().json(GenericResponse {
+        status: StatusCode::CREATED.as_u16(),
+        data: Some(transfer),
+        message: "Transfer recorded successfully".to_string(),
+    }))
+}
+
+async fn list_transactions(
+    state: web::Data<AppState>,
+    query: web::Query<TransactionListQuery>,
+    req: HttpRequest,
+) -> Result<HttpResponse, AppError> {
+    query.validate()?;
+    let claims = request_claims(&req)?;
+
+    let page = list_user_transactions(&state.db, claims.user_id, &query).await?;
+    log::info!("Retrieved {} of {} transactions for user {}", page.items.len(), page.total_count, claims.user_id);
+
+    Ok(HttpResponse::Ok().json(GenericResponse {
+        status: StatusCode::OK.as_u16(),
+        data: Some(page),
+        message: "Transactions retrieved successfully".to_string(),
+    }))
+}
+
+async fn export_transactions(
+    state: web::Data<AppState>,
+    query: web::Query<TransactionListQuery>,
+    export: web::Query<ExportQuery>,
+    req: HttpRequest,
+) -> Result<HttpResponse, AppError> {
+    query.validate()?;
+    let claims = request_claims(&req)?;
+
+    let body = export_user_transactions(&state.db, claims.user_id, &query, export.format).await?;
+    log::info!("Exporting transactions for user {} as {}", claims.user_id, export.format.extension());
+
+    let mut response = HttpResponse::Ok();
+    response
+        .content_type(export.format.content_type())
+        .insert_header(ContentDisposition::attachment(format!("transactions.{}", export.format.extension())));
+    Ok(match body {
+        TransactionExport::Stream(chunks) => response.streaming(chunks),
+        TransactionExport::File(bytes) => response.body(bytes),
+    })
 }
 
-async fn summary(state: web::Data<AppState>, req: HttpRequest) -> impl Responder {
+async fn summary(
+    state: web::Data<AppState>,
+    query: web::Query<SummaryQuery>,
+    req: HttpRequest,
+) -> impl Responder {
     let claims = match req.extensions_mut().get::<Claims>() {
         Some(claims) => claims.clone(),
         None => {
@@ -114,7 +255,7 @@ async fn summary(state: web::Data<AppState>, req: HttpRequest) -> impl Responder
         }
     };
 
-    match get_user_financial_summary(&state.db, claims.user_id).await {
+    match get_user_financial_summary(&state.db, claims.user_id, query.from, query.to).await {
         Ok(summary) => {
             log::info!("Retrieved financial summary for user {}: income={}, expense={}, balance={}", 
                 claims.user_id, summary.total_income, summary.total_expense, summary.balance);
@@ -129,4 +270,121 @@ async fn summary(state: web::Data<AppState>, req: HttpRequest) -> impl Responder
             Err(AppError::Database(e))
         }
     }
+}
+
+async fn monthly_summary(
+    state: web::Data<AppState>,
+    query: web::Query<SummaryQuery>,
+    req: HttpRequest,
+) -> Result<HttpResponse, AppError> {
+    let claims = request_claims(&req)?;
+    let months = get_user_monthly_summary(&state.db, claims.user_id, query.from, query.to).await?;
+
+    Ok(HttpResponse::Ok().json(GenericResponse {
+        status: StatusCode::OK.as_u16(),
+        data: Some(months),
+        message: "Monthly summary retrieved successfully".to_string(),
+    }))
+}
+
+async fn tag_summary(
+    state: web::Data<AppState>,
+    query: web::Query<SummaryQuery>,
+    req: HttpRequest,
+) -> Result<HttpResponse, AppError> {
+    let claims = request_claims(&req)?;
+    let tags = get_user_tag_summary(&state.db, claims.user_id, query.from, query.to).await?;
+
+    Ok(HttpResponse::Ok().json(GenericResponse {
+        status: StatusCode::OK.as_u16(),
+        data: Some(tags),
+        message: "Tag summary retrieved successfully".to_string(),
+    }))
+}
+
+async fn category_summary(
+    state: web::Data<AppState>,
+    query: web::Query<SummaryQuery>,
+    req: HttpRequest,
+) -> Result<HttpResponse, AppError> {
+    let claims = request_claims(&req)?;
+    let categories = get_user_category_summary(&state.db, claims.user_id, query.from, query.to).await?;
+
+    Ok(HttpResponse::Ok().json(GenericResponse {
+        status: StatusCode::OK.as_u16(),
+        data: Some(categories),
+        message: "Category summary retrieved successfully".to_string(),
+    }))
+}
+
+async fn get_transaction(
+    state: web::Data<AppState>,
+    path: web::Path<i32>,
+    req: HttpRequest,
+) -> Result<HttpResponse, AppError> {
+    let claims = request_claims(&req)?;
+    let transaction = get_accessible_transaction(&state.db, &claims, path.into_inner()).await?;
+
+    Ok(HttpResponse::Ok().json(GenericResponse {
+        status: StatusCode::OK.as_u16(),
+        data: Some(transaction),
+        message: "Transaction retrieved successfully".to_string(),
+    }))
+}
+
+async fn update_transaction(
+    state: web::Data<AppState>,
+    path: web::Path<i32>,
+    transaction: web::Json<TransactionRequest>,
+    req: HttpRequest,
+) -> Result<HttpResponse, AppError> {
+    transaction.validate()?;
+    let claims = request_claims(&req)?;
+
+    let draft = TransactionDraft::from(transaction.into_inner());
+    let updated = replace_user_transaction(&state.db, &claims, path.into_inner(), &draft).await?;
+    log::info!("Transaction updated by user {}: {}", claims.user_id, updated);
+
+    Ok(HttpResponse::Ok().json(GenericResponse {
+        status: StatusCode::OK.as_u16(),
+        data: Some(updated),
+        message: "Transaction updated successfully".to_string(),
+    }))
+}
+
+async fn patch_transaction(
+    state: web::Data<AppState>,
+    path: web::Path<i32>,
+    patch: web::Json<TransactionPatchRequest>,
+    req: HttpRequest,
+) -> Result<HttpResponse, AppError> {
+    patch.validate()?;
+    let claims = request_claims(&req)?;
+
+    let updated = patch_user_transaction(&state.db, &claims, path.into_inner(), &patch).await?;
+    log::info!("Transaction patched by user {}: {}", claims.user_id, updated);
+
+    Ok(HttpResponse::Ok().json(GenericResponse {
+        status: StatusCode::OK.as_u16(),
+        data: Some(updated),
+        message: "Transaction updated successfully".to_string(),
+    }))
+}
+
+async fn delete_transaction(
+    state: web::Data<AppState>,
+    path: web::Path<i32>,
+    req: HttpRequest,
+) -> Result<HttpResponse, AppError> {
+    let claims = request_claims(&req)?;
+    let id = path.into_inner();
+
+    remove_user_transaction(&state.db, state.storage.as_ref(), &claims, id).await?;
+    log::info!("Transaction {} deleted by user {}", id, claims.user_id);
+
+    Ok(HttpResponse::Ok().json(GenericResponse {
+        status: StatusCode::OK.as_u16(),
+        data: None::<()>,
+        message: "Transaction deleted successfully".to_string(),
+    }))
 }
\ No newline at end of file
@@ -1,28 +1,580 @@
-use sqlx::PgPool;
+use std::collections::HashMap;
 
-use crate::modules::transactions::models::Transaction;
-use crate::modules::transactions::repositories::{insert_transaction, find_transactions_by_user_id, calculate_user_transaction_summary};
-use crate::modules::transactions::routes::SummaryResponse;
+use bigdecimal::BigDecimal;
+use chrono::{NaiveDate, Utc};
+use sqlx::{PgConnection, PgPool};
+use log::warn;
 
+use crate::modules::accounts::models::Account;
+use crate::modules::attachments::repositories::find_attachment_keys_by_transaction_ids;
+use crate::modules::attachments::services::discard_stored_files;
+use crate::modules::attachments::storage::AttachmentStorage;
+use crate::modules::accounts::repositories::{
+    find_account_by_id, find_accounts_with_balance_by_user_id, calculate_account_balance_before,
+};
+use crate::modules::accounts::services::ensure_account_accepts_transactions;
+use crate::modules::categories::repositories::find_categories_by_user_id;
+use crate::modules::categories::services::ensure_category_accepts_transactions;
+use crate::modules::duplicates::services::flag_duplicates;
+use crate::modules::tags::models::normalize_tags;
+use crate::modules::rules::services::apply_rules_to_draft;
+use crate::modules::transactions::dtos::{TransactionPatchRequest, TransactionListQuery};
+use crate::modules::transactions::export::{csv_stream, jsonl_stream, xlsx_file, ExportNames, TransactionExport};
+use crate::modules::transactions::journal::{journal_stream, JournalBook, JournalSyntax};
+use crate::modules::transactions::models::{
+    ExportFormat, RecordedTransaction, SplitDraft, Transaction, TransactionDraft, TransactionKind, TransactionSortField, Transfer,
+    TransferDraft,
+};
+use crate::modules::transactions::repositories::{
+    insert_transaction, find_transactions_page, count_transactions, calculate_user_transaction_summary, calculate_user_monthly_summary,
+    find_transaction_by_id, update_transaction, delete_transaction, insert_transfer, update_transfer_leg,
+    stream_transactions, stream_journal_transactions, find_first_transaction_date, calculate_user_tag_summary,
+    calculate_user_category_summary,
+};
+use crate::modules::transactions::routes::{
+    SummaryResponse, MonthlySummaryResponse, TagSummaryResponse, CategorySummaryResponse,
+};
+use crate::utils::errors::AppError;
+use crate::utils::jwt::Claims;
+use crate::utils::pagination::{invalid_cursor, Cursor, Paginated, SortDirection, DEFAULT_PAGE_LIMIT, MAX_PAGE_LIMIT};
+
+/// Records a transaction and flags it if it looks like one recorded before. It is
+/// recorded either way; a failed duplicate check is only logged. The first of the user's
+/// rules that applies to the transaction adds its tags, and its category if none was given.
 pub async fn record_user_transaction(
     pool: &PgPool,
     user_id: i32,
-    amount: f64,
-    category: &str,
-    description: &str,
-) -> Result<Transaction, sqlx::Error> {
-    insert_transaction(pool, user_id, amount, category, description).await
+    draft: &TransactionDraft,
+) -> Result<RecordedTransaction, AppError> {
+    let mut tx = pool.begin().await?;
+    let mut recorded = record_user_transaction_unchecked(&mut tx, pool, user_id, draft).await?;
+    tx.commit().await?;
+    recorded.suspected_duplicate_of = flag_transaction_duplicates(pool, user_id, recorded.transaction.id).await;
+    Ok(recorded)
+}
+
+/// Records a transaction like `record_user_transaction`, but on the caller's connection and
+/// without the duplicate check, for callers that write more in the same database
+/// transaction and have to link the transaction to something before checking it. Checks
+/// are read through `pool`.
+pub async fn record_user_transaction_unchecked(
+    conn: &mut PgConnection,
+    pool: &PgPool,
+    user_id: i32,
+    draft: &TransactionDraft,
+) -> Result<RecordedTransaction, AppError> {
+    if draft.kind == TransactionKind::Transfer {
+        return Err(AppError::invalid_field(
+            "kind",
+            "transfer",
+            "Transfers are recorded between two accounts through /transactions/transfers",
+        ));
+    }
+    ensure_account_accepts_transactions(pool, user_id, draft.account_id, "account_id").await?;
+
+    let mut draft = draft.clone();
+    let applied_rule_id = apply_rules_to_draft(pool, user_id, &mut draft).await?;
+    if let Some(category_id) = draft.category_id {
+        ensure_category_accepts_transactions(pool, user_id, category_id, draft.kind).await?;
+    }
+    ensure_amount_sign(&draft)?;
+    ensure_valid_splits(pool, user_id, &draft).await?;
+    let transaction = insert_transaction(conn, user_id, &draft).await?;
+
+    Ok(RecordedTransaction { transaction, suspected_duplicate_of: Vec::new(), applied_rule_id })
+}
+
+/// Flags a newly recorded transaction if it looks like one recorded before, and returns
+/// the ids of those. A failed check is only logged.
+pub async fn flag_transaction_duplicates(pool: &PgPool, user_id: i32, transaction_id: i32) -> Vec<i32> {
+    match flag_duplicates(pool, user_id, &[transaction_id]).await {
+        Ok(pairs) => pairs.into_iter().map(|(original_id, _)| original_id).collect(),
+        Err(e) => {
+            warn!("Failed to check transaction {} for duplicates: {:?}", transaction_id, e);
+            Vec::new()
+        }
+    }
+}
+
+pub async fn record_user_transfer(
+    pool: &PgPool,
+    user_id: i32,
+    draft: &TransferDraft,
+) -> Result<Transfer, AppError> {
+    let from = ensure_account_accepts_transactions(pool, user_id, draft.from_account_id, "from_account_id").await?;
+    let to = ensure_account_accepts_transactions(pool, user_id, draft.to_account_id, "to_account_id").await?;
+    ensure_transfer_accounts(&from, &to, "to_account_id")?;
+
+    Ok(insert_transfer(pool, user_id, draft).await?)
 }
 
-pub async fn list_user_transactions(pool: &PgPool, user_id: i32) -> Result<Vec<Transaction>, sqlx::Error> {
-    find_transactions_by_user_id(pool, user_id).await
+/// Only an adjustment can lower a balance by a negative amount; every other kind is given
+/// as a positive amount, and its kind says which way the money goes.
+fn ensure_amount_sign(draft: &TransactionDraft) -> Result<(), AppError> {
+    if draft.amount < BigDecimal::from(0) && draft.kind != TransactionKind::Adjustment {
+        return Err(AppError::invalid_field("amount", "sign", "Only adjustments can have a negative amount"));
+    }
+    Ok(())
+}
+
+/// A split transaction is categorized by its lines alone, each of which must fit its kind,
+/// and together they must add up to its amount. Lines are positive, so a negative
+/// adjustment's add up to its size. Transfers are never split.
+async fn ensure_valid_splits(pool: &PgPool, user_id: i32, draft: &TransactionDraft) -> Result<(), AppError> {
+    if draft.splits.is_empty() {
+        return Ok(());
+    }
+    if draft.kind == TransactionKind::Transfer {
+        return Err(AppError::invalid_field("splits", "transfer", "Transfers cannot be split"));
+    }
+    if draft.category_id.is_some() {
+        return Err(AppError::invalid_field(
+            "category_id",
+            "split",
+            "A split transaction is categorized by its splits",
+        ));
+    }
+
+    let total: BigDecimal = draft.splits.iter().map(|split| &split.amount).sum();
+    let expected = draft.amount.abs();
+    if total != expected {
+        return Err(AppError::invalid_field(
+            "splits",
+            "sum",
+            &format!("The splits add up to {:.2} but the transaction amount is {:.2}", total, expected),
+        ));
+    }
+    for split in &draft.splits {
+        ensure_category_accepts_transactions(pool, user_id, split.category_id, draft.kind).await?;
+    }
+    Ok(())
 }
 
-pub async fn get_user_financial_summary(pool: &PgPool, user_id: i32) -> Result<SummaryResponse, sqlx::Error> {
-    let (total_income, total_expense) = calculate_user_transaction_summary(pool, user_id).await?;
+/// Both sides of a transfer must be distinct accounts holding the same currency.
+fn ensure_transfer_accounts(account: &Account, other: &Account, field: &'static str) -> Result<(), AppError> {
+    if account.id == other.id {
+        return Err(AppError::invalid_field(field, "same_account", "A transfer needs two different accounts"));
+    }
+    if account.currency != other.currency {
+        return Err(AppError::invalid_field(
+            field,
+            "currency",
+            "Both accounts of a transfer must use the same currency",
+        ));
+    }
+    Ok(())
+}
+
+pub async fn list_user_transactions(
+    pool: &PgPool,
+    user_id: i32,
+    query: &TransactionListQuery,
+) -> Result<Paginated<Transaction>, AppError> {
+    let cursor = match &query.cursor {
+        Some(raw) => {
+            let cursor = Cursor::decode(raw)?;
+            if cursor.sort != query.sort.as_str() || !query.sort.is_valid_cursor_value(&cursor.value) {
+                return Err(invalid_cursor());
+            }
+            Some(cursor)
+        }
+        None => None,
+    };
+
+    let filter = query.filter();
+    let limit = query.limit.unwrap_or(DEFAULT_PAGE_LIMIT).min(MAX_PAGE_LIMIT);
+
+    // Fetch one extra row to learn whether another page follows
+    let mut items = find_transactions_page(pool, user_id, &filter, query.sort, query.order, cursor.as_ref(), limit + 1).await?;
+    let has_more = items.len() as i64 > limit;
+    items.truncate(limit as usize);
+
+    let next_cursor = match items.last() {
+        Some(last) if has_more => Some(
+            Cursor {
+                sort: query.sort.as_str().to_string(),
+                value: query.sort.cursor_value(last),
+                id: last.id,
+            }
+            .encode(),
+        ),
+        _ => None,
+    };
+    let total_count = count_transactions(pool, user_id, &filter).await?;
+
+    Ok(Paginated {
+        items,
+        next_cursor,
+        total_count,
+        limit,
+    })
+}
+
+/// Exports every transaction matching the list query's filters, in its sort order; the
+/// cursor and limit are ignored. Everything but XLSX is streamed as rows are read.
+pub async fn export_user_transactions(
+    pool: &PgPool,
+    user_id: i32,
+    query: &TransactionListQuery,
+    format: ExportFormat,
+) -> Result<TransactionExport, AppError> {
+    let syntax = match format {
+        ExportFormat::Beancount => JournalSyntax::Beancount,
+        ExportFormat::Ledger => JournalSyntax::Ledger,
+        ExportFormat::Csv | ExportFormat::Jsonl | ExportFormat::Xlsx => {
+            let names = load_export_names(pool, user_id).await?;
+            let rows = stream_transactions(pool.clone(), user_id, query.filter(), query.sort, query.order);
+            return Ok(match format {
+                ExportFormat::Xlsx => TransactionExport::File(xlsx_file(rows, names).await?),
+                ExportFormat::Jsonl => TransactionExport::Stream(jsonl_stream(rows, names)),
+                _ => TransactionExport::Stream(csv_stream(rows, names)),
+            });
+        }
+    };
+
+    // Journals read in date order whatever the listing's sort
+    let book = load_journal_book(pool, user_id, query).await?;
+    let rows = stream_journal_transactions(
+        pool.clone(),
+        user_id,
+        query.filter(),
+        TransactionSortField::OccurredOn,
+        SortDirection::Asc,
+    );
+    Ok(TransactionExport::Stream(journal_stream(rows, book, syntax)))
+}
+
+/// The journal starts on `from` if given, with each account's balance before that day as
+/// its opening balance; otherwise on the user's first transaction (or first account) with
+/// the accounts' own opening balances. Opening balances are booked for the filtered account
+/// only when one is given, and otherwise for all.
+async fn load_journal_book(pool: &PgPool, user_id: i32, query: &TransactionListQuery) -> Result<JournalBook, AppError> {
+    let accounts: Vec<Account> = find_accounts_with_balance_by_user_id(pool, user_id, true)
+        .await?
+        .into_iter()
+        .map(|entry| entry.account)
+        .collect();
+    let categories = find_categories_by_user_id(pool, user_id).await?;
+
+    let start = match query.from {
+        Some(from) => from,
+        None => {
+            let first_account = accounts.iter().map(|account| account.created_at.date_naive()).min();
+            let first_transaction = find_first_transaction_date(pool, user_id).await?;
+            first_transaction
+                .into_iter()
+                .chain(first_account)
+                .min()
+                .unwrap_or_else(|| Utc::now().date_naive())
+        }
+    };
+
+    let mut openings = Vec::new();
+    for account in &accounts {
+        if query.account_id.is_some_and(|account_id| account_id != account.id) {
+            continue;
+        }
+        let balance = match query.from {
+            Some(from) => calculate_account_balance_before(pool, account.id, from).await?,
+            None => account.opening_balance.clone(),
+        };
+        if balance != BigDecimal::from(0) {
+            openings.push((account.id, balance));
+        }
+    }
+
+    Ok(JournalBook::new(start, &accounts, &categories, openings))
+}
+
+async fn load_export_names(pool: &PgPool, user_id: i32) -> Result<ExportNames, AppError> {
+    let accounts = find_accounts_with_balance_by_user_id(pool, user_id, true)
+        .await?
+        .into_iter()
+        .map(|entry| (entry.account.id, (entry.account.name, entry.account.currency)))
+        .collect();
+
+    let categories = find_categories_by_user_id(pool, user_id).await?;
+    let own_names: HashMap<i32, &str> = categories.iter().map(|category| (category.id, category.name.as_str())).collect();
+    let categories = categories
+        .iter()
+        .map(|category| {
+            let name = match category.parent_id.and_then(|parent_id| own_names.get(&parent_id)) {
+                Some(parent) => format!("{} > {}", parent, category.name),
+                None => category.name.clone(),
+            };
+            (category.id, name)
+        })
+        .collect();
+
+    Ok(ExportNames { accounts, categories })
+}
+
+pub async fn get_user_financial_summary(
+    pool: &PgPool,
+    user_id: i32,
+    from: Option<NaiveDate>,
+    to: Option<NaiveDate>,
+) -> Result<SummaryResponse, sqlx::Error> {
+    let (total_income, total_expense, balance) = calculate_user_transaction_summary(pool, user_id, from, to).await?;
     Ok(SummaryResponse {
         total_income,
-        total_expense: total_expense.abs(),
-        balance: total_income + total_expense,
+        total_expense,
+        balance,
     })
-}
\ No newline at end of file
+}
+
+pub async fn get_user_monthly_summary(
+    pool: &PgPool,
+    user_id: i32,
+    from: Option<NaiveDate>,
+    to: Option<NaiveDate>,
+) -> Result<Vec<MonthlySummaryResponse>, sqlx::Error> {
+    let months = calculate_user_monthly_summary(pool, user_id, from, to).await?;
+    Ok(months
+        .into_iter()
+        .map(|(month, total_income, total_expense, balance)| MonthlySummaryResponse {
+            month: month.format("%Y-%m").to_string(),
+            total_income,
+            total_expense,
+            balance,
+        })
+        .collect())
+}
+
+/// Income, expenses and balance per category, with split transactions counted by their
+/// lines.
+pub async fn get_user_category_summary(
+    pool: &PgPool,
+    user_id: i32,
+    from: Option<NaiveDate>,
+    to: Option<NaiveDate>,
+) -> Result<Vec<CategorySummaryResponse>, sqlx::Error> {
+    let categories = calculate_user_category_summary(pool, user_id, from, to).await?;
+    Ok(categories
+        .into_iter()
+        .map(|(category_id, category, transactions, total_income, total_expense, balance)| CategorySummaryResponse {
+            category_id,
+            category,
+            transactions,
+            total_income,
+            total_expense,
+            balance,
+        })
+        .collect())
+}
+
+/// Income, expenses and balance of the transactions carrying each tag. A transaction with
+/// several tags counts towards each of them.
+pub async fn get_user_tag_summary(
+    pool: &PgPool,
+    user_id: i32,
+    from: Option<NaiveDate>,
+    to: Option<NaiveDate>,
+) -> Result<Vec<TagSummaryResponse>, sqlx::Error> {
+    let tags = calculate_user_tag_summary(pool, user_id, from, to).await?;
+    Ok(tags
+        .into_iter()
+        .map(|(tag_id, tag, transactions, total_income, total_expense, balance)| TagSummaryResponse {
+            tag_id,
+            tag,
+            transactions,
+            total_income,
+            total_expense,
+            balance,
+        })
+        .collect())
+}
+
+/// Loads transaction `id` if the caller owns it or is an admin.
+pub async fn get_accessible_transaction(pool: &PgPool, claims: &Claims, id: i32) -> Result<Transaction, AppError> {
+    let transaction = find_transaction_by_id(pool, id)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("Transaction {} not found", id)))?;
+
+    if transaction.user_id != claims.user_id && !claims.is_admin() {
+        warn!("User {} attempted to access transaction {} owned by user {}", claims.user_id, id, transaction.user_id);
+        return Err(AppError::Forbidden("You do not have access to this transaction".to_string()));
+    }
+
+    Ok(transaction)
+}
+
+pub async fn replace_user_transaction(
+    pool: &PgPool,
+    claims: &Claims,
+    id: i32,
+    draft: &TransactionDraft,
+) -> Result<Transaction, AppError> {
+    let existing = get_accessible_transaction(pool, claims, id).await?;
+    save_transaction(pool, &existing, draft, true).await
+}
+
+pub async fn patch_user_transaction(
+    pool: &PgPool,
+    claims: &Claims,
+    id: i32,
+    patch: &TransactionPatchRequest,
+) -> Result<Transaction, AppError> {
+    let existing = get_accessible_transaction(pool, claims, id).await?;
+
+    // New splits replace the category and a new category replaces the splits, unless both
+    // are given
+    let splits = match &patch.splits {
+        Some(splits) => splits.iter().map(SplitDraft::from).collect(),
+        None if patch.category_id.is_some() => Vec::new(),
+        None => existing.splits.iter().map(SplitDraft::from).collect(),
+    };
+    let category_id = match &patch.splits {
+        Some(splits) if !splits.is_empty() => patch.category_id,
+        _ => patch.category_id.or(existing.category_id),
+    };
+    let kind = patch.kind.unwrap_or(existing.kind);
+    let draft = TransactionDraft {
+        account_id: patch.account_id.unwrap_or(existing.account_id),
+        kind,
+        amount: patch.amount.clone().unwrap_or_else(|| match kind {
+            // An adjustment keeps its direction
+            TransactionKind::Adjustment if existing.kind == TransactionKind::Adjustment => existing.amount.clone(),
+            _ => existing.amount.abs(),
+        }),
+        category_id,
+        description: patch.description.clone().or_else(|| existing.description.clone()).unwrap_or_default(),
+        occurred_on: patch.occurred_on.or(Some(existing.occurred_on)),
+        occurred_time: patch.occurred_time.unwrap_or(existing.occurred_time),
+        external_id: existing.external_id.clone(),
+        tags: patch.tags.as_deref().map_or_else(|| existing.tags.clone(), normalize_tags),
+        splits,
+    };
+
+    save_transaction(pool, &existing, &draft, patch.account_id.is_some()).await
+}
+
+/// Writes `draft` over `existing`, checking the target account when `check_account` is set.
+/// A transfer leg is saved together with its counterpart so both sides stay consistent.
+async fn save_transaction(
+    pool: &PgPool,
+    existing: &Transaction,
+    draft: &TransactionDraft,
+    check_account: bool,
+) -> Result<Transaction, AppError> {
+    if (draft.kind == TransactionKind::Transfer) != (existing.kind == TransactionKind::Transfer) {
+        return Err(AppError::invalid_field(
+            "kind",
+            "transfer",
+            "A transaction cannot be turned into or out of a transfer",
+        ));
+    }
+    let account = if check_account {
+        Some(ensure_account_accepts_transactions(pool, existing.user_id, draft.account_id, "account_id").await?)
+    } else {
+        None
+    };
+    if let Some(category_id) = draft.category_id {
+        ensure_category_accepts_transactions(pool, existing.user_id, category_id, draft.kind).await?;
+    }
+    ensure_amount_sign(draft)?;
+    ensure_valid_splits(pool, existing.user_id, draft).await?;
+
+    let updated = match existing.counterpart_id {
+        Some(counterpart_id) => {
+            if let Some(account) = &account {
+                let counterpart_account = match find_transaction_by_id(pool, counterpart_id).await? {
+                    Some(counterpart) => find_account_by_id(pool, counterpart.account_id).await?,
+                    None => None,
+                };
+                if let Some(counterpart_account) = counterpart_account {
+                    ensure_transfer_accounts(account, &counterpart_account, "account_id")?;
+                }
+            }
+
+            // Each leg keeps its direction: the source stays negative, the destination positive
+            let amount = if existing.amount < BigDecimal::from(0) {
+                -draft.amount.abs()
+            } else {
+                draft.amount.abs()
+            };
+            update_transfer_leg(pool, existing.id, counterpart_id, draft, &amount).await?
+        }
+        None => update_transaction(pool, existing.id, draft).await?,
+    };
+
+    updated.ok_or_else(|| AppError::NotFound(format!("Transaction {} not found", existing.id)))
+}
+
+/// Deletes a transaction, or both legs of a transfer, with the files attached to them.
+pub async fn remove_user_transaction(
+    pool: &PgPool,
+    storage: &dyn AttachmentStorage,
+    claims: &Claims,
+    id: i32,
+) -> Result<(), AppError> {
+    let transaction = get_accessible_transaction(pool, claims, id).await?;
+
+    // The other leg of a transfer is deleted with it, attachments and all
+    let transaction_ids: Vec<i32> = std::iter::once(id).chain(transaction.counterpart_id).collect();
+    let attachment_keys = find_attachment_keys_by_transaction_ids(pool, &transaction_ids).await?;
+    if !delete_transaction(pool, id).await? {
+        return Err(AppError::NotFound(format!("Transaction {} not found", id)));
+    }
+    discard_stored_files(storage, &attachment_keys).await;
+    Ok(())
+}
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use uuid::Uuid;
+
+    use super::*;
+    use crate::modules::accounts::models::AccountType;
+    use crate::modules::accounts::repositories::insert_account;
+    use crate::modules::categories::models::CategoryKind;
+    use crate::modules::categories::repositories::insert_category;
+    use crate::modules::users::repositories::insert_user;
+    use crate::utils::test_db::rolled_back_pool;
+
+    fn amount(value: &str) -> BigDecimal {
+        BigDecimal::from_str(value).unwrap()
+    }
+
+    #[actix_web::test]
+    async fn negative_adjustment_is_split_into_negative_lines() {
+        let Some(pool) = rolled_back_pool().await else {
+            return;
+        };
+        let username = format!("splits_{}", Uuid::new_v4().simple());
+        let user = insert_user(&pool, &username, &format!("{}@example.com", username), "hash", "USER").await.unwrap();
+        let account = insert_account(&pool, user.id, "Checking", AccountType::Checking, "USD", &amount("1000.00"))
+            .await
+            .unwrap();
+        let fees = insert_category(&pool, user.id, None, "Fees", CategoryKind::Expense, None, None).await.unwrap();
+        let interest = insert_category(&pool, user.id, None, "Interest", CategoryKind::Income, None, None)
+            .await
+            .unwrap();
+        let mut draft = TransactionDraft {
+            account_id: account.id,
+            kind: TransactionKind::Adjustment,
+            amount: amount("-150.00"),
+            category_id: None,
+            description: "Bank correction".to_string(),
+            occurred_on: None,
+            occurred_time: None,
+            external_id: None,
+            tags: Vec::new(),
+            splits: vec![
+                SplitDraft { category_id: fees.id, amount: amount("120.00"), memo: None },
+                SplitDraft { category_id: interest.id, amount: amount("20.00"), memo: None },
+            ],
+        };
+
+        let result = ensure_valid_splits(&pool, user.id, &draft).await;
+        assert!(matches!(result, Err(AppError::Validation(_))));
+
+        draft.splits[1].amount = amount("30.00");
+        ensure_valid_splits(&pool, user.id, &draft).await.unwrap();
+        let transaction = insert_transaction(&mut pool.acquire().await.unwrap(), user.id, &draft).await.unwrap();
+        assert_eq!(transaction.amount, amount("-150.00"));
+        let lines: Vec<BigDecimal> = transaction.splits.iter().map(|split| split.amount.clone()).collect();
+        assert_eq!(lines, [amount("-120.00"), amount("-30.00")]);
+    }
+}
@@ -1,5 +1,5 @@
 use serde::Deserialize;
-use validator::{Validate, ValidationError};
+use validator::Validate;
 use crate::utils::roles::Role;
 
 #[derive(Deserialize, Validate)]
@@ -10,8 +10,6 @@ pub struct RegisterRequest {
     pub email: String,
     #[validate(length(min = 8, message = "Password must be at least 8 characters"))]
     pub password: String,
-    #[serde(default)]
-    pub role: Option<Role>,
 }
 
 #[derive(Deserialize, Validate)]
@@ -22,13 +20,13 @@ pub struct LoginRequest {
     pub password: String,
 }
 
-// fn validate_role(role: &Option<String>) -> Result<(), ValidationError> {
-//     if let Some(r) = role {
-//         if Role::from_str(&r.to_uppercase()).is_err() {
-//             let mut error = ValidationError::new("role");
-//             error.message = Some("Role must be 'USER' or 'ADMIN'".into());
-//             return Err(error);
-//         }
-//     }
-//     Ok(())
-// }
\ No newline at end of file
+#[derive(Deserialize, Validate)]
+pub struct RefreshRequest {
+    #[validate(length(min = 1, message = "Refresh token cannot be empty"))]
+    pub refresh_token: String,
+}
+
+#[derive(Deserialize)]
+pub struct RoleRequest {
+    pub role: Role,
+}
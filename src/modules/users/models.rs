@@ -2,6 +2,7 @@ use serde::{Deserialize, Serialize};
 use serde::de::{self, Deserializer};
 use std::fmt;
 use std::str::FromStr;
+use crate::modules::mfa::models::MfaChallenge;
 use crate::utils::roles::Role;
 
 #[derive(Serialize, Deserialize, sqlx::FromRow)]
@@ -9,6 +10,7 @@ pub struct User {
     pub id: i32,
     pub username: String,
     pub email: String,
+    #[serde(skip_serializing)]
     pub password_hash: String,
     #[serde(deserialize_with = "deserialize_role")]
     pub role: Role,
@@ -31,6 +33,58 @@ impl fmt::Display for User {
     }
 }
 
+/// What a successful login or refresh returns: a short-lived JWT to authenticate requests
+/// with, and the refresh token to get the next one.
+#[derive(Serialize)]
+pub struct AuthTokens {
+    pub token: String,
+    pub token_type: &'static str,
+    /// Seconds until `token` expires.
+    pub expires_in: i64,
+    pub refresh_token: String,
+    /// Only on the login that enabled two-factor authentication, as the admin enrollment
+    /// at login does.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub recovery_codes: Option<Vec<String>>,
+}
+
+/// What checking a user's password leads to: a session straight away, or a second step
+/// when two-factor authentication is on.
+#[derive(Serialize)]
+#[serde(untagged)]
+pub enum LoginOutcome {
+    Authenticated(AuthTokens),
+    MfaRequired(MfaChallenge),
+}
+
+/// A stored refresh token as looked up on refresh, with the state of its session.
+pub struct RefreshTokenRecord {
+    pub id: i32,
+    pub session_id: i32,
+    pub user_id: i32,
+    pub expires_at: chrono::DateTime<chrono::Utc>,
+    pub used_at: Option<chrono::DateTime<chrono::Utc>>,
+    pub session_revoked_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+/// The device a request came from, as recorded on the session it starts or refreshes.
+pub struct ClientInfo {
+    pub user_agent: Option<String>,
+    pub ip_address: Option<String>,
+}
+
+/// An open session as shown to its user. `current` marks the one the request was made
+/// with.
+#[derive(Serialize)]
+pub struct Session {
+    pub id: i32,
+    pub user_agent: Option<String>,
+    pub ip_address: Option<String>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub last_used_at: chrono::DateTime<chrono::Utc>,
+    pub current: bool,
+}
+
 fn deserialize_role<'de, D>(deserializer: D) -> Result<Role, D::Error>
 where
     D: Deserializer<'de>,
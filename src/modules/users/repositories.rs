@@ -1,8 +1,8 @@
-use crate::utils::roles::Role;
-use sqlx::PgPool;
 use chrono::{DateTime, Utc};
+use sqlx::{PgConnection, PgExecutor, PgPool};
 
-use crate::modules::users::models::User;
+use crate::modules::users::models::{ClientInfo, RefreshTokenRecord, Session, User};
+use crate::utils::roles::Role;
 
 pub async fn check_user_exists(
     pool: &PgPool,
@@ -27,7 +27,7 @@ pub async fn check_user_exists(
 }
 
 pub async fn insert_user(
-    pool: &PgPool,
+    executor: impl PgExecutor<'_>,
     username: &str,
     email: &str,
     password_hash: &str,
@@ -40,7 +40,7 @@ pub async fn insert_user(
         password_hash,
         role
     )
-        .fetch_one(pool)
+        .fetch_one(executor)
         .await?;
 
     Ok(User {
@@ -50,10 +50,99 @@ pub async fn insert_user(
         password_hash: record.password_hash,
         role: record.role,
         created_at: record.created_at.expect("created_at is NOT NULL"),
-        updated_at: record.updated_at.expect("updated_at is NOT NULL"),
+        updated_at: record.updated_at,
     })
 }
 
+pub async fn find_user_by_id(executor: impl PgExecutor<'_>, id: i32) -> Result<Option<User>, sqlx::Error> {
+    let record = sqlx::query!(
+        "SELECT id, username, email, password_hash, role as \"role: Role\", COALESCE(created_at, NOW()) as created_at, updated_at FROM users WHERE id = $1",
+        id
+    )
+        .fetch_optional(executor)
+        .await?;
+
+    Ok(record.map(|r| User {
+        id: r.id,
+        username: r.username,
+        email: r.email,
+        password_hash: r.password_hash,
+        role: r.role,
+        created_at: r.created_at.expect("created_at is NOT NULL"),
+        updated_at: r.updated_at,
+    }))
+}
+
+/// Like `find_user_by_id`, but locks the user until the end of the transaction, so that
+/// their second-factor attempts are checked one at a time.
+pub async fn find_user_by_id_for_update(conn: &mut PgConnection, id: i32) -> Result<Option<User>, sqlx::Error> {
+    let record = sqlx::query!(
+        "SELECT id, username, email, password_hash, role as \"role: Role\", COALESCE(created_at, NOW()) as created_at, updated_at FROM users WHERE id = $1 FOR NO KEY UPDATE",
+        id
+    )
+        .fetch_optional(conn)
+        .await?;
+
+    Ok(record.map(|r| User {
+        id: r.id,
+        username: r.username,
+        email: r.email,
+        password_hash: r.password_hash,
+        role: r.role,
+        created_at: r.created_at.expect("created_at is NOT NULL"),
+        updated_at: r.updated_at,
+    }))
+}
+
+pub async fn update_user_role(executor: impl PgExecutor<'_>, id: i32, role: &Role) -> Result<Option<User>, sqlx::Error> {
+    let record = sqlx::query!(
+        "UPDATE users SET role = $2, updated_at = NOW() WHERE id = $1 RETURNING id, username, email, password_hash, role as \"role: Role\", COALESCE(created_at, NOW()) as created_at, updated_at",
+        id,
+        role.as_str()
+    )
+        .fetch_optional(executor)
+        .await?;
+
+    Ok(record.map(|r| User {
+        id: r.id,
+        username: r.username,
+        email: r.email,
+        password_hash: r.password_hash,
+        role: r.role,
+        created_at: r.created_at.expect("created_at is NOT NULL"),
+        updated_at: r.updated_at,
+    }))
+}
+
+pub async fn count_admins(pool: &PgPool) -> Result<i64, sqlx::Error> {
+    sqlx::query_scalar!(r#"SELECT COUNT(*) as "count!" FROM users WHERE role = 'ADMIN'"#)
+        .fetch_one(pool)
+        .await
+}
+
+/// Counts the admins, locking their rows until the transaction ends so that no other role
+/// change can take one away in the meantime.
+pub async fn count_admins_for_update(conn: &mut PgConnection) -> Result<i64, sqlx::Error> {
+    sqlx::query_scalar!(
+        r#"SELECT COUNT(*) as "count!" FROM (SELECT id FROM users WHERE role = 'ADMIN' ORDER BY id FOR UPDATE) admins"#
+    )
+        .fetch_one(conn)
+        .await
+}
+
+/// Makes the user with `username` an admin, but only while there is no admin at all.
+/// Returns whether the user was promoted.
+pub async fn promote_first_admin(pool: &PgPool, username: &str) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query!(
+        "UPDATE users SET role = 'ADMIN', updated_at = NOW() WHERE username = $1 AND NOT EXISTS (SELECT 1 FROM users WHERE role = 'ADMIN')",
+        username
+    )
+        .execute(pool)
+        .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
 pub async fn find_user_by_username(
     pool: &PgPool,
     username: &str,
@@ -72,6 +161,183 @@ pub async fn find_user_by_username(
         password_hash: r.password_hash,
         role: r.role,
         created_at: r.created_at.expect("created_at is NOT NULL"),
-        updated_at: r.updated_at.expect("updated_at is NOT NULL"),
+        updated_at: r.updated_at,
     }))
-}
\ No newline at end of file
+}
+pub async fn insert_session(
+    executor: impl PgExecutor<'_>,
+    user_id: i32,
+    client: &ClientInfo,
+) -> Result<i32, sqlx::Error> {
+    sqlx::query_scalar!(
+        "INSERT INTO sessions (user_id, user_agent, ip_address) VALUES ($1, $2, $3) RETURNING id",
+        user_id,
+        client.user_agent,
+        client.ip_address
+    )
+        .fetch_one(executor)
+        .await
+}
+
+/// Marks a session as just used, as it is on every refresh, from the client's current
+/// address.
+pub async fn touch_session(executor: impl PgExecutor<'_>, id: i32, client: &ClientInfo) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        "UPDATE sessions SET last_used_at = NOW(), ip_address = COALESCE($2, ip_address) WHERE id = $1",
+        id,
+        client.ip_address
+    )
+        .execute(executor)
+        .await?;
+    Ok(())
+}
+
+/// Lists a user's sessions that can still be refreshed: not revoked, and holding a
+/// refresh token that is neither used nor expired. Most recently used first.
+pub async fn find_open_sessions(
+    pool: &PgPool,
+    user_id: i32,
+    current_session_id: i32,
+) -> Result<Vec<Session>, sqlx::Error> {
+    sqlx::query_as!(
+        Session,
+        r#"
+        SELECT s.id, s.user_agent, s.ip_address, s.created_at, s.last_used_at, s.id = $2 as "current!"
+        FROM sessions s
+        WHERE s.user_id = $1
+          AND s.revoked_at IS NULL
+          AND EXISTS (
+              SELECT 1 FROM refresh_tokens r
+              WHERE r.session_id = s.id AND r.used_at IS NULL AND r.expires_at > NOW()
+          )
+        ORDER BY s.last_used_at DESC, s.id DESC
+        "#,
+        user_id,
+        current_session_id
+    )
+        .fetch_all(pool)
+        .await
+}
+
+/// Ends one of a user's sessions. Returns false if the user has no such session still
+/// open.
+pub async fn revoke_user_session(executor: impl PgExecutor<'_>, user_id: i32, id: i32) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query!(
+        "UPDATE sessions SET revoked_at = NOW() WHERE id = $1 AND user_id = $2 AND revoked_at IS NULL",
+        id,
+        user_id
+    )
+        .execute(executor)
+        .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// Ends a session; none of its refresh tokens can be used any more, and neither can the
+/// access tokens issued for it.
+pub async fn revoke_session(executor: impl PgExecutor<'_>, id: i32) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query!("UPDATE sessions SET revoked_at = NOW() WHERE id = $1 AND revoked_at IS NULL", id)
+        .execute(executor)
+        .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+pub async fn insert_refresh_token(
+    executor: impl PgExecutor<'_>,
+    session_id: i32,
+    token_hash: &str,
+    expires_at: DateTime<Utc>,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        "INSERT INTO refresh_tokens (session_id, token_hash, expires_at) VALUES ($1, $2, $3)",
+        session_id,
+        token_hash,
+        expires_at
+    )
+        .execute(executor)
+        .await?;
+    Ok(())
+}
+
+/// Looks up a refresh token by hash and locks it until the end of the transaction, so
+/// that the same token can't be rotated twice at once.
+pub async fn find_refresh_token_for_update(
+    conn: &mut PgConnection,
+    token_hash: &str,
+) -> Result<Option<RefreshTokenRecord>, sqlx::Error> {
+    sqlx::query_as!(
+        RefreshTokenRecord,
+        r#"
+        SELECT r.id, r.session_id, s.user_id, r.expires_at, r.used_at, s.revoked_at as session_revoked_at
+        FROM refresh_tokens r
+        JOIN sessions s ON s.id = r.session_id
+        WHERE r.token_hash = $1
+        FOR UPDATE OF r
+        "#,
+        token_hash
+    )
+        .fetch_optional(conn)
+        .await
+}
+
+pub async fn mark_refresh_token_used(executor: impl PgExecutor<'_>, id: i32) -> Result<(), sqlx::Error> {
+    sqlx::query!("UPDATE refresh_tokens SET used_at = NOW() WHERE id = $1", id)
+        .execute(executor)
+        .await?;
+    Ok(())
+}
+
+/// Ends every session of a user's still open, and returns how many there were.
+pub async fn revoke_user_sessions(executor: impl PgExecutor<'_>, user_id: i32) -> Result<u64, sqlx::Error> {
+    let result = sqlx::query!("UPDATE sessions SET revoked_at = NOW() WHERE user_id = $1 AND revoked_at IS NULL", user_id)
+        .execute(executor)
+        .await?;
+
+    Ok(result.rows_affected())
+}
+
+pub async fn insert_revoked_token(
+    executor: impl PgExecutor<'_>,
+    jti: &str,
+    user_id: i32,
+    expires_at: DateTime<Utc>,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        "INSERT INTO revoked_tokens (jti, user_id, expires_at) VALUES ($1, $2, $3) ON CONFLICT (jti) DO NOTHING",
+        jti,
+        user_id,
+        expires_at
+    )
+        .execute(executor)
+        .await?;
+    Ok(())
+}
+
+/// Whether an access token can no longer be used: it was revoked itself, or its session
+/// was.
+pub async fn is_token_revoked(pool: &PgPool, jti: &str, session_id: i32) -> Result<bool, sqlx::Error> {
+    sqlx::query_scalar!(
+        r#"
+        SELECT EXISTS (SELECT 1 FROM revoked_tokens WHERE jti = $1)
+            OR EXISTS (SELECT 1 FROM sessions WHERE id = $2 AND revoked_at IS NOT NULL) as "revoked!"
+        "#,
+        jti,
+        session_id
+    )
+        .fetch_one(pool)
+        .await
+}
+
+/// Deletes revocations of access tokens that have expired since, and refresh tokens past
+/// their expiry. Returns how many of each were deleted.
+pub async fn purge_expired_tokens(pool: &PgPool) -> Result<(u64, u64), sqlx::Error> {
+    let revoked = sqlx::query!("DELETE FROM revoked_tokens WHERE expires_at < NOW()")
+        .execute(pool)
+        .await?;
+    let refresh = sqlx::query!("DELETE FROM refresh_tokens WHERE expires_at < NOW()")
+        .execute(pool)
+        .await?;
+
+    Ok((revoked.rows_affected(), refresh.rows_affected()))
+}
@@ -1,14 +1,21 @@
-use actix_web::{http::StatusCode, web, HttpResponse, Responder, HttpRequest, HttpMessage};
-use sqlx::PgPool;
+use actix_web::{http::{header, StatusCode}, web, HttpResponse, Responder, HttpRequest, HttpMessage};
 use validator::Validate;
 
-use crate::modules::users::dtos::{RegisterRequest, LoginRequest};
-use crate::modules::users::services::{register_new_user, login_user};
+use crate::modules::users::dtos::{RegisterRequest, LoginRequest, RefreshRequest, RoleRequest};
+use crate::modules::mfa::dtos::{MfaLoginRequest, MfaEnrollRequest};
+use crate::modules::mfa::services::issue_mfa_enrollment_token;
+use crate::modules::users::services::{
+    register_new_user, login_user, refresh_user_tokens, change_user_role, logout_session, logout_all_sessions,
+    list_user_sessions, end_user_session, complete_mfa_login, start_login_totp_enrollment,
+};
+use crate::modules::users::models::{ClientInfo, LoginOutcome};
 use crate::utils::jwt::Claims;
 use crate::utils::response::GenericResponse;
 use crate::utils::errors::AppError;
-use crate::middleware::jwt::JwtMiddleware;
 use crate::utils::roles::Role;
+use crate::utils::constants::auth::MAX_USER_AGENT_LENGTH;
+use crate::middleware::jwt::{request_claims, JwtMiddleware};
+use crate::middleware::rbac::RbacMiddleware;
 use crate::AppState;
 
 pub fn init(cfg: &mut web::ServiceConfig) {
@@ -22,12 +29,63 @@ pub fn init(cfg: &mut web::ServiceConfig) {
                 web::resource("/login")
                     .route(web::post().to(login))
             )
+            .service(
+                web::resource("/login/mfa")
+                    .route(web::post().to(login_mfa))
+            )
+            .service(
+                web::resource("/login/mfa/enroll")
+                    .route(web::post().to(login_mfa_enroll))
+            )
+            .service(
+                web::resource("/token/refresh")
+                    .route(web::post().to(refresh))
+            )
+            .service(
+                web::resource("/logout")
+                    .wrap(JwtMiddleware)
+                    .route(web::post().to(logout))
+            )
+            .service(
+                web::resource("/logout/all")
+                    .wrap(JwtMiddleware)
+                    .route(web::post().to(logout_all))
+            )
+            .service(
+                web::resource("/sessions")
+                    .wrap(JwtMiddleware)
+                    .route(web::get().to(list_sessions))
+            )
+            .service(
+                web::resource("/sessions/{id}")
+                    .wrap(JwtMiddleware)
+                    .route(web::delete().to(delete_session))
+            )
             .service(
                 web::resource("/profile")
                     .wrap(JwtMiddleware)
                     .route(web::get().to(get_profile))
             ),
     );
+    cfg.service(
+        web::scope("/admin/users")
+            .service(
+                web::resource("/{id}/role")
+                    .wrap(RbacMiddleware {
+                        allowed_roles: vec![Role::ADMIN]
+                    })
+                    .wrap(JwtMiddleware)
+                    .route(web::patch().to(patch_role))
+            )
+            .service(
+                web::resource("/{id}/mfa-enrollment-token")
+                    .wrap(RbacMiddleware {
+                        allowed_roles: vec![Role::ADMIN]
+                    })
+                    .wrap(JwtMiddleware)
+                    .route(web::post().to(post_mfa_enrollment_token))
+            ),
+    );
 }
 
 async fn register(
@@ -41,7 +99,6 @@ async fn register(
         &user_data.username,
         &user_data.email,
         &user_data.password,
-        user_data.role.as_ref(),
     ).await?;
 
     Ok(HttpResponse::Created().json(GenericResponse {
@@ -51,25 +108,173 @@ async fn register(
     }))
 }
 
+async fn logout(state: web::Data<AppState>, req: HttpRequest) -> Result<HttpResponse, AppError> {
+    let claims = request_claims(&req)?;
+    logout_session(&state.db, &claims).await?;
+
+    Ok(HttpResponse::Ok().json(GenericResponse {
+        status: StatusCode::OK.as_u16(),
+        data: None::<()>,
+        message: "Logged out successfully".to_string(),
+    }))
+}
+
+async fn logout_all(state: web::Data<AppState>, req: HttpRequest) -> Result<HttpResponse, AppError> {
+    let claims = request_claims(&req)?;
+    let sessions = logout_all_sessions(&state.db, &claims).await?;
+
+    Ok(HttpResponse::Ok().json(GenericResponse {
+        status: StatusCode::OK.as_u16(),
+        data: Some(serde_json::json!({ "sessions_revoked": sessions })),
+        message: "Logged out of all sessions successfully".to_string(),
+    }))
+}
+
+async fn list_sessions(state: web::Data<AppState>, req: HttpRequest) -> Result<HttpResponse, AppError> {
+    let claims = request_claims(&req)?;
+    let sessions = list_user_sessions(&state.db, &claims).await?;
+
+    Ok(HttpResponse::Ok().json(GenericResponse {
+        status: StatusCode::OK.as_u16(),
+        data: Some(sessions),
+        message: "Sessions retrieved successfully".to_string(),
+    }))
+}
+
+async fn delete_session(
+    state: web::Data<AppState>,
+    path: web::Path<i32>,
+    req: HttpRequest,
+) -> Result<HttpResponse, AppError> {
+    let claims = request_claims(&req)?;
+    end_user_session(&state.db, &claims, path.into_inner()).await?;
+
+    Ok(HttpResponse::Ok().json(GenericResponse {
+        status: StatusCode::OK.as_u16(),
+        data: None::<()>,
+        message: "Session revoked successfully".to_string(),
+    }))
+}
+
+/// The device a request came from. The address is that of the direct peer; forwarding
+/// headers are not trusted, since anyone can set them.
+fn client_info(req: &HttpRequest) -> ClientInfo {
+    let user_agent = req
+        .headers()
+        .get(header::USER_AGENT)
+        .and_then(|value| value.to_str().ok())
+        .filter(|value| !value.is_empty())
+        .map(|value| value.chars().take(MAX_USER_AGENT_LENGTH).collect());
+
+    ClientInfo {
+        user_agent,
+        ip_address: req.peer_addr().map(|addr| addr.ip().to_string()),
+    }
+}
+
+async fn patch_role(
+    state: web::Data<AppState>,
+    path: web::Path<i32>,
+    body: web::Json<RoleRequest>,
+    req: HttpRequest,
+) -> Result<HttpResponse, AppError> {
+    let claims = request_claims(&req)?;
+    let user = change_user_role(&state.db, &claims, path.into_inner(), &body.role).await?;
+
+    Ok(HttpResponse::Ok().json(GenericResponse {
+        status: StatusCode::OK.as_u16(),
+        data: Some(user),
+        message: "User role updated successfully".to_string(),
+    }))
+}
+
+async fn post_mfa_enrollment_token(
+    state: web::Data<AppState>,
+    path: web::Path<i32>,
+    req: HttpRequest,
+) -> Result<HttpResponse, AppError> {
+    let claims = request_claims(&req)?;
+    let token = issue_mfa_enrollment_token(&state.db, &claims, path.into_inner()).await?;
+
+    Ok(HttpResponse::Created().json(GenericResponse {
+        status: StatusCode::CREATED.as_u16(),
+        data: Some(token),
+        message: "Enrollment token issued; it is shown only this once".to_string(),
+    }))
+}
+
 async fn login(
     state: web::Data<AppState>,
     credentials: web::Json<LoginRequest>,
+    req: HttpRequest,
 ) -> Result<HttpResponse, AppError> {
     credentials.validate()?;
 
-    let token = login_user(
+    let outcome = login_user(
         &state.db,
         &credentials.username,
         &credentials.password,
+        &client_info(&req),
     ).await?;
+    let message = match &outcome {
+        LoginOutcome::Authenticated(_) => "Login successful",
+        LoginOutcome::MfaRequired(challenge) if challenge.enrollment_required => {
+            "Two-factor authentication must be set up with an enrollment token from another admin to continue"
+        }
+        LoginOutcome::MfaRequired(_) => "Two-factor authentication code required",
+    };
 
     Ok(HttpResponse::Ok().json(GenericResponse {
         status: StatusCode::OK.as_u16(),
-        data: Some(serde_json::json!({ "token": token })),
+        data: Some(outcome),
+        message: message.to_string(),
+    }))
+}
+
+async fn login_mfa(
+    state: web::Data<AppState>,
+    body: web::Json<MfaLoginRequest>,
+    req: HttpRequest,
+) -> Result<HttpResponse, AppError> {
+    body.validate()?;
+    let tokens = complete_mfa_login(&state.db, &body.mfa_token, &body.code, &client_info(&req)).await?;
+
+    Ok(HttpResponse::Ok().json(GenericResponse {
+        status: StatusCode::OK.as_u16(),
+        data: Some(tokens),
         message: "Login successful".to_string(),
     }))
 }
 
+async fn login_mfa_enroll(
+    state: web::Data<AppState>,
+    body: web::Json<MfaEnrollRequest>,
+) -> Result<HttpResponse, AppError> {
+    body.validate()?;
+    let enrollment = start_login_totp_enrollment(&state.db, &body.mfa_token, &body.enrollment_token).await?;
+
+    Ok(HttpResponse::Ok().json(GenericResponse {
+        status: StatusCode::OK.as_u16(),
+        data: Some(enrollment),
+        message: "Add this secret to your authenticator app, then log in with a code from it".to_string(),
+    }))
+}
+
+async fn refresh(
+    state: web::Data<AppState>,
+    body: web::Json<RefreshRequest>,
+    req: HttpRequest,
+) -> Result<HttpResponse, AppError> {
+    body.validate()?;
+    let tokens = refresh_user_tokens(&state.db, &body.refresh_token, &client_info(&req)).await?;
+
+    Ok(HttpResponse::Ok().json(GenericResponse {
+        status: StatusCode::OK.as_u16(),
+        data: Some(tokens),
+        message: "Token refreshed successfully".to_string(),
+    }))
+}
+
 async fn get_profile(req: HttpRequest) -> impl Responder {
     if let Some(claims) = req.extensions_mut().get::<Claims>() {
         Ok(HttpResponse::Ok().json(GenericResponse {
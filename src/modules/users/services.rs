@@ -1,19 +1,41 @@
-use sqlx::PgPool;
+use chrono::{DateTime, Duration, Utc};
+use uuid::Uuid;
+use sqlx::{PgConnection, PgPool};
 
 use crate::utils::bcrypt::hash_password;
 use crate::utils::jwt::{generate_token, Claims};
 use crate::utils::roles::Role;
-use crate::modules::users::models::User;
-use crate::modules::users::repositories::{insert_user, find_user_by_username, check_user_exists};
+use crate::utils::constants::auth::{
+    ACCESS_TOKEN_EXPIRY_SECONDS, REFRESH_TOKEN_EXPIRY_SECONDS, MAX_MFA_ATTEMPTS, MAX_MFA_FAILURES, MFA_FAILURE_WINDOW_SECONDS,
+};
+use crate::utils::tokens::{generate_opaque_token, hash_token};
+use crate::modules::users::models::{AuthTokens, ClientInfo, LoginOutcome, Session, User};
+use crate::modules::users::repositories::{
+    insert_user, find_user_by_username, check_user_exists, find_user_by_id, find_user_by_id_for_update, update_user_role, count_admins,
+    count_admins_for_update, promote_first_admin, insert_session, touch_session, revoke_session, insert_refresh_token,
+    find_refresh_token_for_update, mark_refresh_token_used, revoke_user_sessions, insert_revoked_token, is_token_revoked,
+    purge_expired_tokens, find_open_sessions, revoke_user_session,
+};
+use crate::modules::categories::services::seed_default_categories;
+use crate::modules::mfa::models::{MfaChallengeRecord, TotpEnrollment};
+use crate::modules::mfa::repositories::{
+    is_totp_enabled, find_mfa_challenge_for_update, record_mfa_attempt, delete_mfa_challenge,
+    purge_expired_mfa_challenges, insert_mfa_failure, count_mfa_failures_since, delete_mfa_failures,
+    purge_mfa_failures_before, use_mfa_enrollment_token, purge_expired_mfa_enrollment_tokens,
+};
+use crate::modules::mfa::services::{
+    create_mfa_challenge, verify_second_factor, confirm_totp, enroll_totp, needs_totp_enrollment,
+};
 use crate::utils::errors::AppError;
 use log::{error, warn, info};
 
+/// Registers a user. Everyone who signs up is a USER; admins are appointed by other
+/// admins through `change_user_role`, and the first one by `bootstrap_admin`.
 pub async fn register_new_user(
     pool: &PgPool,
     username: &str,
     email: &str,
     password: &str,
-    role: Option<&Role>,
 ) -> Result<User, AppError> {
     // Check if username or email already exists
     if let Some(message) = check_user_exists(pool, username, email).await? {
@@ -21,25 +43,76 @@ pub async fn register_new_user(
     }
 
     let password_hash = hash_password(password)?;
-    let role = role.unwrap_or(&Role::USER).as_str().to_string();
-    
-    match insert_user(pool, username, email, &password_hash, &role).await {
-        Ok(user) => {
-            info!("User registered successfully: {}", user);
-            Ok(user)
-        },
+    // The user and their default categories are created together or not at all
+    let mut tx = pool.begin().await?;
+    let user = match insert_user(&mut *tx, username, email, &password_hash, Role::USER.as_str()).await {
+        Ok(user) => user,
         Err(e) => {
             error!("Failed to register user {}: {:?}", username, e);
-            Err(AppError::Database(e))
+            return Err(AppError::Database(e));
         }
+    };
+    seed_default_categories(&mut tx, user.id).await?;
+    tx.commit().await?;
+
+    info!("User registered successfully: {}", user);
+    Ok(user)
+}
+
+/// Gives a user another role. The last admin cannot be made a USER, so that there is
+/// always someone left to manage roles; the admins stay locked until the change is made, so
+/// that two demotions at once cannot both pass the check. An admin who is made a USER is
+/// logged out of every session, since their access tokens still carry the ADMIN role, and
+/// so is a new admin who still has to add an authenticator.
+pub async fn change_user_role(pool: &PgPool, claims: &Claims, id: i32, role: &Role) -> Result<User, AppError> {
+    let mut tx = pool.begin().await?;
+    let admins = count_admins_for_update(&mut tx).await?;
+    let user = find_user_by_id_for_update(&mut tx, id)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("User {} not found", id)))?;
+    if user.role == *role {
+        return Ok(user);
+    }
+    if user.role == Role::ADMIN && admins <= 1 {
+        return Err(AppError::Conflict("The last admin cannot be made a user".to_string()));
+    }
+
+    let demoted = user.role == Role::ADMIN;
+    let user = update_user_role(&mut *tx, id, role)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("User {} not found", id)))?;
+    let must_enroll = needs_totp_enrollment(&mut tx, user.id, &user.role).await?;
+    let revoked = if demoted || must_enroll { revoke_user_sessions(&mut *tx, user.id).await? } else { 0 };
+    tx.commit().await?;
+    info!("User {} changed the role of user {} to {}", claims.user_id, user.id, user.role);
+    if demoted {
+        info!("Ended {} sessions of former admin {}", revoked, user.id);
+    } else if must_enroll {
+        info!("Ended {} sessions of new admin {} until they enable two-factor authentication", revoked, user.id);
+    }
+    Ok(user)
+}
+
+/// Makes the registered user named `username` the first admin. Nothing changes once any
+/// admin exists, so the setting can be left in place after the first start.
+pub async fn bootstrap_admin(pool: &PgPool, username: &str) -> Result<(), AppError> {
+    if promote_first_admin(pool, username).await? {
+        info!("User {} promoted to first admin", username);
+    } else if count_admins(pool).await? == 0 {
+        warn!("Cannot bootstrap admin {}: no such user has registered", username);
     }
+    Ok(())
 }
 
+/// Checks a user's password and starts a session for them on the client's device. Users
+/// with two-factor authentication, and admins who must have it, only get an "mfa pending"
+/// token to finish with `complete_mfa_login`.
 pub async fn login_user(
     pool: &PgPool,
     username: &str,
     password: &str,
-) -> Result<String, AppError> {
+    client: &ClientInfo,
+) -> Result<LoginOutcome, AppError> {
     let user = find_user_by_username(pool, username)
         .await
         .map_err(|e| {
@@ -63,21 +136,384 @@ pub async fn login_user(
         return Err(AppError::InvalidCredentials("Invalid password".to_string()));
     }
 
+    let enrollment_required = needs_totp_enrollment(&mut *pool.acquire().await?, user.id, &user.role).await?;
+    if enrollment_required || is_totp_enabled(pool, user.id).await? {
+        let challenge = create_mfa_challenge(pool, user.id, enrollment_required).await?;
+        info!("Password accepted for user {}; waiting for the second factor", username);
+        return Ok(LoginOutcome::MfaRequired(challenge));
+    }
+
+    let mut tx = pool.begin().await?;
+    let tokens = start_session(&mut tx, user, client).await?;
+    tx.commit().await?;
+    Ok(LoginOutcome::Authenticated(tokens))
+}
+
+/// Finishes a login that is waiting for its second factor: a code from the user's
+/// authenticator or one of their recovery codes. For an admin who has to enroll, the code
+/// confirms the authenticator set up with `start_login_totp_enrollment`, and the response
+/// carries their recovery codes. After `MAX_MFA_ATTEMPTS` wrong codes the user has to log
+/// in again, and after `MAX_MFA_FAILURES` across their logins within
+/// `MFA_FAILURE_WINDOW_SECONDS` no code is accepted until the oldest of them ages out.
+pub async fn complete_mfa_login(
+    pool: &PgPool,
+    mfa_token: &str,
+    code: &str,
+    client: &ClientInfo,
+) -> Result<AuthTokens, AppError> {
+    let mut tx = pool.begin().await?;
+    let challenge = find_mfa_challenge_for_update(&mut tx, &hash_token(mfa_token)).await?;
+    let challenge = open_mfa_challenge(challenge)?;
+    let user = find_user_by_id_for_update(&mut tx, challenge.user_id)
+        .await?
+        .ok_or_else(|| AppError::Unauthorized("Invalid MFA token".to_string()))?;
+    let window_start = Utc::now() - Duration::seconds(MFA_FAILURE_WINDOW_SECONDS);
+    if count_mfa_failures_since(&mut *tx, user.id, window_start).await? >= MAX_MFA_FAILURES {
+        warn!("Second factor for user {} refused after too many wrong codes", user.username);
+        return Err(AppError::TooManyRequests(
+            "Too many wrong codes; please wait a few minutes before trying again".to_string(),
+        ));
+    }
+
+    let (accepted, recovery_codes) = if is_totp_enabled(&mut *tx, user.id).await? {
+        (verify_second_factor(&mut tx, user.id, code).await?, None)
+    } else {
+        let recovery_codes = confirm_totp(&mut tx, user.id, code).await?;
+        (recovery_codes.is_some(), recovery_codes.map(|codes| codes.recovery_codes))
+    };
+    if !accepted {
+        insert_mfa_failure(&mut *tx, user.id).await?;
+        let attempts = record_mfa_attempt(&mut *tx, challenge.id).await?;
+        if attempts >= MAX_MFA_ATTEMPTS {
+            delete_mfa_challenge(&mut *tx, challenge.id).await?;
+            tx.commit().await?;
+            warn!("Too many wrong codes for user {}; login abandoned", user.username);
+            return Err(AppError::InvalidCredentials("Too many wrong codes; please log in again".to_string()));
+        }
+        tx.commit().await?;
+        warn!("Wrong code for user {}", user.username);
+        return Err(AppError::InvalidCredentials("Invalid code".to_string()));
+    }
+
+    delete_mfa_challenge(&mut *tx, challenge.id).await?;
+    delete_mfa_failures(&mut *tx, user.id).await?;
+    let mut tokens = start_session(&mut tx, &user, client).await?;
+    tx.commit().await?;
+
+    if recovery_codes.is_some() {
+        info!("User {} enabled two-factor authentication at login", user.username);
+    }
+    tokens.recovery_codes = recovery_codes;
+    Ok(tokens)
+}
+
+/// Sets up an authenticator for an admin whose login is waiting for them to enroll. Their
+/// password is not enough: they need the enrollment token another admin issued them, which
+/// is used up.
+pub async fn start_login_totp_enrollment(
+    pool: &PgPool,
+    mfa_token: &str,
+    enrollment_token: &str,
+) -> Result<TotpEnrollment, AppError> {
+    let mut tx = pool.begin().await?;
+    let challenge = find_mfa_challenge_for_update(&mut tx, &hash_token(mfa_token)).await?;
+    let challenge = open_mfa_challenge(challenge)?;
+    let user = find_user_by_id(&mut *tx, challenge.user_id)
+        .await?
+        .ok_or_else(|| AppError::Unauthorized("Invalid MFA token".to_string()))?;
+    if !use_mfa_enrollment_token(&mut *tx, user.id, &hash_token(enrollment_token)).await? {
+        warn!("Invalid enrollment token for user {}", user.username);
+        return Err(AppError::Unauthorized(
+            "Invalid or expired enrollment token; ask another admin for a new one".to_string(),
+        ));
+    }
+    let enrollment = enroll_totp(&mut *tx, user.id, &user.username).await?;
+    tx.commit().await?;
+
+    Ok(enrollment)
+}
+
+fn open_mfa_challenge(challenge: Option<MfaChallengeRecord>) -> Result<MfaChallengeRecord, AppError> {
+    let challenge = challenge.ok_or_else(|| AppError::Unauthorized("Invalid MFA token".to_string()))?;
+    if challenge.expires_at <= Utc::now() {
+        return Err(AppError::Unauthorized("MFA token expired; please log in again".to_string()));
+    }
+    Ok(challenge)
+}
+
+async fn start_session(conn: &mut PgConnection, user: &User, client: &ClientInfo) -> Result<AuthTokens, AppError> {
+    let session_id = insert_session(&mut *conn, user.id, client).await?;
+    let refresh_token = issue_refresh_token(conn, session_id).await?;
+    let token = generate_access_token(user, session_id)?;
+
+    info!("Started session {} for user: {}", session_id, user.username);
+    Ok(AuthTokens {
+        token,
+        token_type: "Bearer",
+        expires_in: ACCESS_TOKEN_EXPIRY_SECONDS,
+        refresh_token,
+        recovery_codes: None,
+    })
+}
+
+/// Trades a refresh token for a new access token and the next refresh token of its
+/// session. Each refresh token works once: one that was already rotated is taken as
+/// stolen, and its whole session is revoked.
+pub async fn refresh_user_tokens(
+    pool: &PgPool,
+    refresh_token: &str,
+    client: &ClientInfo,
+) -> Result<AuthTokens, AppError> {
+    let mut tx = pool.begin().await?;
+    let Some(stored) = find_refresh_token_for_update(&mut tx, &hash_token(refresh_token)).await? else {
+        return Err(AppError::Unauthorized("Invalid refresh token".to_string()));
+    };
+    if stored.session_revoked_at.is_some() {
+        return Err(AppError::Unauthorized("This session has ended; please log in again".to_string()));
+    }
+    if stored.used_at.is_some() {
+        revoke_session(&mut *tx, stored.session_id).await?;
+        tx.commit().await?;
+        warn!(
+            "Refresh token reused for session {} of user {}; session revoked",
+            stored.session_id, stored.user_id
+        );
+        return Err(AppError::Unauthorized(
+            "This refresh token was already used; the session has been revoked".to_string(),
+        ));
+    }
+    if stored.expires_at <= Utc::now() {
+        return Err(AppError::Unauthorized("Refresh token expired; please log in again".to_string()));
+    }
+
+    let user = find_user_by_id(&mut *tx, stored.user_id)
+        .await?
+        .ok_or_else(|| AppError::Unauthorized("Invalid refresh token".to_string()))?;
+    mark_refresh_token_used(&mut *tx, stored.id).await?;
+    touch_session(&mut *tx, stored.session_id, client).await?;
+    let refresh_token = issue_refresh_token(&mut tx, stored.session_id).await?;
+    let token = generate_access_token(&user, stored.session_id)?;
+    tx.commit().await?;
+
+    Ok(AuthTokens {
+        token,
+        token_type: "Bearer",
+        expires_in: ACCESS_TOKEN_EXPIRY_SECONDS,
+        refresh_token,
+        recovery_codes: None,
+    })
+}
+
+async fn issue_refresh_token(conn: &mut PgConnection, session_id: i32) -> Result<String, AppError> {
+    let token = generate_opaque_token();
+    let expires_at = Utc::now() + Duration::seconds(REFRESH_TOKEN_EXPIRY_SECONDS);
+    insert_refresh_token(conn, session_id, &hash_token(&token), expires_at).await?;
+    Ok(token)
+}
+
+/// Ends the session the caller's token belongs to, and the token itself at once.
+pub async fn logout_session(pool: &PgPool, claims: &Claims) -> Result<(), AppError> {
+    let mut tx = pool.begin().await?;
+    revoke_access_token(&mut tx, claims).await?;
+    revoke_session(&mut *tx, claims.sid).await?;
+    tx.commit().await?;
+
+    info!("User {} logged out of session {}", claims.user_id, claims.sid);
+    Ok(())
+}
+
+/// Ends every session of the caller's, the current one included. Returns how many were
+/// still open.
+pub async fn logout_all_sessions(pool: &PgPool, claims: &Claims) -> Result<u64, AppError> {
+    let mut tx = pool.begin().await?;
+    revoke_access_token(&mut tx, claims).await?;
+    let sessions = revoke_user_sessions(&mut *tx, claims.user_id).await?;
+    tx.commit().await?;
+
+    info!("User {} logged out of all {} sessions", claims.user_id, sessions);
+    Ok(sessions)
+}
+
+/// The caller's open sessions, the current one marked.
+pub async fn list_user_sessions(pool: &PgPool, claims: &Claims) -> Result<Vec<Session>, AppError> {
+    Ok(find_open_sessions(pool, claims.user_id, claims.sid).await?)
+}
+
+/// Ends one of the caller's sessions, on whichever device it is. Its refresh tokens stop
+/// working at once, and so do the access tokens issued for it.
+pub async fn end_user_session(pool: &PgPool, claims: &Claims, id: i32) -> Result<(), AppError> {
+    let mut tx = pool.begin().await?;
+    if !revoke_user_session(&mut *tx, claims.user_id, id).await? {
+        return Err(AppError::NotFound(format!("Session {} not found", id)));
+    }
+    if id == claims.sid {
+        revoke_access_token(&mut tx, claims).await?;
+    }
+    tx.commit().await?;
+
+    info!("User {} ended session {}", claims.user_id, id);
+    Ok(())
+}
+
+/// Whether the access token with these claims has been revoked, by itself or with its
+/// session.
+pub async fn is_access_token_revoked(pool: &PgPool, claims: &Claims) -> Result<bool, AppError> {
+    Ok(is_token_revoked(pool, &claims.jti, claims.sid).await?)
+}
+
+/// Purges expired token revocations, refresh tokens, pending logins and enrollment tokens,
+/// and wrong second-factor codes that no longer count, now and then every `period` on the
+/// server's runtime.
+pub fn spawn_token_purger(pool: PgPool, period: std::time::Duration) {
+    actix_web::rt::spawn(async move {
+        let mut ticker = actix_web::rt::time::interval(period);
+        loop {
+            ticker.tick().await;
+            match purge_expired_tokens(&pool).await {
+                Ok((0, 0)) => {}
+                Ok((revoked, refresh)) => info!("Purged {} expired token revocations and {} refresh tokens", revoked, refresh),
+                Err(e) => error!("Failed to purge expired tokens: {:?}", e),
+            }
+            match purge_expired_mfa_challenges(&pool).await {
+                Ok(0) => {}
+                Ok(challenges) => info!("Purged {} expired two-factor login challenges", challenges),
+                Err(e) => error!("Failed to purge expired two-factor login challenges: {:?}", e),
+            }
+            let window_start = Utc::now() - Duration::seconds(MFA_FAILURE_WINDOW_SECONDS);
+            if let Err(e) = purge_mfa_failures_before(&pool, window_start).await {
+                error!("Failed to purge old two-factor failures: {:?}", e);
+            }
+            if let Err(e) = purge_expired_mfa_enrollment_tokens(&pool).await {
+                error!("Failed to purge expired two-factor enrollment tokens: {:?}", e);
+            }
+        }
+    });
+}
+
+async fn revoke_access_token(conn: &mut PgConnection, claims: &Claims) -> Result<(), AppError> {
+    let expires_at = DateTime::from_timestamp(claims.exp as i64, 0).unwrap_or_else(Utc::now);
+    insert_revoked_token(conn, &claims.jti, claims.user_id, expires_at).await?;
+    Ok(())
+}
+
+/// A JWT for `user` with their current role, valid for `ACCESS_TOKEN_EXPIRY_SECONDS` or
+/// until `session_id` is revoked.
+fn generate_access_token(user: &User, session_id: i32) -> Result<String, AppError> {
     let claims = Claims {
         sub: user.username.clone(),
         user_id: user.id,
         role: user.role.as_str().to_string(),
-        exp: (chrono::Utc::now() + chrono::Duration::hours(24)).timestamp() as usize,
+        exp: (Utc::now() + Duration::seconds(ACCESS_TOKEN_EXPIRY_SECONDS)).timestamp() as usize,
+        jti: Uuid::new_v4().to_string(),
+        sid: session_id,
     };
-    
-    match generate_token(&claims) {
-        Ok(token) => {
-            info!("Generated token for user: {}", username);
-            Ok(token)
-        },
-        Err(e) => {
-            error!("Failed to generate token for user {}: {:?}", username, e);
-            Err(AppError::InternalServerError("Failed to generate authentication token".to_string()))
+
+    generate_token(&claims).map_err(|e| {
+        error!("Failed to generate token for user {}: {:?}", user.username, e);
+        AppError::InternalServerError("Failed to generate authentication token".to_string())
+    })
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::modules::mfa::models::totp_for;
+    use crate::modules::mfa::repositories::{enable_totp, update_security_settings};
+    use crate::modules::mfa::services::issue_mfa_enrollment_token;
+    use crate::utils::test_db::rolled_back_pool;
+
+    const PASSWORD: &str = "correct horse battery staple";
+
+    fn client() -> ClientInfo {
+        ClientInfo { user_agent: Some("tests".to_string()), ip_address: None }
+    }
+
+    /// A new user. The password is hashed at the lowest cost, to keep the logins quick.
+    async fn new_user(pool: &PgPool, role: Role) -> User {
+        let username = format!("mfa_{}", Uuid::new_v4().simple());
+        let email = format!("{}@example.com", username);
+        let password_hash = bcrypt::hash(PASSWORD, 4).unwrap();
+        insert_user(pool, &username, &email, &password_hash, role.as_str()).await.unwrap()
+    }
+
+    /// A user with an enabled authenticator, and its secret.
+    async fn user_with_totp(pool: &PgPool) -> (User, String) {
+        let user = new_user(pool, Role::USER).await;
+        let username = user.username.clone();
+        let secret = enroll_totp(pool, user.id, &username).await.unwrap().secret;
+        enable_totp(pool, user.id, 0).await.unwrap();
+        (user, secret)
+    }
+
+    async fn mfa_token(pool: &PgPool, user: &User) -> String {
+        match login_user(pool, &user.username, PASSWORD, &client()).await.unwrap() {
+            LoginOutcome::MfaRequired(challenge) => challenge.mfa_token,
+            LoginOutcome::Authenticated(_) => panic!("a second factor is required"),
+        }
+    }
+
+    fn current_code(secret: &str) -> String {
+        totp_for(secret, "").unwrap().generate_current().unwrap()
+    }
+
+    #[actix_web::test]
+    async fn wrong_codes_count_across_logins() {
+        let Some(pool) = rolled_back_pool().await else {
+            return;
+        };
+        let (user, secret) = user_with_totp(&pool).await;
+
+        // Each login takes MAX_MFA_ATTEMPTS wrong codes; logging in again doesn't start over
+        let mut failures = 0;
+        while failures < MAX_MFA_FAILURES {
+            let mfa_token = mfa_token(&pool, &user).await;
+            for _ in 0..MAX_MFA_ATTEMPTS.min((MAX_MFA_FAILURES - failures) as i32) {
+                let result = complete_mfa_login(&pool, &mfa_token, "000-000", &client()).await;
+                assert!(matches!(result, Err(AppError::InvalidCredentials(_))));
+                failures += 1;
+            }
         }
+
+        let mfa_token = mfa_token(&pool, &user).await;
+        let result = complete_mfa_login(&pool, &mfa_token, &current_code(&secret), &client()).await;
+        assert!(matches!(result, Err(AppError::TooManyRequests(_))));
+
+        // Once they are older than the window, codes are checked again
+        sqlx::query("UPDATE mfa_failures SET created_at = created_at - INTERVAL '1 hour' WHERE user_id = $1")
+            .bind(user.id)
+            .execute(&pool)
+            .await
+            .unwrap();
+        let result = complete_mfa_login(&pool, &mfa_token, "000-000", &client()).await;
+        assert!(matches!(result, Err(AppError::InvalidCredentials(_))));
     }
-}
\ No newline at end of file
+
+    #[actix_web::test]
+    async fn enrolling_at_login_takes_a_token_from_another_admin() {
+        let Some(pool) = rolled_back_pool().await else {
+            return;
+        };
+        update_security_settings(&pool, true).await.unwrap();
+        let (issuer, _) = user_with_totp(&pool).await;
+        let admin = new_user(&pool, Role::ADMIN).await;
+        let mfa_token = mfa_token(&pool, &admin).await;
+
+        // The password alone is not enough
+        let result = start_login_totp_enrollment(&pool, &mfa_token, "made-up").await;
+        assert!(matches!(result, Err(AppError::Unauthorized(_))));
+
+        let claims = Claims {
+            sub: issuer.username.clone(),
+            user_id: issuer.id,
+            role: Role::ADMIN.as_str().to_string(),
+            exp: 0,
+            jti: Uuid::new_v4().to_string(),
+            sid: 0,
+        };
+        let token = issue_mfa_enrollment_token(&pool, &claims, admin.id).await.unwrap().enrollment_token;
+        start_login_totp_enrollment(&pool, &mfa_token, &token).await.unwrap();
+
+        // It is used up
+        let result = start_login_totp_enrollment(&pool, &mfa_token, &token).await;
+        assert!(matches!(result, Err(AppError::Unauthorized(_))));
+    }
+}
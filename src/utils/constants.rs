@@ -4,13 +4,47 @@ pub mod api {
 }
 
 pub mod auth {
-    pub const JWT_EXPIRY_SECONDS: i64 = 24 * 3600; // 24 hours
+    pub const ACCESS_TOKEN_EXPIRY_SECONDS: i64 = 15 * 60; // 15 minutes
+    pub const REFRESH_TOKEN_EXPIRY_SECONDS: i64 = 30 * 24 * 3600; // 30 days
+    pub const TOKEN_PURGE_INTERVAL_SECONDS: u64 = 3600; // 1 hour
+    pub const MAX_USER_AGENT_LENGTH: usize = 512;
+    pub const MFA_CHALLENGE_EXPIRY_SECONDS: i64 = 5 * 60; // 5 minutes
+    pub const MAX_MFA_ATTEMPTS: i32 = 5;
+    pub const MAX_MFA_FAILURES: i64 = 10; // per user within the window below
+    pub const MFA_FAILURE_WINDOW_SECONDS: i64 = 15 * 60; // 15 minutes
+    pub const MFA_ENROLLMENT_TOKEN_EXPIRY_SECONDS: i64 = 24 * 3600; // 1 day
+    pub const RECOVERY_CODE_COUNT: usize = 10;
+    pub const TOTP_ISSUER: &str = "Finance Tracker";
 }
 
 pub mod db {
     pub const MAX_USERNAME_LENGTH: usize = 50;
 }
 
+pub mod scheduler {
+    pub const RECURRING_INTERVAL_SECONDS: u64 = 15 * 60; // 15 minutes
+}
+
 pub mod server {
     pub const PORT: u16 = 8080; // Default port
-}
\ No newline at end of file
+}
+
+pub mod uploads {
+    pub const MAX_UPLOAD_BYTES: usize = 5 * 1024 * 1024; // 5 MB
+    pub const MAX_IMPORT_ROWS: usize = 5000;
+    pub const MAX_ATTACHMENT_BYTES: usize = 10 * 1024 * 1024; // 10 MB
+    pub const MAX_ATTACHMENTS_PER_TRANSACTION: i64 = 10;
+    pub const DEFAULT_ATTACHMENT_DIR: &str = "attachments";
+}
+
+pub mod matching {
+    /// Transactions dated at most this many days apart can be duplicates of each other.
+    pub const DUPLICATE_WINDOW_DAYS: i32 = 3;
+    /// Share of the shorter description's words the other must contain as well.
+    pub const DUPLICATE_MIN_WORD_OVERLAP: f64 = 0.5;
+}
+
+pub mod labels {
+    pub const MAX_TAG_LENGTH: usize = 50;
+    pub const MAX_TAGS_PER_TRANSACTION: usize = 20;
+}
@@ -1,9 +1,6 @@
 use actix_web::{http::StatusCode, HttpResponse, ResponseError};
 use thiserror::Error;
-use serde::{Deserialize, Serialize};
-use sqlx::postgres::PgDatabaseError;
-use validator::ValidationErrors;
-use std::fmt;
+use validator::{ValidationError, ValidationErrors};
 use log::{error, warn, info};
 use bcrypt::BcryptError;
 
@@ -25,6 +22,24 @@ pub enum AppError {
     Forbidden(String),
     #[error("Resource already exists: {0}")]
     ResourceExists(String),
+    #[error("Not found: {0}")]
+    NotFound(String),
+    #[error("Conflict: {0}")]
+    Conflict(String),
+    #[error("Too many requests: {0}")]
+    TooManyRequests(String),
+}
+
+impl AppError {
+    /// A validation error for a single field, for checks that can't be expressed
+    /// as `validator` attributes on the request.
+    pub fn invalid_field(field: &'static str, code: &'static str, message: &str) -> Self {
+        let mut error = ValidationError::new(code);
+        error.message = Some(message.to_string().into());
+        let mut errors = ValidationErrors::new();
+        errors.add(field, error);
+        AppError::Validation(errors)
+    }
 }
 
 impl From<ValidationErrors> for AppError {
@@ -57,24 +72,30 @@ impl ResponseError for AppError {
         match self {
             AppError::Database(e) => {
                 error!("Database error: {:?}", e);
-                if let sqlx::Error::Database(db_err) = e {
-                    if let Some(code) = db_err.code() {
-                        if code == "23505" {
-                            let message = if db_err.message().contains("users_username_key") {
-                                "Username is already taken"
-                            } else if db_err.message().contains("users_email_key") {
-                                "Email is already taken"
-                            } else {
-                                "Resource already exists"
-                            };
-                            warn!("Database constraint violation: {}", message);
-                            return HttpResponse::Conflict().json(GenericResponse {
-                                status: StatusCode::CONFLICT.as_u16(),
-                                data: None::<()>,
-                                message: message.to_string(),
-                            });
-                        }
-                    }
+                if let sqlx::Error::Database(db_err) = e
+                    && db_err.code().as_deref() == Some("23505")
+                {
+                    let message = if db_err.message().contains("users_username_key") {
+                        "Username is already taken"
+                    } else if db_err.message().contains("users_email_key") {
+                        "Email is already taken"
+                    } else if db_err.message().contains("accounts_user_id_name_key") {
+                        "An account with this name already exists"
+                    } else if db_err.message().contains("categories_user_id_kind_parent_id_name_key") {
+                        "A category with this name already exists"
+                    } else if db_err.message().contains("tags_user_id_name_key") {
+                        "A tag with this name already exists"
+                    } else if db_err.message().contains("transactions_account_id_external_id_key") {
+                        "This transaction has already been imported"
+                    } else {
+                        "Resource already exists"
+                    };
+                    warn!("Database constraint violation: {}", message);
+                    return HttpResponse::Conflict().json(GenericResponse {
+                        status: StatusCode::CONFLICT.as_u16(),
+                        data: None::<()>,
+                        message: message.to_string(),
+                    });
                 }
                 HttpResponse::InternalServerError().json(GenericResponse {
                     status: StatusCode::INTERNAL_SERVER_ERROR.as_u16(),
@@ -130,6 +151,30 @@ impl ResponseError for AppError {
                     message: msg.clone(),
                 })
             },
+            AppError::NotFound(msg) => {
+                info!("Not found: {}", msg);
+                HttpResponse::NotFound().json(GenericResponse {
+                    status: StatusCode::NOT_FOUND.as_u16(),
+                    data: None::<()>,
+                    message: msg.clone(),
+                })
+            },
+            AppError::Conflict(msg) => {
+                info!("Conflict: {}", msg);
+                HttpResponse::Conflict().json(GenericResponse {
+                    status: StatusCode::CONFLICT.as_u16(),
+                    data: None::<()>,
+                    message: msg.clone(),
+                })
+            },
+            AppError::TooManyRequests(msg) => {
+                warn!("Too many requests: {}", msg);
+                HttpResponse::TooManyRequests().json(GenericResponse {
+                    status: StatusCode::TOO_MANY_REQUESTS.as_u16(),
+                    data: None::<()>,
+                    message: msg.clone(),
+                })
+            },
         }
     }
 }
\ No newline at end of file
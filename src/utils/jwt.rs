@@ -1,6 +1,9 @@
 use jsonwebtoken::{encode, decode, DecodingKey, EncodingKey, Header, Validation};
 use serde::{Serialize, Deserialize};
 use std::env;
+use std::str::FromStr;
+
+use crate::utils::roles::Role;
 
 #[derive(Serialize, Deserialize, Clone)]
 pub struct Claims {
@@ -8,6 +11,16 @@ pub struct Claims {
     pub user_id: i32,
     pub role: String,
     pub exp: usize,
+    /// Unique id of the token, under which it can be revoked.
+    pub jti: String,
+    /// The login session the token was issued for.
+    pub sid: i32,
+}
+
+impl Claims {
+    pub fn is_admin(&self) -> bool {
+        Role::from_str(&self.role).is_ok_and(|role| role == Role::ADMIN)
+    }
 }
 
 pub fn generate_token(claims: &Claims) -> Result<String, jsonwebtoken::errors::Error> {
@@ -5,6 +5,11 @@ pub mod jwt;
 pub mod roles;
 pub mod constants;
 pub mod not_found;
+pub mod money;
+pub mod pagination;
+pub mod tokens;
+#[cfg(test)]
+pub mod test_db;
 
 pub use response::*;
 pub use errors::*;
@@ -12,4 +17,7 @@ pub use bcrypt::*;
 pub use jwt::*;
 pub use roles::*;
 pub use constants::*;
-pub use not_found::*;
\ No newline at end of file
+pub use not_found::*;
+pub use money::*;
+pub use pagination::*;
+pub use tokens::*;
\ No newline at end of file
@@ -0,0 +1,82 @@
+use bigdecimal::BigDecimal;
+use serde::de::{self, Deserialize, Deserializer, Visitor};
+use serde::Serializer;
+use std::fmt;
+use std::str::FromStr;
+
+/// Decimal places money is stored with, matching the `DECIMAL(12,2)` columns.
+pub const MONEY_SCALE: i64 = 2;
+
+/// Returns true when the amount has no more decimal places than a stored amount can hold.
+pub fn has_money_scale(amount: &BigDecimal) -> bool {
+    let (_, scale) = amount.normalized().as_bigint_and_exponent();
+    scale <= MONEY_SCALE
+}
+
+/// Serializes an amount as a string with exactly two decimal places. Values read back
+/// from Postgres can carry a wider scale than the column's, e.g. `12.3400`.
+pub fn serialize_amount<S>(amount: &BigDecimal, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    serializer.collect_str(&format_args!("{:.2}", amount))
+}
+
+pub fn serialize_optional_amount<S>(amount: &Option<BigDecimal>, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    match amount {
+        Some(amount) => serialize_amount(amount, serializer),
+        None => serializer.serialize_none(),
+    }
+}
+
+/// Deserializes an amount sent either as a string (`"12.34"`) or a number (`12.34`).
+/// Numbers are read from their shortest decimal form, so `0.1` stays exactly `0.1`
+/// rather than becoming the nearest binary fraction.
+pub fn deserialize_amount<'de, D>(deserializer: D) -> Result<BigDecimal, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    deserializer.deserialize_any(AmountVisitor)
+}
+
+pub fn deserialize_optional_amount<'de, D>(deserializer: D) -> Result<Option<BigDecimal>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(serde::Deserialize)]
+    struct Amount(#[serde(deserialize_with = "deserialize_amount")] BigDecimal);
+
+    Ok(Option::<Amount>::deserialize(deserializer)?.map(|Amount(amount)| amount))
+}
+
+struct AmountVisitor;
+
+impl Visitor<'_> for AmountVisitor {
+    type Value = BigDecimal;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a decimal amount as a string or number")
+    }
+
+    fn visit_str<E: de::Error>(self, value: &str) -> Result<BigDecimal, E> {
+        BigDecimal::from_str(value.trim()).map_err(|_| E::custom(format!("invalid amount: {}", value)))
+    }
+
+    fn visit_i64<E: de::Error>(self, value: i64) -> Result<BigDecimal, E> {
+        Ok(BigDecimal::from(value))
+    }
+
+    fn visit_u64<E: de::Error>(self, value: u64) -> Result<BigDecimal, E> {
+        Ok(BigDecimal::from(value))
+    }
+
+    fn visit_f64<E: de::Error>(self, value: f64) -> Result<BigDecimal, E> {
+        if !value.is_finite() {
+            return Err(E::custom("amount must be a finite number"));
+        }
+        self.visit_str(&value.to_string())
+    }
+}
@@ -0,0 +1,82 @@
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use serde::{Deserialize, Serialize};
+
+use crate::utils::errors::AppError;
+
+pub const DEFAULT_PAGE_LIMIT: i64 = 50;
+pub const MAX_PAGE_LIMIT: i64 = 200;
+
+#[derive(Debug, Clone, Copy, PartialEq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SortDirection {
+    Asc,
+    #[default]
+    Desc,
+}
+
+impl SortDirection {
+    pub fn as_sql(&self) -> &'static str {
+        match self {
+            SortDirection::Asc => "ASC",
+            SortDirection::Desc => "DESC",
+        }
+    }
+
+    /// Comparison that selects the rows after a cursor in this direction.
+    pub fn after_operator(&self) -> &'static str {
+        match self {
+            SortDirection::Asc => ">",
+            SortDirection::Desc => "<",
+        }
+    }
+}
+
+/// One page of a keyset-paginated listing.
+#[derive(Serialize)]
+pub struct Paginated<T: Serialize> {
+    pub items: Vec<T>,
+    pub next_cursor: Option<String>,
+    pub total_count: i64,
+    pub limit: i64,
+}
+
+/// A position in a listing: the sort key and id of the last row already returned.
+/// `sort` records which ordering the cursor belongs to so it cannot be replayed
+/// against a different one.
+pub struct Cursor {
+    pub sort: String,
+    pub value: String,
+    pub id: i32,
+}
+
+impl Cursor {
+    pub fn encode(&self) -> String {
+        URL_SAFE_NO_PAD.encode(format!("{}|{}|{}", self.sort, self.value, self.id))
+    }
+
+    pub fn decode(cursor: &str) -> Result<Self, AppError> {
+        let raw = URL_SAFE_NO_PAD
+            .decode(cursor)
+            .ok()
+            .and_then(|bytes| String::from_utf8(bytes).ok())
+            .ok_or_else(invalid_cursor)?;
+
+        let mut parts = raw.splitn(3, '|');
+        let sort = parts.next().ok_or_else(invalid_cursor)?;
+        let value = parts.next().ok_or_else(invalid_cursor)?;
+        let id = parts
+            .next()
+            .and_then(|id| id.parse::<i32>().ok())
+            .ok_or_else(invalid_cursor)?;
+
+        Ok(Cursor {
+            sort: sort.to_string(),
+            value: value.to_string(),
+            id,
+        })
+    }
+}
+
+pub fn invalid_cursor() -> AppError {
+    AppError::invalid_field("cursor", "cursor", "Invalid or expired cursor")
+}
@@ -1,6 +1,7 @@
 use serde::{Deserialize, Serialize};
 use std::fmt;
-use sqlx::{Type, Decode, Postgres, postgres::{PgHasArrayType, PgTypeInfo}};
+use std::str::FromStr;
+use sqlx::Type;
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Type)]
 #[serde(rename_all = "UPPERCASE")]
@@ -17,8 +18,12 @@ impl Role {
             Role::ADMIN => "ADMIN",
         }
     }
+}
+
+impl FromStr for Role {
+    type Err = String;
 
-    pub fn from_str(s: &str) -> Result<Self, String> {
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
         match s.to_uppercase().as_str() {
             "USER" => Ok(Role::USER),
             "ADMIN" => Ok(Role::ADMIN),
@@ -0,0 +1,25 @@
+use sqlx::postgres::PgPoolOptions;
+use sqlx::{Connection, PgPool};
+
+/// A single-connection pool on `DATABASE_URL` whose connection stays inside a transaction
+/// that is never committed. The code under test's own transactions become savepoints, and
+/// everything a test writes is rolled back when the connection closes, even if the test
+/// fails. `None`, so the test is skipped, when `DATABASE_URL` is not set.
+pub async fn rolled_back_pool() -> Option<PgPool> {
+    let Ok(url) = std::env::var("DATABASE_URL") else {
+        eprintln!("DATABASE_URL is not set; skipping a test that needs a migrated database");
+        return None;
+    };
+    let pool = PgPoolOptions::new()
+        .max_connections(1)
+        .after_connect(|conn, _| {
+            Box::pin(async move {
+                std::mem::forget(conn.begin().await?);
+                Ok(())
+            })
+        })
+        .connect(&url)
+        .await
+        .unwrap();
+    Some(pool)
+}
@@ -0,0 +1,13 @@
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use sha2::{Digest, Sha256};
+
+/// A random, URL-safe token carrying 256 bits of entropy, for secrets handed to clients.
+pub fn generate_opaque_token() -> String {
+    URL_SAFE_NO_PAD.encode(rand::random::<[u8; 32]>())
+}
+
+/// The hex-encoded SHA-256 hash under which an opaque token is stored. Tokens are random
+/// enough that no salt or slow hash is needed.
+pub fn hash_token(token: &str) -> String {
+    hex::encode(Sha256::digest(token.as_bytes()))
+}